@@ -1,5 +1,9 @@
 use super::api_model::*;
-use crate::character_model::{Ability, AttackAction, AttackType, CharacterAsi};
+use crate::character_model::{
+    Ability, AttackAction, AttackType, CharacterAsi, WeaponProficiency,
+    WeaponProperty,
+};
+use crate::dice::roll_mode::{RollMode, RollModeEffect, RollTarget};
 use lazy_regex::{regex, regex_captures};
 use leptos::leptos_dom::log;
 use serde::{Deserialize, Serialize};
@@ -31,6 +35,101 @@ impl Feature {
             hidden: true,
         }
     }
+    pub fn new_weapon_proficiency(
+        prof: WeaponProficiency,
+        desc: &str,
+        source_slug: &str,
+    ) -> Self {
+        Self {
+            name: format!("{}", prof),
+            desc: desc.to_string(),
+            level: 1,
+            feature_type: FeatureType::WeaponProficiency(prof),
+            source_slug: source_slug.to_string(),
+            hidden: true,
+        }
+    }
+}
+
+/// Species traits such as "Dwarven Combat Training" or "Elf Weapon
+/// Training" grant proficiency with a list of weapons. Open5e only
+/// provides these as text, so pull the weapon list out of the
+/// description and turn it into hidden proficiency features.
+fn weapon_training_features(features: &[Feature]) -> Vec<Feature> {
+    let pattern = regex!(r"You have proficiency with (?:the )?([^.]+)\.");
+    features
+        .iter()
+        .filter(|f| f.name.contains("Training"))
+        .flat_map(|f| {
+            let list = pattern
+                .captures(&f.desc)
+                .and_then(|c| c.get(1))
+                .map(|m| m.as_str())
+                .unwrap_or_default();
+            WeaponProficiency::parse_list(list)
+                .into_iter()
+                .map(|prof| {
+                    Feature::new_weapon_proficiency(
+                        prof,
+                        &f.desc,
+                        &f.source_slug,
+                    )
+                })
+                .collect::<Vec<Feature>>()
+        })
+        .collect()
+}
+
+/// Traits like "Dwarven Resilience" or "Brave" give advantage on saves
+/// against certain things. Turn those into hidden roll mode features,
+/// so they can be shown as reminders when rolling saves.
+fn save_advantage_features(features: &[Feature]) -> Vec<Feature> {
+    // Only ability names may come before "saving throws", and the
+    // situation ends with its clause, so that "against poison, and
+    // you have resistance..." is just "against poison"
+    let pattern = regex!(
+        r"You have (advantage|disadvantage) on ((?:(?:all|and|Strength|Dexterity|Constitution|Intelligence|Wisdom|Charisma),? )*)saving throws against ([^.,;]+)"
+    );
+    let mut out = vec![];
+    for f in features {
+        let Some(captures) = pattern.captures(&f.desc) else {
+            continue;
+        };
+        let mode = if &captures[1] == "advantage" {
+            RollMode::Advantage
+        } else {
+            RollMode::Disadvantage
+        };
+        // Saves can be limited to specific abilities, i.e. "all
+        // Intelligence, Wisdom, and Charisma saving throws"
+        let targets = captures[2]
+            .replace(" and ", ",")
+            .split([',', ' '])
+            .filter_map(Ability::from_string)
+            .map(RollTarget::Save)
+            .collect::<Vec<RollTarget>>();
+        let targets = if targets.is_empty() {
+            vec![RollTarget::AllSaves]
+        } else {
+            targets
+        };
+        for target in targets {
+            out.push(Feature {
+                name: f.name.to_string(),
+                desc: f.desc.to_string(),
+                level: f.level.max(1),
+                feature_type: FeatureType::RollMode(RollModeEffect {
+                    mode,
+                    target,
+                    situation: Some(format!("against {}", captures[3].trim())),
+                    source: f.name.to_string(),
+                }),
+                source_slug: f.source_slug.to_string(),
+                hidden: true,
+            });
+        }
+    }
+    out
 }
 
 #[derive(Serialize, Deserialize, Default, Clone, PartialEq)]
@@ -39,6 +138,8 @@ pub enum FeatureType {
     SavingThrow(Ability),
     SkillProficency(String),
     OtherProficency(String),
+    WeaponProficiency(WeaponProficiency),
+    RollMode(RollModeEffect),
     Option(FeatureOptions),
     Fluff,
     #[default]
@@ -53,7 +154,7 @@ pub struct FeatureOptions {
 /// A selection of an item from within a FeatureOptions.
 /// Since FeatureOptions can allow for multiple choices,
 /// you can have multiple of these per FeatureOptions.
-#[derive(Serialize, Deserialize, PartialEq)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct FeatureOptionsSelection {
     /// Each feature option belongs to a Feature, which has a feature slug
     /// This string represents that slug.
@@ -115,6 +216,10 @@ impl Species {
         if !current_feature.name.is_empty() {
             features.push(current_feature);
         }
+        let mut weapon_profs = weapon_training_features(&features);
+        let mut save_advantages = save_advantage_features(&features);
+        features.append(&mut weapon_profs);
+        features.append(&mut save_advantages);
         features
     }
 }
@@ -166,6 +271,10 @@ impl Subspecies {
         if !current_feature.name.is_empty() {
             features.push(current_feature);
         }
+        let mut weapon_profs = weapon_training_features(&features);
+        let mut save_advantages = save_advantage_features(&features);
+        features.append(&mut weapon_profs);
+        features.append(&mut save_advantages);
         features
     }
 }
@@ -174,11 +283,7 @@ impl Class {
     pub fn base_hp(&self) -> i32 {
         let mut split = self.hp_at_1st_level.split(' ');
         if let Some(word) = split.next() {
-            if let Ok(num) = str::parse::<i32>(word) {
-                num
-            } else {
-                0
-            }
+            str::parse::<i32>(word).unwrap_or_default()
         } else {
             0
         }
@@ -348,7 +453,16 @@ impl Class {
             }
         }
 
-        features.sort_by(|a, b| a.level.cmp(&b.level));
+        // Add weapon proficencies
+        for prof in WeaponProficiency::parse_list(&self.prof_weapons) {
+            features.push(Feature::new_weapon_proficiency(
+                prof,
+                &self.prof_weapons,
+                &source_slug,
+            ));
+        }
+
+        features.sort_by_key(|f| f.level);
         features
     }
 }
@@ -521,45 +635,127 @@ impl Background {
 }
 #[allow(dead_code)]
 impl Weapon {
-    pub fn is_finesse(&self) -> bool {
+    /// Parse the raw Open5e property strings into typed properties.
+    pub fn parse_properties(&self) -> Vec<WeaponProperty> {
         match &self.properties {
-            Some(p) => p.contains(&String::from("finesse")),
-            None => false,
+            Some(p) => {
+                p.iter().map(|s| WeaponProperty::from_string(s)).collect()
+            }
+            None => vec![],
         }
     }
+    fn has_property(&self, property: WeaponProperty) -> bool {
+        self.parse_properties().contains(&property)
+    }
+    pub fn is_finesse(&self) -> bool {
+        self.has_property(WeaponProperty::Finesse)
+    }
     pub fn is_light(&self) -> bool {
-        match &self.properties {
-            Some(p) => p.contains(&String::from("light")),
-            None => false,
-        }
+        self.has_property(WeaponProperty::Light)
+    }
+    pub fn is_heavy(&self) -> bool {
+        self.has_property(WeaponProperty::Heavy)
     }
     pub fn is_reach(&self) -> bool {
-        match &self.properties {
-            Some(p) => p.contains(&String::from("reach")),
-            None => false,
-        }
+        self.has_property(WeaponProperty::Reach)
+    }
+    pub fn is_two_handed(&self) -> bool {
+        self.has_property(WeaponProperty::TwoHanded)
+    }
+    pub fn is_loading(&self) -> bool {
+        self.has_property(WeaponProperty::Loading)
+    }
+    pub fn is_thrown(&self) -> bool {
+        self.parse_properties()
+            .iter()
+            .any(|p| matches!(p, WeaponProperty::Thrown(_)))
     }
     pub fn is_ranged(&self) -> bool {
         self.category.contains(&String::from("Ranged"))
     }
+    pub fn attack_type(&self) -> AttackType {
+        if self.is_ranged() {
+            AttackType::Ranged
+        } else if self.is_thrown() {
+            AttackType::MeleeOrRanged
+        } else {
+            AttackType::Melee
+        }
+    }
     pub fn to_attack(&self) -> AttackAction {
         AttackAction {
             name: self.name.to_string(),
             slug: self.name.to_string(),
-            ability: if self.is_finesse() {
+            // Finesse weapons pick the better of Strength and
+            // Dexterity when the attack is made.
+            ability: if self.is_ranged() {
                 Ability::Dexterity
             } else {
                 Ability::Strength
             },
             damage_base: self.damage_dice.to_string(),
-            proficient: false,
-            attack_type: if self.is_ranged() {
-                AttackType::Ranged
-            } else {
-                AttackType::Melee
-            },
+            weapon: self.name.to_string(),
+            category: self.category.to_string(),
+            attack_type: self.attack_type(),
             reach: if self.is_reach() { 10 } else { 5 },
             damage_type: self.damage_type.to_string(),
+            properties: self.parse_properties(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trait_feature(name: &str, desc: &str) -> Feature {
+        Feature {
+            name: name.to_string(),
+            desc: desc.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn effects(desc: &str) -> Vec<RollModeEffect> {
+        save_advantage_features(&[trait_feature("Trait", desc)])
+            .into_iter()
+            .filter_map(|f| match f.feature_type {
+                FeatureType::RollMode(effect) => Some(effect),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_save_advantage() {
+        let resilience = effects(
+            "You have advantage on saving throws against poison, and you \
+            have resistance against poison damage.",
+        );
+        assert_eq!(resilience.len(), 1);
+        assert_eq!(resilience[0].target, RollTarget::AllSaves);
+        assert_eq!(resilience[0].situation.as_deref(), Some("against poison"));
+
+        let cunning = effects(
+            "You have advantage on all Intelligence, Wisdom, and Charisma \
+            saving throws against magic.",
+        );
+        assert_eq!(
+            cunning.iter().map(|e| e.target.clone()).collect::<Vec<_>>(),
+            vec![
+                RollTarget::Save(Ability::Intelligence),
+                RollTarget::Save(Ability::Wisdom),
+                RollTarget::Save(Ability::Charisma),
+            ]
+        );
+        assert_eq!(cunning[0].situation.as_deref(), Some("against magic"));
+
+        // Advantage on something else, with saves mentioned later on,
+        // isn't advantage on saves
+        assert!(effects(
+            "You have advantage on initiative rolls. Creatures make \
+            saving throws against your spells."
+        )
+        .is_empty());
+    }
+}
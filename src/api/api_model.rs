@@ -1,6 +1,5 @@
 use serde::Deserialize;
 use serde::Serialize;
-use serde_json::Value;
 
 use crate::character_model::Ability;
 use crate::character_model::CharacterAsi;
@@ -9,8 +8,9 @@ use crate::character_model::CharacterAsi;
 #[serde(rename_all = "camelCase")]
 pub struct SpeciesAPI {
     pub count: i32,
-    pub next: Value,
-    pub previous: Value,
+    /// Address of the page after this one, or `None` on the last.
+    pub next: Option<String>,
+    pub previous: Option<String>,
     pub results: Vec<Species>,
 }
 
@@ -93,8 +93,9 @@ pub struct Subspecies {
 #[serde(rename_all = "camelCase")]
 pub struct ClassesAPI {
     pub count: i32,
-    pub next: Value,
-    pub previous: Value,
+    /// Address of the page after this one, or `None` on the last.
+    pub next: Option<String>,
+    pub previous: Option<String>,
     pub results: Vec<Class>,
 }
 
@@ -157,8 +158,9 @@ pub struct Archetype {
 #[serde(rename_all = "camelCase")]
 pub struct BackgroundsAPI {
     pub count: i32,
-    pub next: Value,
-    pub previous: Value,
+    /// Address of the page after this one, or `None` on the last.
+    pub next: Option<String>,
+    pub previous: Option<String>,
     pub results: Vec<Background>,
 }
 
@@ -193,8 +195,9 @@ pub struct Background {
 #[serde(rename_all = "camelCase")]
 pub struct WeaponApi {
     pub count: i64,
-    pub next: String,
-    pub previous: Value,
+    /// Address of the page after this one, or `None` on the last.
+    pub next: Option<String>,
+    pub previous: Option<String>,
     pub results: Vec<Weapon>,
 }
 
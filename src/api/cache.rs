@@ -0,0 +1,196 @@
+//! Open5e's data kept in browser storage, a page at a time, so the
+//! app loads without waiting for Open5e and still works offline.
+//! The cache is kept in `cache_backend()`, apart from characters, and
+//! is limited in size, with the longest-cached pages removed first.
+
+use serde::{Deserialize, Serialize};
+
+use super::Page;
+use crate::storage::StorageBackend;
+
+/// How long cached data is used before asking Open5e for it again, in
+/// seconds. Open5e's lists rarely change. When it is asked, the
+/// browser revalidates its own copy with the ETag Open5e sent, so an
+/// unchanged page isn't downloaded again; see `fetch_page`.
+pub const CACHE_TTL: u64 = 24 * 60 * 60;
+
+const CACHE_KEY_PREFIX: &str = "open5e_cache:";
+
+/// The most the cache holds, in bytes of JSON. Open5e's lists come to
+/// well under this; it only stops the cache growing without end if
+/// the addresses they're fetched from change.
+const CACHE_LIMIT: usize = 8 * 1024 * 1024;
+
+/// One page of a list, as Open5e sent it.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct CachedPage {
+    /// When Open5e was last asked for the page, in seconds since the
+    /// epoch.
+    pub fetched_at: u64,
+    pub body: String,
+}
+
+/// A whole list from the cache.
+#[derive(Clone, PartialEq, Debug)]
+pub struct CachedList<T> {
+    pub results: Vec<T>,
+    /// When the oldest page in the list was fetched.
+    pub fetched_at: u64,
+}
+
+impl<T> CachedList<T> {
+    pub fn is_stale(&self, now: u64) -> bool {
+        now.saturating_sub(self.fetched_at) >= CACHE_TTL
+    }
+}
+
+/// Seconds since the epoch.
+pub fn now() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
+
+fn cache_key(url: &str) -> String {
+    format!("{}{}", CACHE_KEY_PREFIX, url)
+}
+
+pub fn load_page(
+    storage: &dyn StorageBackend,
+    url: &str,
+) -> Option<CachedPage> {
+    serde_json::from_str(&storage.get(&cache_key(url))?).ok()
+}
+
+pub fn write_page(storage: &dyn StorageBackend, url: &str, page: &CachedPage) {
+    let Ok(json) = serde_json::to_string(page) else {
+        return;
+    };
+    let key = cache_key(url);
+    make_room(storage, &key, json.len());
+    // Running out of room only means fetching it again next time
+    let _ = storage.set(&key, &json);
+}
+
+/// Remove the longest-cached pages, other than the one at `key`, until
+/// there's room for `size` more bytes within `CACHE_LIMIT`.
+fn make_room(storage: &dyn StorageBackend, key: &str, size: usize) {
+    let mut pages = storage
+        .keys()
+        .into_iter()
+        .filter(|k| k.starts_with(CACHE_KEY_PREFIX) && k != key)
+        .filter_map(|k| {
+            let json = storage.get(&k)?;
+            // A page that can't be read is no use, so goes first
+            let fetched_at = serde_json::from_str::<CachedPage>(&json)
+                .map_or(0, |page| page.fetched_at);
+            Some((fetched_at, k, json.len()))
+        })
+        .collect::<Vec<_>>();
+    pages.sort();
+    let mut used = size + pages.iter().map(|(_, _, len)| len).sum::<usize>();
+    for (_, k, len) in pages {
+        if used <= CACHE_LIMIT {
+            break;
+        }
+        storage.remove(&k);
+        used -= len;
+    }
+}
+
+/// Remove every cached page from `storage`.
+pub fn remove_pages(storage: &dyn StorageBackend) {
+    for key in storage.keys() {
+        if key.starts_with(CACHE_KEY_PREFIX) {
+            storage.remove(&key);
+        }
+    }
+}
+
+/// Every page of the list starting at `url`, if they're all cached.
+pub fn load_list<P: Page>(
+    storage: &dyn StorageBackend,
+    url: &str,
+) -> Option<CachedList<P::Item>> {
+    let mut list = CachedList {
+        results: vec![],
+        fetched_at: u64::MAX,
+    };
+    let mut next = Some(url.to_string());
+    while let Some(url) = next.take() {
+        let cached = load_page(storage, &url)?;
+        let page = serde_json::from_str::<P>(&cached.body).ok()?;
+        list.fetched_at = list.fetched_at.min(cached.fetched_at);
+        next = page.next().filter(|n| *n != url).map(str::to_string);
+        list.results.extend(page.into_results());
+    }
+    Some(list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::api_model::{Weapon, WeaponApi};
+    use crate::storage::MemoryStorage;
+
+    const FIRST: &str = "https://api.open5e.com/v1/weapons/";
+    const SECOND: &str = "https://api.open5e.com/v1/weapons/?page=2";
+
+    fn page(name: &str, next: Option<&str>, fetched_at: u64) -> CachedPage {
+        CachedPage {
+            fetched_at,
+            body: serde_json::json!({
+                "count": 2,
+                "next": next,
+                "previous": null,
+                "results": [Weapon {
+                    name: name.to_string(),
+                    ..Default::default()
+                }],
+            })
+            .to_string(),
+        }
+    }
+
+    #[test]
+    fn loads_every_cached_page() {
+        let storage = MemoryStorage::default();
+        write_page(&storage, FIRST, &page("Club", Some(SECOND), 1000));
+        // Only part of the list is cached
+        assert!(load_list::<WeaponApi>(&storage, FIRST).is_none());
+
+        write_page(&storage, SECOND, &page("Dagger", None, 500));
+        let list = load_list::<WeaponApi>(&storage, FIRST).unwrap();
+        assert_eq!(
+            list.results.iter().map(|w| &w.name).collect::<Vec<_>>(),
+            vec!["Club", "Dagger"]
+        );
+        assert_eq!(list.fetched_at, 500);
+        assert!(!list.is_stale(500 + CACHE_TTL - 1));
+        assert!(list.is_stale(500 + CACHE_TTL));
+        assert_eq!(load_page(&storage, SECOND).unwrap().fetched_at, 500);
+    }
+
+    #[test]
+    fn removes_oldest_pages_when_full() {
+        let storage = MemoryStorage::default();
+        storage.set("character", "{}").unwrap();
+        let big = |fetched_at| CachedPage {
+            fetched_at,
+            body: "x".repeat(CACHE_LIMIT / 4),
+        };
+        write_page(&storage, FIRST, &big(3));
+        write_page(&storage, SECOND, &big(1));
+        write_page(&storage, "third", &big(2));
+        // Writing the first again only replaces it
+        write_page(&storage, FIRST, &big(4));
+        assert!(load_page(&storage, SECOND).is_some());
+
+        write_page(&storage, "fourth", &big(5));
+        assert!(load_page(&storage, SECOND).is_none());
+        for url in [FIRST, "third", "fourth"] {
+            assert!(load_page(&storage, url).is_some());
+        }
+
+        remove_pages(&storage);
+        assert_eq!(storage.keys(), vec!["character"]);
+    }
+}
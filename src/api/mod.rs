@@ -2,123 +2,383 @@ use self::api_model::{
     Background, BackgroundsAPI, Class, ClassesAPI, Species, SpeciesAPI, Weapon,
     WeaponApi,
 };
+use self::cache::{load_list, load_page, now, write_page, CachedPage};
+use crate::storage::{backend, cache_backend};
 use leptos::leptos_dom::log;
-use leptos::{create_local_resource, Resource};
+use leptos::{
+    create_effect, create_local_resource, create_rw_signal, spawn_local,
+    store_value, untrack, Resource, RwSignal, Signal, SignalGet,
+    SignalGetUntracked, SignalSet, SignalUpdate, SignalWith,
+};
+use serde::de::DeserializeOwned;
 
 pub mod api_extensions;
 pub mod api_model;
+pub mod cache;
 
+/// The data the app is built on: classes, species, backgrounds and
+/// weapons, either from the SRD bundled with the app or from Open5e.
 #[derive(Clone, Copy)]
 pub struct FuturesWrapper {
-    pub classes: Resource<(), Vec<Class>>,
-    pub species: Resource<(), Vec<Species>>,
-    pub backgrounds: Resource<(), Vec<Background>>,
-    pub weapons: Resource<(), Vec<Weapon>>,
+    pub classes: Resource<Option<String>, Vec<Class>>,
+    pub species: Resource<Option<String>, Vec<Species>>,
+    pub backgrounds: Resource<Option<String>, Vec<Background>>,
+    pub weapons: Resource<Option<String>, Vec<Weapon>>,
+    /// If Open5e couldn't be reached and cached data is being used
+    /// instead, when the oldest of it was fetched.
+    pub offline_cache_date: RwSignal<Option<u64>>,
 }
 impl FuturesWrapper {
-    pub fn new() -> Self {
+    /// `open5e_url` is the Open5e instance to fetch from, or `None` to
+    /// use the bundled SRD. The lists are loaded again when it changes.
+    pub fn new(open5e_url: Signal<Option<String>>) -> Self {
+        // Pages used to be cached alongside characters, so give the
+        // room they took back
+        cache::remove_pages(&backend());
+        let offline_cache_date = create_rw_signal(None);
+        // A date from one source says nothing about the next
+        create_effect(move |_| {
+            open5e_url.track();
+            offline_cache_date.set(None);
+        });
         Self {
-            classes: create_local_resource(|| (), fetch_classes),
-            species: create_local_resource(|| (), fetch_species),
-            backgrounds: create_local_resource(|| (), fetch_backgrounds),
-            weapons: create_local_resource(|| (), fetch_weapons),
+            classes: list_resource::<ClassesAPI>(
+                open5e_url,
+                "/v1/classes/",
+                include_str!("srd/classes.json"),
+                "class",
+                |_| true,
+                offline_cache_date,
+            ),
+            species: list_resource::<SpeciesAPI>(
+                open5e_url,
+                "/v1/races/",
+                include_str!("srd/species.json"),
+                "species",
+                |_| true,
+                offline_cache_date,
+            ),
+            // A5e Backgrounds are harder to parse, so exclude them for now
+            backgrounds: list_resource::<BackgroundsAPI>(
+                open5e_url,
+                "/v1/backgrounds/?document_slug__not_in=a5e",
+                include_str!("srd/backgrounds.json"),
+                "background",
+                |b| b.document_slug != "a5e",
+                offline_cache_date,
+            ),
+            weapons: list_resource::<WeaponApi>(
+                open5e_url,
+                "/v1/weapons/",
+                include_str!("srd/weapons.json"),
+                "weapon",
+                |_| true,
+                offline_cache_date,
+            ),
+            offline_cache_date,
         }
     }
 }
 
-/// Fetch list of species options from Open5e
-pub async fn fetch_species(_: ()) -> Vec<Species> {
-    let res = reqwest::get("https://api.open5e.com/v1/races/").await;
-    match res {
-        Ok(response) => match response.json::<SpeciesAPI>().await {
-            Ok(api) => api.results,
-            Err(e) => {
-                log!("Could not deserialize data from Open5e to the SpeciesAPI struct!");
-                log!("{}", e);
-                vec![]
-            }
+/// A list from the bundled SRD, or from the Open5e instance at
+/// `open5e_url` when there is one. `path` is where the list is on
+/// Open5e, and `srd` the bundled copy of it.
+///
+/// Lists from Open5e are served from the cache straight away if
+/// they're all there. A stale cache is checked with Open5e in the
+/// background, and the list updated if it's changed. `name` is what
+/// the list is of, i.e. "class", for logging, and `keep` picks which
+/// of its items the app uses.
+fn list_resource<P: Page>(
+    open5e_url: Signal<Option<String>>,
+    path: &'static str,
+    srd: &'static str,
+    name: &'static str,
+    keep: fn(&P::Item) -> bool,
+    offline_cache_date: RwSignal<Option<u64>>,
+) -> Resource<Option<String>, Vec<P::Item>>
+where
+    P::Item: Clone + PartialEq + 'static,
+{
+    let note_offline = move |fetched: &Fetched<P::Item>| {
+        if let Some(date) = fetched.offline_cache_date {
+            offline_cache_date
+                .update(|d| *d = Some(d.map_or(date, |d| d.min(date))));
+        }
+    };
+    // The resource itself, for the background check to update
+    let this = store_value(None::<Resource<Option<String>, Vec<P::Item>>>);
+    let resource = create_local_resource(
+        move || open5e_url.get(),
+        move |base_url| async move {
+            let Some(base_url) = base_url else {
+                return parse_srd::<P>(srd, name)
+                    .into_iter()
+                    .filter(keep)
+                    .collect();
+            };
+            let url = format!("{}{}", base_url, path);
+            let results = match load_list::<P>(&cache_backend(), &url) {
+                Some(cached) => {
+                    if cached.is_stale(now()) {
+                        spawn_local(async move {
+                            let fetched = fetch_all::<P>(&url, name).await;
+                            // Nothing to update if the source changed
+                            // in the meantime
+                            let Some(resource) =
+                                this.get_value().filter(|_| {
+                                    open5e_url.get_untracked() == Some(base_url)
+                                })
+                            else {
+                                return;
+                            };
+                            note_offline(&fetched);
+                            if !fetched.complete {
+                                return;
+                            }
+                            let results = fetched
+                                .results
+                                .into_iter()
+                                .filter(keep)
+                                .collect::<Vec<_>>();
+                            if untrack(|| {
+                                resource.with(|r| r.as_ref() != Some(&results))
+                            }) {
+                                resource.set(results);
+                            }
+                        });
+                    }
+                    cached.results
+                }
+                None => {
+                    let fetched = fetch_all::<P>(&url, name).await;
+                    note_offline(&fetched);
+                    fetched.results
+                }
+            };
+            results.into_iter().filter(keep).collect()
         },
+    );
+    this.set_value(Some(resource));
+    resource
+}
+
+/// A list from the SRD bundled with the app, which is kept in the
+/// same form as a page from Open5e.
+pub fn parse_srd<P: Page>(json: &str, name: &str) -> Vec<P::Item> {
+    match serde_json::from_str::<P>(json) {
+        Ok(page) => page.into_results(),
         Err(e) => {
-            log!("Error fetching species data from Open5e!");
+            log!("Could not deserialize the bundled {} data!", name);
             log!("{}", e);
             vec![]
         }
     }
 }
 
-/// Fetch list of class options from Open5e
-pub async fn fetch_classes(_: ()) -> Vec<Class> {
-    let res = reqwest::get("https://api.open5e.com/v1/classes/").await;
-    match res {
-        Ok(response) => match response.json::<ClassesAPI>().await {
-            Ok(api) => api.results,
-            // Handle deserialization error condition
-            Err(e) => {
-                log!("Could not deserialize data from Open5e to the ClassAPI struct!");
-                log!("{}", e);
-                vec![]
-            }
-        },
-        // If our request errors, return an empty list
-        Err(e) => {
-            log!("Error fetching class data from Open5e!");
-            log!("{}", e);
-            vec![]
-        }
+/// One page of a list from Open5e. Lists come a page at a time, with
+/// the address of the page after, or null on the last one.
+pub trait Page: DeserializeOwned {
+    type Item;
+    fn next(&self) -> Option<&str>;
+    fn into_results(self) -> Vec<Self::Item>;
+}
+
+impl Page for SpeciesAPI {
+    type Item = Species;
+    fn next(&self) -> Option<&str> {
+        self.next.as_deref()
+    }
+    fn into_results(self) -> Vec<Species> {
+        self.results
     }
 }
 
-/// Fetch list of background options from Open5e
-pub async fn fetch_backgrounds(_: ()) -> Vec<Background> {
-    // A5e Backgrounds are harder to parse, so exclude them for now
-    let res = reqwest::get(
-        "https://api.open5e.com/v1/backgrounds/?document_slug__not_in=a5e",
-    )
-    .await;
-    match res {
-        Ok(response) => {
-            match response.json::<BackgroundsAPI>().await {
-                Ok(api) => api
-                    .results
-                    .into_iter()
-                    .filter(|b| b.document_slug != "a5e")
-                    .collect(),
-                // Handle deserialization error condition
-                Err(e) => {
-                    log!("Could not deserialize data from Open5e to the BackgroundAPI struct!");
-                    log!("{}", e);
-                    vec![]
-                }
-            }
-        }
-        // If our request errors, return an empty list
-        Err(e) => {
-            log!("Error fetching background data from Open5e!");
-            log!("{}", e);
-            vec![]
-        }
+impl Page for ClassesAPI {
+    type Item = Class;
+    fn next(&self) -> Option<&str> {
+        self.next.as_deref()
+    }
+    fn into_results(self) -> Vec<Class> {
+        self.results
+    }
+}
+
+impl Page for BackgroundsAPI {
+    type Item = Background;
+    fn next(&self) -> Option<&str> {
+        self.next.as_deref()
+    }
+    fn into_results(self) -> Vec<Background> {
+        self.results
     }
 }
 
-/// Fetch list of weapons  from Open5e
-pub async fn fetch_weapons(_: ()) -> Vec<Weapon> {
-    // A5e Backgrounds are harder to parse, so exclude them for now
-    let res = reqwest::get("https://api.open5e.com/v1/weapons/").await;
-    match res {
-        Ok(response) => match response.json::<WeaponApi>().await {
-            Ok(api) => api.results,
+impl Page for WeaponApi {
+    type Item = Weapon;
+    fn next(&self) -> Option<&str> {
+        self.next.as_deref()
+    }
+    fn into_results(self) -> Vec<Weapon> {
+        self.results
+    }
+}
+
+/// A list fetched from Open5e.
+pub struct Fetched<T> {
+    pub results: Vec<T>,
+    /// False if a page couldn't be fetched, or found in the cache, so
+    /// the results are missing the pages from there on.
+    pub complete: bool,
+    /// If some pages came from the cache because Open5e couldn't be
+    /// reached, when the oldest of those was fetched.
+    pub offline_cache_date: Option<u64>,
+}
+
+/// Fetch every page of a list from Open5e, starting at `url` and
+/// following each page's `next` until the last. `name` is what the
+/// list is of, i.e. "class", for logging. Each page is cached, and
+/// the cached copy used if Open5e can't be reached.
+pub async fn fetch_all<P: Page>(url: &str, name: &str) -> Fetched<P::Item> {
+    let storage = cache_backend();
+    let mut fetched = Fetched {
+        results: vec![],
+        complete: false,
+        offline_cache_date: None,
+    };
+    let mut next = Some(url.to_string());
+    while let Some(url) = next.take() {
+        let cached = load_page(&storage, &url);
+        let page = match fetch_page(&url).await {
+            Ok(page) => page,
+            // If our request errors, fall back on the cache
+            Err(e) => {
+                log!("Error fetching {} data from Open5e!", name);
+                log!("{}", e);
+                match cached {
+                    Some(cached) => {
+                        let date = cached.fetched_at;
+                        fetched.offline_cache_date = Some(
+                            fetched
+                                .offline_cache_date
+                                .map_or(date, |d| d.min(date)),
+                        );
+                        cached
+                    }
+                    None => return fetched,
+                }
+            }
+        };
+        match serde_json::from_str::<P>(&page.body) {
+            Ok(parsed) => {
+                write_page(&storage, &url, &page);
+                // A page pointing back at itself would never finish
+                next = parsed.next().filter(|n| *n != url).map(str::to_string);
+                fetched.results.extend(parsed.into_results());
+            }
             // Handle deserialization error condition
             Err(e) => {
-                log!("Could not de>serialize data from Open5e to the WeaponAPI struct!");
+                log!("Could not deserialize {} data from Open5e!", name);
                 log!("{}", e);
-                vec![]
+                return fetched;
             }
-        },
-        // If our request errors, return an empty list
-        Err(e) => {
-            log!("Error fetching weapon data from Open5e!");
-            log!("{}", e);
-            vec![]
         }
     }
+    fetched.complete = true;
+    fetched
+}
+
+/// Fetch one page from Open5e, revalidating any copy the browser has.
+///
+/// The request uses fetch's "no-cache" mode, so the browser always
+/// checks with Open5e, adding the `If-None-Match` from the ETag it
+/// stored itself. Open5e answers an unchanged page with a 304, and the
+/// browser hands back its copy. Setting `If-None-Match` here instead
+/// would make every request need a CORS preflight, which Open5e may
+/// refuse. This only saves the download if Open5e sends an ETag and
+/// lets the page be stored; otherwise the whole page is fetched.
+async fn fetch_page(url: &str) -> Result<CachedPage, reqwest::Error> {
+    let request = reqwest::Client::new().get(url);
+    // Fetch's cache modes only exist in the browser
+    #[cfg(target_arch = "wasm32")]
+    let request = request.fetch_cache_no_cache();
+    let response = request.send().await?.error_for_status()?;
+    Ok(CachedPage {
+        fetched_at: now(),
+        body: response.text().await?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_next_page() {
+        let page: WeaponApi = serde_json::from_str(
+            r#"{"count": 60, "next": "https://api.open5e.com/v1/weapons/?page=2",
+                "previous": null, "results": []}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            page.next(),
+            Some("https://api.open5e.com/v1/weapons/?page=2")
+        );
+        // The last page has no next page
+        let page: WeaponApi = serde_json::from_str(
+            r#"{"count": 60, "next": null,
+                "previous": "https://api.open5e.com/v1/weapons/?page=1",
+                "results": []}"#,
+        )
+        .unwrap();
+        assert_eq!(page.next(), None);
+    }
+
+    #[test]
+    fn parses_bundled_srd() {
+        let classes =
+            parse_srd::<ClassesAPI>(include_str!("srd/classes.json"), "class");
+        assert_eq!(classes.len(), 12);
+        let fighter = classes.iter().find(|c| c.slug == "fighter").unwrap();
+        let features = fighter.features();
+        assert!(features
+            .iter()
+            .any(|f| f.name == "Extra Attack" && f.level == 5));
+        // A later use of a feature is split out at its own level
+        assert!(features
+            .iter()
+            .any(|f| f.name == "Action Surge" && f.level == 17));
+        let sorcerer = classes.iter().find(|c| c.slug == "sorcerer").unwrap();
+        assert!(sorcerer.features().iter().any(|f| matches!(
+            &f.feature_type,
+            api_extensions::FeatureType::WeaponProficiency(p)
+                if p.covers("Crossbow, light", "Simple Ranged Weapons")
+        )));
+
+        let species = parse_srd::<SpeciesAPI>(
+            include_str!("srd/species.json"),
+            "species",
+        );
+        assert_eq!(species.len(), 9);
+        let dwarf = species.iter().find(|s| s.slug == "dwarf").unwrap();
+        let features = dwarf.features();
+        assert!(features.iter().any(|f| f.name == "Darkvision."));
+        assert!(features.iter().any(|f| matches!(
+            &f.feature_type,
+            api_extensions::FeatureType::WeaponProficiency(p)
+                if p.covers("Light hammer", "Simple Melee Weapons")
+        )));
+        assert_eq!(dwarf.subraces[0].slug, "hill-dwarf");
+
+        let backgrounds = parse_srd::<BackgroundsAPI>(
+            include_str!("srd/backgrounds.json"),
+            "background",
+        );
+        assert_eq!(backgrounds[0].name, "Acolyte");
+        let weapons =
+            parse_srd::<WeaponApi>(include_str!("srd/weapons.json"), "weapon");
+        assert_eq!(weapons.len(), 37);
+        let longbow = weapons.iter().find(|w| w.slug == "longbow").unwrap();
+        assert!(longbow.is_ranged() && longbow.is_heavy());
+    }
 }
@@ -1,11 +1,15 @@
 use std::fmt::Display;
 
+use lazy_regex::regex_captures;
 use leptos::{Signal, SignalGet};
 use serde::{Deserialize, Serialize};
 
-use crate::api::api_extensions::FeatureOptionsSelection;
+use crate::api::api_extensions::{Feature, FeatureOptionsSelection};
+use crate::api::api_model::{Background, Class, Species};
+use crate::dice::roll_mode::{RollMode, RollModeEffect, RollTarget};
+use crate::level_history::LevelSnapshot;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct CharacterDetails {
     pub name: String,
 
@@ -19,6 +23,9 @@ pub struct CharacterDetails {
 
     // HP without con mod factored in.
     pub ability_scores: AbilityScores,
+
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
 }
 
 impl CharacterDetails {
@@ -32,6 +39,7 @@ impl CharacterDetails {
             //level: 1,
             xp: 0,
             ability_scores: AbilityScores::new(),
+            conditions: vec![],
         }
     }
     pub fn prof_bonus(&self) -> i32 {
@@ -80,6 +88,17 @@ impl AbilityScores {
     pub fn score_to_mod(score: i32) -> i32 {
         (score - 10) / 2
     }
+    /// Score before any ASIs are applied.
+    pub fn base_score(&self, ability: &Ability) -> i32 {
+        match ability {
+            Ability::Strength => self.base_str,
+            Ability::Dexterity => self.base_dex,
+            Ability::Constitution => self.base_con,
+            Ability::Wisdom => self.base_wis,
+            Ability::Intelligence => self.base_int,
+            Ability::Charisma => self.base_cha,
+        }
+    }
     pub fn new() -> Self {
         /*
         let test = CharacterAsi {
@@ -189,6 +208,17 @@ impl AbilityScoresReactive {
     pub fn score_to_mod(score: i32) -> i32 {
         (score - 10) / 2
     }
+    /// Score with ASIs applied.
+    pub fn get_ability_score(&self, ability: &Ability) -> i32 {
+        match ability {
+            Ability::Strength => self.str_score(),
+            Ability::Dexterity => self.dex_score(),
+            Ability::Constitution => self.con_score(),
+            Ability::Wisdom => self.wis_score(),
+            Ability::Intelligence => self.int_score(),
+            Ability::Charisma => self.cha_score(),
+        }
+    }
     pub fn get_ability_mod(&self, ability: &Ability) -> i32 {
         match ability {
             Ability::Strength => self.str_mod(),
@@ -211,6 +241,14 @@ pub enum Ability {
     Charisma,
 }
 impl Ability {
+    pub const ALL: [Ability; 6] = [
+        Ability::Strength,
+        Ability::Dexterity,
+        Ability::Constitution,
+        Ability::Wisdom,
+        Ability::Intelligence,
+        Ability::Charisma,
+    ];
     pub fn to_string(&self) -> &str {
         match self {
             Ability::Strength => "Strength",
@@ -234,6 +272,162 @@ impl Ability {
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Skill {
+    Acrobatics,
+    AnimalHandling,
+    Arcana,
+    Athletics,
+    Deception,
+    History,
+    Insight,
+    Intimidation,
+    Investigation,
+    Medicine,
+    Nature,
+    Perception,
+    Performance,
+    Persuasion,
+    Religion,
+    SleightOfHand,
+    Stealth,
+    Survival,
+}
+impl Skill {
+    pub const ALL: [Skill; 18] = [
+        Skill::Acrobatics,
+        Skill::AnimalHandling,
+        Skill::Arcana,
+        Skill::Athletics,
+        Skill::Deception,
+        Skill::History,
+        Skill::Insight,
+        Skill::Intimidation,
+        Skill::Investigation,
+        Skill::Medicine,
+        Skill::Nature,
+        Skill::Perception,
+        Skill::Performance,
+        Skill::Persuasion,
+        Skill::Religion,
+        Skill::SleightOfHand,
+        Skill::Stealth,
+        Skill::Survival,
+    ];
+    /// The ability score a check with this skill uses.
+    pub fn ability(&self) -> Ability {
+        match self {
+            Skill::Athletics => Ability::Strength,
+            Skill::Acrobatics | Skill::SleightOfHand | Skill::Stealth => {
+                Ability::Dexterity
+            }
+            Skill::Arcana
+            | Skill::History
+            | Skill::Investigation
+            | Skill::Nature
+            | Skill::Religion => Ability::Intelligence,
+            Skill::AnimalHandling
+            | Skill::Insight
+            | Skill::Medicine
+            | Skill::Perception
+            | Skill::Survival => Ability::Wisdom,
+            Skill::Deception
+            | Skill::Intimidation
+            | Skill::Performance
+            | Skill::Persuasion => Ability::Charisma,
+        }
+    }
+    /// Match a skill proficiency from the API, i.e. " Animal Handling".
+    pub fn from_string(string: &str) -> Option<Skill> {
+        let name = string.trim().to_lowercase();
+        Skill::ALL
+            .into_iter()
+            .find(|s| s.to_string().to_lowercase() == name)
+    }
+}
+impl Display for Skill {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Skill::Acrobatics => "Acrobatics",
+            Skill::AnimalHandling => "Animal Handling",
+            Skill::Arcana => "Arcana",
+            Skill::Athletics => "Athletics",
+            Skill::Deception => "Deception",
+            Skill::History => "History",
+            Skill::Insight => "Insight",
+            Skill::Intimidation => "Intimidation",
+            Skill::Investigation => "Investigation",
+            Skill::Medicine => "Medicine",
+            Skill::Nature => "Nature",
+            Skill::Perception => "Perception",
+            Skill::Performance => "Performance",
+            Skill::Persuasion => "Persuasion",
+            Skill::Religion => "Religion",
+            Skill::SleightOfHand => "Sleight of Hand",
+            Skill::Stealth => "Stealth",
+            Skill::Survival => "Survival",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Conditions that change how the character rolls.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Condition {
+    Blinded,
+    Frightened,
+    Invisible,
+    Poisoned,
+    Prone,
+    Restrained,
+}
+impl Condition {
+    pub const ALL: [Condition; 6] = [
+        Condition::Blinded,
+        Condition::Frightened,
+        Condition::Invisible,
+        Condition::Poisoned,
+        Condition::Prone,
+        Condition::Restrained,
+    ];
+    pub fn roll_mode_effects(&self) -> Vec<RollModeEffect> {
+        let source = self.to_string();
+        let effect = |mode, target| RollModeEffect::new(mode, target, &source);
+        match self {
+            Condition::Blinded | Condition::Prone => {
+                vec![effect(RollMode::Disadvantage, RollTarget::Attacks)]
+            }
+            Condition::Frightened | Condition::Poisoned => vec![
+                effect(RollMode::Disadvantage, RollTarget::Attacks),
+                effect(RollMode::Disadvantage, RollTarget::AllChecks),
+            ],
+            Condition::Invisible => {
+                vec![effect(RollMode::Advantage, RollTarget::Attacks)]
+            }
+            Condition::Restrained => vec![
+                effect(RollMode::Disadvantage, RollTarget::Attacks),
+                effect(
+                    RollMode::Disadvantage,
+                    RollTarget::Save(Ability::Dexterity),
+                ),
+            ],
+        }
+    }
+}
+impl Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Condition::Blinded => "Blinded",
+            Condition::Frightened => "Frightened",
+            Condition::Invisible => "Invisible",
+            Condition::Poisoned => "Poisoned",
+            Condition::Prone => "Prone",
+            Condition::Restrained => "Restrained",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 fn xp_to_level(xp: i32) -> i32 {
     match xp {
         i32::MIN..=299 => 1,
@@ -284,60 +478,444 @@ fn level_to_xp(level: i32) -> i32 {
     }
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct DynamicCharacterData {
-    pub features_selections: Vec<FeatureOptionsSelection>,
+/// Everything saved about a character. This is stored, exported and
+/// swapped out as one unit, so its parts can't get out of step.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct CharacterDocument {
+    pub details: CharacterDetails,
+    #[serde(default)]
+    pub selected_optional_features: Vec<FeatureOptionsSelection>,
+    #[serde(default)]
     pub attack_list: Vec<AttackAction>,
+    #[serde(default, skip_serializing_if = "Homebrew::is_empty")]
+    pub homebrew: Homebrew,
+    /// The character at each level they've levelled up from.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub level_history: Vec<LevelSnapshot>,
+}
+
+/// Classes, species, backgrounds and features that aren't on Open5e,
+/// i.e. ones brought in from another app. They're kept with the
+/// character that uses them, in the same shape as Open5e's, and are
+/// offered alongside Open5e's entries.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+pub struct Homebrew {
+    #[serde(default)]
+    pub classes: Vec<Class>,
+    #[serde(default)]
+    pub species: Vec<Species>,
+    #[serde(default)]
+    pub backgrounds: Vec<Background>,
+    /// Features the character has on top of the ones their class,
+    /// species and background give, i.e. feats.
+    #[serde(default)]
+    pub features: Vec<HomebrewFeature>,
+}
+
+impl Homebrew {
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+            && self.species.is_empty()
+            && self.backgrounds.is_empty()
+            && self.features.is_empty()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+pub struct HomebrewFeature {
+    pub name: String,
+    pub desc: String,
+}
+
+impl HomebrewFeature {
+    pub fn to_feature(&self) -> Feature {
+        Feature {
+            name: self.name.to_string(),
+            desc: self.desc.to_string(),
+            level: 1,
+            source_slug: "homebrew".to_string(),
+            ..Default::default()
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
 pub enum AttackType {
     Melee,
     Ranged,
+    /// Thrown melee weapons, such as daggers or handaxes.
+    MeleeOrRanged,
 }
 impl Display for AttackType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let name = match self {
             AttackType::Melee => "Melee",
             AttackType::Ranged => "Ranged",
+            AttackType::MeleeOrRanged => "Melee or Ranged",
         };
         write!(f, "{}", name)
     }
 }
 
-#[derive(Serialize, Deserialize)]
+/// A weapon proficiency granted by a feature. Proficiencies either
+/// cover a whole category of weapons, or a single named weapon.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum WeaponProficiency {
+    Simple,
+    Martial,
+    /// A specific weapon, stored as a normalized name
+    /// (see `normalize_weapon_name`).
+    Named(String),
+}
+impl WeaponProficiency {
+    /// Parse a single entry of a proficiency list, such as
+    /// "Simple weapons", "martial weapons" or "hand crossbows".
+    pub fn from_string(string: &str) -> Option<WeaponProficiency> {
+        let string = string.trim().to_lowercase();
+        let string = string.trim_start_matches("the ").trim();
+        match string {
+            "" | "none" => None,
+            "simple weapons" => Some(WeaponProficiency::Simple),
+            "martial weapons" => Some(WeaponProficiency::Martial),
+            _ => Some(WeaponProficiency::Named(normalize_weapon_name(
+                string.strip_suffix('s').unwrap_or(string),
+            ))),
+        }
+    }
+    /// Parse a comma separated list of proficiencies, as found in
+    /// `Class::prof_weapons` or a species' weapon training trait.
+    pub fn parse_list(string: &str) -> Vec<WeaponProficiency> {
+        string
+            .replace(" and ", ",")
+            .split(',')
+            .filter_map(WeaponProficiency::from_string)
+            .collect()
+    }
+    /// Whether this proficiency applies to a weapon with the given name
+    /// and Open5e category (i.e. "Simple Melee Weapons").
+    pub fn covers(&self, weapon_name: &str, category: &str) -> bool {
+        match self {
+            WeaponProficiency::Simple => category.contains("Simple"),
+            WeaponProficiency::Martial => category.contains("Martial"),
+            WeaponProficiency::Named(name) => {
+                *name == normalize_weapon_name(weapon_name)
+            }
+        }
+    }
+}
+impl Display for WeaponProficiency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WeaponProficiency::Simple => write!(f, "Simple weapons"),
+            WeaponProficiency::Martial => write!(f, "Martial weapons"),
+            WeaponProficiency::Named(name) => write!(f, "Weapon: {}", name),
+        }
+    }
+}
+
+/// Open5e lists some weapons with their qualifier after a comma
+/// ("Crossbow, hand"), while proficiency lists use the natural
+/// order ("hand crossbows"). This brings both into the same form.
+pub fn normalize_weapon_name(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    match name.split_once(',') {
+        Some((base, qualifier)) => {
+            format!("{} {}", qualifier.trim(), base.trim())
+        }
+        None => name,
+    }
+}
+
+/// Normal and long range of a ranged or thrown weapon, in feet.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct WeaponRange {
+    pub normal: i32,
+    pub long: i32,
+}
+impl WeaponRange {
+    /// Parse a range in the form Open5e uses, i.e. "range 80/320".
+    pub fn from_string(string: &str) -> Option<WeaponRange> {
+        let (_, normal, long) = regex_captures!(r"(\d+)/(\d+)", string)?;
+        Some(WeaponRange {
+            normal: normal.parse().ok()?,
+            long: long.parse().ok()?,
+        })
+    }
+}
+impl Display for WeaponRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "range {}/{} ft.", self.normal, self.long)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum WeaponProperty {
+    Finesse,
+    Light,
+    Heavy,
+    Reach,
+    TwoHanded,
+    Loading,
+    /// Damage dice when wielded with two hands.
+    Versatile(String),
+    Thrown(WeaponRange),
+    Ammunition(WeaponRange),
+    /// Any property we don't handle specially, such as "special".
+    Other(String),
+}
+impl WeaponProperty {
+    /// Parse a single property from the Open5e weapon properties list,
+    /// such as "versatile (1d10)" or "ammunition (range 80/320)".
+    pub fn from_string(string: &str) -> WeaponProperty {
+        let string = string.trim().to_lowercase();
+        let (name, argument) = match string.split_once('(') {
+            Some((name, rest)) => (name.trim(), rest.trim_end_matches(')')),
+            None => (string.as_str(), ""),
+        };
+        let range = WeaponRange::from_string(argument);
+        match (name, range) {
+            ("finesse", _) => WeaponProperty::Finesse,
+            ("light", _) => WeaponProperty::Light,
+            ("heavy", _) => WeaponProperty::Heavy,
+            ("reach", _) => WeaponProperty::Reach,
+            ("two-handed", _) => WeaponProperty::TwoHanded,
+            ("loading", _) => WeaponProperty::Loading,
+            ("versatile", _) if !argument.is_empty() => {
+                WeaponProperty::Versatile(argument.trim().to_string())
+            }
+            ("thrown", Some(range)) => WeaponProperty::Thrown(range),
+            ("ammunition", Some(range)) => WeaponProperty::Ammunition(range),
+            _ => WeaponProperty::Other(string.to_string()),
+        }
+    }
+}
+impl Display for WeaponProperty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WeaponProperty::Finesse => write!(f, "finesse"),
+            WeaponProperty::Light => write!(f, "light"),
+            WeaponProperty::Heavy => write!(f, "heavy"),
+            WeaponProperty::Reach => write!(f, "reach"),
+            WeaponProperty::TwoHanded => write!(f, "two-handed"),
+            WeaponProperty::Loading => write!(f, "loading"),
+            WeaponProperty::Versatile(dice) => {
+                write!(f, "versatile ({})", dice)
+            }
+            WeaponProperty::Thrown(range) => write!(f, "thrown ({})", range),
+            WeaponProperty::Ammunition(range) => {
+                write!(f, "ammunition ({})", range)
+            }
+            WeaponProperty::Other(other) => write!(f, "{}", other),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct AttackAction {
     pub name: String,
     pub slug: String,
     pub ability: Ability,
     pub damage_base: String,
-    pub proficient: bool,
+    /// Name of the Open5e weapon this attack was created from.
+    /// Empty for custom attacks.
+    #[serde(default)]
+    pub weapon: String,
+    /// Open5e category of the weapon, i.e. "Martial Melee Weapons".
+    /// Empty for custom attacks.
+    #[serde(default)]
+    pub category: String,
     pub attack_type: AttackType,
     pub reach: i32,
     pub damage_type: String,
+    #[serde(default)]
+    pub properties: Vec<WeaponProperty>,
 }
 
 impl AttackAction {
-    pub fn generate_description(
+    /// Custom attacks aren't tied to a weapon, so we assume
+    /// the character is proficient with them.
+    pub fn is_proficient(&self, weapon_profs: &[WeaponProficiency]) -> bool {
+        if self.weapon.is_empty() && self.category.is_empty() {
+            return true;
+        }
+        weapon_profs
+            .iter()
+            .any(|p| p.covers(&self.weapon, &self.category))
+    }
+    pub fn is_finesse(&self) -> bool {
+        self.properties.contains(&WeaponProperty::Finesse)
+    }
+    /// Range of the attack, from either the thrown or the
+    /// ammunition property.
+    pub fn range(&self) -> Option<WeaponRange> {
+        self.properties.iter().find_map(|p| match p {
+            WeaponProperty::Thrown(range) => Some(*range),
+            WeaponProperty::Ammunition(range) => Some(*range),
+            _ => None,
+        })
+    }
+    pub fn versatile_damage(&self) -> Option<String> {
+        self.properties.iter().find_map(|p| match p {
+            WeaponProperty::Versatile(dice) => Some(dice.to_string()),
+            _ => None,
+        })
+    }
+    /// Finesse weapons use whichever of Strength and Dexterity
+    /// is higher. Everything else uses the attack's own ability.
+    pub fn attack_ability(
+        &self,
+        ability_scores: AbilityScoresReactive,
+    ) -> Ability {
+        if self.is_finesse()
+            && ability_scores.dex_mod() > ability_scores.str_mod()
+        {
+            Ability::Dexterity
+        } else if self.is_finesse() {
+            Ability::Strength
+        } else {
+            self.ability.clone()
+        }
+    }
+    /// Modifier added to the damage roll.
+    pub fn damage_mod(&self, ability_scores: AbilityScoresReactive) -> i32 {
+        ability_scores.get_ability_mod(&self.attack_ability(ability_scores))
+    }
+    /// Bonus added to the d20 when making the attack roll.
+    pub fn to_hit(
         &self,
         proficency_bonus: Signal<i32>,
         ability_scores: AbilityScoresReactive,
-    ) -> String {
-        let ab_mod = ability_scores.get_ability_mod(&self.ability);
-        let to_hit = ab_mod
-            + if self.proficient {
+        weapon_profs: &[WeaponProficiency],
+    ) -> i32 {
+        self.damage_mod(ability_scores)
+            + if self.is_proficient(weapon_profs) {
                 proficency_bonus()
             } else {
                 0
-            };
-        format!(
-            "_{} Weapon Attack:_ +{} to hit, reach {} ft., one target. _Hit:_ {} + {} {} damage.",
+            }
+    }
+    pub fn generate_description(
+        &self,
+        proficency_bonus: Signal<i32>,
+        ability_scores: AbilityScoresReactive,
+        weapon_profs: &[WeaponProficiency],
+    ) -> String {
+        let ab_mod = self.damage_mod(ability_scores);
+        let to_hit =
+            self.to_hit(proficency_bonus, ability_scores, weapon_profs);
+        let reach = format!("reach {} ft.", self.reach);
+        let distance = match (self.attack_type, self.range()) {
+            (AttackType::Melee, _) | (_, None) => reach,
+            (AttackType::Ranged, Some(range)) => range.to_string(),
+            (AttackType::MeleeOrRanged, Some(range)) => {
+                format!("{} or {}", reach, range)
+            }
+        };
+        let versatile = match self.versatile_damage() {
+            Some(dice) => format!(
+                ", or {} + {} {} damage if used with two hands",
+                dice, ab_mod, self.damage_type
+            ),
+            None => String::new(),
+        };
+        let mut description = format!(
+            "_{} Weapon Attack:_ +{} to hit, {}, one target. _Hit:_ {} + {} {} damage{}.",
             self.attack_type,
             to_hit,
-            self.reach,
+            distance,
             self.damage_base,
             ab_mod,
-            self.damage_type
-        )
+            self.damage_type,
+            versatile
+        );
+        if !self.properties.is_empty() {
+            let properties = self
+                .properties
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<String>>()
+                .join(", ");
+            description += &format!("\n\n_Properties:_ {}", properties);
+        }
+        description
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_weapon_properties() {
+        let range = |normal, long| WeaponRange { normal, long };
+        assert_eq!(
+            WeaponProperty::from_string("thrown (range 20/60)"),
+            WeaponProperty::Thrown(range(20, 60))
+        );
+        assert_eq!(
+            WeaponProperty::from_string("Ammunition (range 80/320)"),
+            WeaponProperty::Ammunition(range(80, 320))
+        );
+        assert_eq!(
+            WeaponProperty::from_string(" versatile (1d10)"),
+            WeaponProperty::Versatile("1d10".to_string())
+        );
+        assert_eq!(
+            WeaponProperty::from_string("two-handed"),
+            WeaponProperty::TwoHanded
+        );
+        // Anything else is kept as it is, to show on the sheet
+        assert_eq!(
+            WeaponProperty::from_string("special"),
+            WeaponProperty::Other("special".to_string())
+        );
+        // A thrown weapon without a range can't be thrown anywhere
+        assert_eq!(
+            WeaponProperty::from_string("thrown"),
+            WeaponProperty::Other("thrown".to_string())
+        );
+        assert_eq!(
+            WeaponRange::from_string("range 80/320"),
+            Some(range(80, 320))
+        );
+        assert_eq!(WeaponRange::from_string("1d10"), None);
+        assert_eq!(range(20, 60).to_string(), "range 20/60 ft.");
+    }
+
+    #[test]
+    fn parses_weapon_proficiencies() {
+        let named = |name: &str| WeaponProficiency::Named(name.to_string());
+        assert_eq!(
+            WeaponProficiency::parse_list(
+                "Simple weapons, hand crossbows, longswords"
+            ),
+            vec![
+                WeaponProficiency::Simple,
+                named("hand crossbow"),
+                named("longsword"),
+            ]
+        );
+        assert_eq!(
+            WeaponProficiency::parse_list(
+                "the battleaxe, handaxe, light hammer, and warhammer"
+            ),
+            vec![
+                named("battleaxe"),
+                named("handaxe"),
+                named("light hammer"),
+                named("warhammer"),
+            ]
+        );
+        assert!(WeaponProficiency::parse_list("None").is_empty());
+
+        // Open5e puts the qualifier after a comma
+        assert_eq!(normalize_weapon_name("Crossbow, light"), "light crossbow");
+        assert!(named("light crossbow").covers("Crossbow, light", ""));
+        assert!(named("longsword").covers("Longsword", ""));
+        assert!(!named("longsword").covers("Shortsword", ""));
+        assert!(WeaponProficiency::Martial
+            .covers("Longsword", "Martial Melee Weapons"));
+        assert!(!WeaponProficiency::Simple
+            .covers("Longsword", "Martial Melee Weapons"));
     }
 }
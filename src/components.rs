@@ -42,11 +42,11 @@ pub fn CustomSelect() -> HtmlElement<Select> {
 /// # Arguments
 ///
 /// * `current` - The currently selected item. An item with a slug matching this
-/// string will be marked "selected"
+///   string will be marked "selected"
 /// * `slug` - The "value" of this option
 /// * `name` - Part of the title of this option
 /// * `doc_title` - The part of the title of this option, which goes in
-/// parenthesis after the name.
+///   parenthesis after the name.
 pub fn OptionWithDocTitle(
     current: &str,
     slug: &str,
@@ -0,0 +1,540 @@
+//! Characters from Fight Club 5, as the XML file its "Export" option
+//! writes (a `<pc>` holding a `<character>`).
+//!
+//! Fight Club keeps a full copy of the character's class, race and
+//! background in the file, so when Open5e has nothing with the same
+//! name they're turned into homebrew entries from that copy, rather
+//! than being left out.
+
+use roxmltree::{Document, Node};
+
+use super::*;
+use crate::api::api_model::{ApiAsi, Speed};
+use crate::character_model::{
+    AttackAction, AttackType, WeaponProperty, WeaponRange,
+};
+
+/// The order Fight Club lists ability scores in.
+const ABILITIES: [(&str, Ability); 6] = [
+    ("str", Ability::Strength),
+    ("dex", Ability::Dexterity),
+    ("con", Ability::Constitution),
+    ("int", Ability::Intelligence),
+    ("wis", Ability::Wisdom),
+    ("cha", Ability::Charisma),
+];
+
+/// Read a Fight Club 5 character, matching everything we can to
+/// Open5e and keeping the rest as homebrew.
+pub fn import_character(
+    xml: &str,
+    data: Open5eData,
+) -> Result<Imported, String> {
+    let xml =
+        Document::parse(xml).map_err(|e| format!("not valid XML ({})", e))?;
+    let root = xml.root_element();
+    if root.has_tag_name("compendium") {
+        return Err("a Fight Club 5 compendium, rather than a character. \
+            Export the character from its own page instead"
+            .to_string());
+    }
+    let character = Some(root)
+        .filter(|r| r.has_tag_name("pc"))
+        .and_then(|r| child(r, "character"))
+        .ok_or("not a Fight Club 5 character")?;
+
+    let scores = text(character, "abilities");
+    let mut scores = scores
+        .split(',')
+        .map(|s| s.trim().parse::<i32>().unwrap_or(10))
+        .chain(std::iter::repeat(10));
+    let (weapons, inventory): (Vec<Node>, Vec<Node>) =
+        children(character, "item").partition(|i| is_weapon(*i));
+
+    Ok(import(
+        ForeignCharacter {
+            name: text(character, "name"),
+            classes: children(character, "class")
+                .map(|c| {
+                    let levels = text(c, "level").parse::<i32>().unwrap_or(1);
+                    (homebrew_class(c), levels)
+                })
+                .collect(),
+            xp: text(character, "xp").parse::<i32>().unwrap_or_default(),
+            species: child(character, "race").map(homebrew_species),
+            background: child(character, "background").map(homebrew_background),
+            ability_scores: ABILITIES
+                .iter()
+                .map(|(_, ability)| {
+                    (ability.clone(), scores.next().unwrap_or(10))
+                })
+                .collect(),
+            skills: children(character, "proficiency")
+                .filter_map(|p| {
+                    Skill::from_string(p.text().unwrap_or_default())
+                })
+                .collect(),
+            feats: children(character, "feat")
+                .map(|f| HomebrewFeature {
+                    name: text(f, "name"),
+                    desc: paragraphs(f, "\n\n"),
+                })
+                .collect(),
+            weapons: weapons
+                .into_iter()
+                .map(|w| (text(w, "name"), custom_attack(w)))
+                .collect(),
+            spells: character
+                .descendants()
+                .filter(|n| n.has_tag_name("spell"))
+                .map(|s| text(s, "name"))
+                .filter(|name| !name.is_empty())
+                .collect(),
+            inventory: inventory.into_iter().map(|i| text(i, "name")).collect(),
+            ..Default::default()
+        },
+        data,
+    ))
+}
+
+/// An Open5e-style class from Fight Club's copy of it. Features are
+/// written into the description the way Open5e does, as level 3
+/// headers that mention the level the feature is gained at.
+fn homebrew_class(class: Node) -> Class {
+    let name = text(class, "name");
+    let hit_die = text(class, "hd").parse::<i32>().unwrap_or(8);
+    // Saving throws and the skills to choose from, in one list
+    let proficiencies = list(&text(class, "proficiency"));
+    let saves = proficiencies
+        .iter()
+        .filter(|p| Ability::from_string(p).is_some())
+        .cloned()
+        .collect::<Vec<String>>();
+    let skills = proficiencies
+        .iter()
+        .filter(|p| Skill::from_string(p).is_some())
+        .cloned()
+        .collect::<Vec<String>>();
+    let num_skills = match text(class, "numSkills").as_str() {
+        "2" => "two",
+        "3" => "three",
+        "4" => "four",
+        _ => "",
+    };
+
+    let mut desc = vec![];
+    let mut levels = children(class, "autolevel")
+        .map(|a| (a, a.attribute("level").and_then(|l| l.parse().ok())))
+        .collect::<Vec<(Node, Option<i32>)>>();
+    levels.sort_by_key(|(_, level)| *level);
+    for (autolevel, level) in levels {
+        let level = level.unwrap_or(1);
+        for feature in children(autolevel, "feature") {
+            let body = paragraphs(feature, "\n\n");
+            desc.push(format!("### {}", text(feature, "name")));
+            if level > 1 && !body.contains(&format!("{} level", ordinal(level)))
+            {
+                desc.push(format!(
+                    "At {} level, you gain this feature.",
+                    ordinal(level)
+                ));
+            }
+            if !body.is_empty() {
+                desc.push(body);
+            }
+        }
+    }
+
+    Class {
+        desc: desc.join("\n\n"),
+        prof_armor: text(class, "armor"),
+        prof_weapons: text(class, "weapons"),
+        prof_tools: text(class, "tools"),
+        prof_saving_throws: saves.join(", "),
+        prof_skills: if skills.is_empty() || num_skills.is_empty() {
+            String::new()
+        } else {
+            format!("Choose {} from {}", num_skills, skills.join(", "))
+        },
+        spellcasting_ability: text(class, "spellAbility"),
+        ..new_homebrew_class(&name, hit_die)
+    }
+}
+
+/// An Open5e-style species from Fight Club's copy of a race.
+fn homebrew_species(race: Node) -> Species {
+    let name = text(race, "name");
+    // Bonuses are written like "Con 2, Wis 1"
+    let asi = list(&text(race, "ability"))
+        .iter()
+        .filter_map(|bonus| {
+            let (abbreviation, value) = bonus.split_once(' ')?;
+            let ability = ABILITIES
+                .iter()
+                .find(|(a, _)| a.eq_ignore_ascii_case(abbreviation.trim()))?;
+            Some(ApiAsi {
+                attributes: vec![ability.1.to_string().to_string()],
+                value: value.trim().trim_start_matches('+').parse().ok()?,
+            })
+        })
+        .collect::<Vec<ApiAsi>>();
+    let asi_desc = asi
+        .iter()
+        .map(|a| {
+            format!(
+                "Your {} score increases by {}.",
+                a.attributes.join(" and "),
+                a.value
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    let mut desc = String::new();
+    let mut traits = vec![];
+    for (trait_name, trait_text) in traits_of(race, "\n") {
+        if trait_name == "Description" {
+            desc = trait_text;
+        } else {
+            traits.push(format!("***{}.*** {}", trait_name, trait_text));
+        }
+    }
+
+    Species {
+        slug: homebrew_slug(&name),
+        desc,
+        asi_desc,
+        asi,
+        size: match text(race, "size").as_str() {
+            "T" => "Tiny",
+            "S" => "Small",
+            "L" => "Large",
+            "H" => "Huge",
+            _ => "Medium",
+        }
+        .to_string(),
+        speed: Speed {
+            walk: text(race, "speed").parse().unwrap_or(30),
+        },
+        traits: traits.join("\n\n"),
+        document_slug: "homebrew".to_string(),
+        document_title: "Homebrew".to_string(),
+        name,
+        ..Default::default()
+    }
+}
+
+/// An Open5e-style background from Fight Club's copy of it.
+fn homebrew_background(background: Node) -> Background {
+    let name = text(background, "name");
+    let proficiencies = list(&text(background, "proficiency"));
+    let (skills, tools): (Vec<String>, Vec<String>) = proficiencies
+        .into_iter()
+        .partition(|p| Skill::from_string(p).is_some());
+
+    let mut homebrew = Background {
+        slug: homebrew_slug(&name),
+        skill_proficiencies: Some(skills.join(", ")).filter(|s| !s.is_empty()),
+        tool_proficiencies: Some(tools.join(", ")).filter(|t| !t.is_empty()),
+        document_slug: "homebrew".to_string(),
+        document_title: "Homebrew".to_string(),
+        name,
+        ..Default::default()
+    };
+    let mut other = vec![];
+    for (trait_name, trait_text) in traits_of(background, "\n\n") {
+        if let Some(feature) = trait_name.strip_prefix("Feature:") {
+            homebrew.feature = feature.trim().to_string();
+            homebrew.feature_desc = trait_text;
+        } else {
+            match trait_name.as_str() {
+                "Description" => homebrew.desc = trait_text,
+                "Suggested Characteristics" => {
+                    homebrew.suggested_characteristics = trait_text
+                }
+                "Equipment" => homebrew.equipment = trait_text,
+                "Languages" => homebrew.languages = Some(trait_text),
+                "Skill Proficiencies" | "Tool Proficiencies" => {}
+                _ => other.push(format!("**{}.** {}", trait_name, trait_text)),
+            }
+        }
+    }
+    if !other.is_empty() {
+        other.insert(0, homebrew.desc);
+        homebrew.desc = other.join("\n\n");
+    }
+    homebrew
+}
+
+/// Fight Club marks melee and ranged weapons with their item type,
+/// but any item with damage can be attacked with.
+fn is_weapon(item: Node) -> bool {
+    matches!(text(item, "type").as_str(), "M" | "R")
+        || !text(item, "dmg1").is_empty()
+}
+
+/// An attack for a weapon Open5e doesn't have, from Fight Club's own
+/// description of it.
+fn custom_attack(item: Node) -> AttackAction {
+    let name = text(item, "name");
+    let codes = list(&text(item, "property"));
+    let has_property = |code: &str| codes.iter().any(|c| c == code);
+    // Ranges are written like "20/60"
+    let range =
+        text(item, "range")
+            .split_once('/')
+            .and_then(|(normal, long)| {
+                Some(WeaponRange {
+                    normal: normal.trim().parse().ok()?,
+                    long: long.trim().parse().ok()?,
+                })
+            });
+    let ranged = text(item, "type") == "R";
+
+    let mut properties = vec![];
+    for (code, property) in [
+        ("F", WeaponProperty::Finesse),
+        ("L", WeaponProperty::Light),
+        ("H", WeaponProperty::Heavy),
+        ("R", WeaponProperty::Reach),
+        ("2H", WeaponProperty::TwoHanded),
+        ("LD", WeaponProperty::Loading),
+    ] {
+        if has_property(code) {
+            properties.push(property);
+        }
+    }
+    let versatile = text(item, "dmg2");
+    if has_property("V") && !versatile.is_empty() {
+        properties.push(WeaponProperty::Versatile(versatile));
+    }
+    if let Some(range) = range {
+        if has_property("T") {
+            properties.push(WeaponProperty::Thrown(range));
+        } else if has_property("A") {
+            properties.push(WeaponProperty::Ammunition(range));
+        }
+    }
+
+    AttackAction {
+        slug: name.to_string(),
+        name,
+        ability: if ranged {
+            Ability::Dexterity
+        } else {
+            Ability::Strength
+        },
+        damage_base: text(item, "dmg1"),
+        // Left empty, so the character is assumed to be proficient
+        weapon: String::new(),
+        category: String::new(),
+        attack_type: if ranged {
+            AttackType::Ranged
+        } else if has_property("T") {
+            AttackType::MeleeOrRanged
+        } else {
+            AttackType::Melee
+        },
+        reach: if has_property("R") { 10 } else { 5 },
+        damage_type: match text(item, "dmgType").as_str() {
+            "S" => "slashing",
+            "P" => "piercing",
+            "B" => "bludgeoning",
+            "A" => "acid",
+            "C" => "cold",
+            "F" => "fire",
+            "FC" => "force",
+            "L" => "lightning",
+            "N" => "necrotic",
+            "PS" => "poison",
+            "PY" => "psychic",
+            "R" => "radiant",
+            "T" => "thunder",
+            _ => "",
+        }
+        .to_string(),
+        properties,
+    }
+}
+
+/// "1st", "2nd", "3rd", "4th" and so on.
+fn ordinal(number: i32) -> String {
+    let suffix = match (number % 10, number % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", number, suffix)
+}
+
+/// Name and text of each `<trait>`, with the text's paragraphs
+/// joined by `separator`.
+fn traits_of(node: Node, separator: &str) -> Vec<(String, String)> {
+    children(node, "trait")
+        .map(|t| (text(t, "name"), paragraphs(t, separator)))
+        .collect()
+}
+
+/// Fight Club writes each paragraph as its own `<text>`.
+fn paragraphs(node: Node, separator: &str) -> String {
+    children(node, "text")
+        .filter_map(|t| t.text())
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<&str>>()
+        .join(separator)
+}
+
+/// A comma separated list, i.e. "Strength, Constitution".
+fn list(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn child<'a, 'input>(
+    node: Node<'a, 'input>,
+    tag: &str,
+) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(tag))
+}
+
+fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    tag: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children().filter(move |n| n.has_tag_name(tag))
+}
+
+/// Text of a child element, or empty if there isn't one.
+fn text(node: Node, tag: &str) -> String {
+    child(node, tag)
+        .and_then(|n| n.text())
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::test_data::*;
+    use crate::migrations::{
+        from_versioned_json, to_versioned_json, DOCUMENT_MIGRATIONS,
+    };
+
+    #[test]
+    fn imports_fight_club_character() {
+        let (species, weapons) = ([dwarf()], [longsword()]);
+        let data = Open5eData {
+            classes: &[],
+            species: &species,
+            backgrounds: &[],
+            weapons: &weapons,
+        };
+        let imported = import_character(
+            include_str!("../../tests/fixtures/fight_club/character.xml"),
+            data,
+        )
+        .unwrap();
+        let document = imported.document;
+        let details = &document.details;
+        assert_eq!(details.name, "Vesna");
+        assert_eq!(details.level(), 3);
+        assert_eq!(details.xp, 1200);
+        assert_eq!(details.species, "dwarf");
+        assert_eq!(details.subspecies, "hill-dwarf");
+        // Without the dwarf's bonuses
+        assert_eq!(details.ability_scores.base_con, 14);
+        assert_eq!(details.ability_scores.base_wis, 13);
+        assert_eq!(details.ability_scores.base_str, 17);
+
+        // Open5e doesn't have these, so they're kept as homebrew
+        assert_eq!(details.class, "homebrew-blood-hunter");
+        assert_eq!(details.background, "homebrew-haunted-one");
+        let class = &document.homebrew.classes[0];
+        assert_eq!(class.slug, details.class);
+        assert_eq!(class.base_hp(), 10);
+        let class_features = class.features();
+        let level_of = |name: &str| {
+            class_features
+                .iter()
+                .find(|f| f.name == name)
+                .map(|f| f.level)
+        };
+        assert_eq!(level_of("Hunter's Bane"), Some(1));
+        assert_eq!(level_of("Blood Maledict"), Some(2));
+        assert_eq!(level_of("Blood Hunter Order"), Some(3));
+        assert_eq!(level_of("Extra Attack"), Some(5));
+        assert!(class_features.iter().any(
+            |f| f.feature_type == FeatureType::SavingThrow(Ability::Wisdom)
+        ));
+        let lucky = &document.homebrew.features[0];
+        assert_eq!(lucky.name, "Lucky");
+        assert_eq!(lucky.desc, "You have inexplicable luck.");
+        let background = &document.homebrew.backgrounds[0];
+        assert_eq!(background.feature, "Heart of Darkness");
+        assert_eq!(
+            background.skill_proficiencies.as_deref(),
+            Some("Investigation, Religion")
+        );
+
+        // Three skills from the homebrew class, the others come from
+        // the homebrew background
+        assert_eq!(
+            document
+                .selected_optional_features
+                .iter()
+                .filter(|s| s.slug == "class:homebrew-blood-hunter:class_skills")
+                .count(),
+            3
+        );
+
+        let attacks = &document.attack_list;
+        assert_eq!(attacks.len(), 2);
+        assert_eq!(attacks[0].weapon, "Longsword");
+        assert_eq!(attacks[1].name, "Rite Blade");
+        assert_eq!(attacks[1].damage_type, "slashing");
+        assert!(attacks[1].properties.contains(&WeaponProperty::Finesse));
+        assert!(attacks[1]
+            .properties
+            .contains(&WeaponProperty::Versatile("1d10".to_string())));
+
+        let report = imported.report.unmapped;
+        for line in [
+            "Blood Hunter isn't on Open5e, so it was added as a homebrew class",
+            "Haunted One isn't on Open5e, so it was added as a homebrew \
+                background",
+            "Nothing on the sheet gives proficiency in: Perception",
+            "Not on the sheet, so added as homebrew features: Lucky",
+            "Not on Open5e, so added as custom attacks: Rite Blade",
+            "Inventory isn't tracked yet: Rope, hempen (50 feet)",
+        ] {
+            assert!(report.contains(&line.to_string()), "{:?}", report);
+        }
+
+        // Homebrew is saved with the character
+        let json = to_versioned_json(&document).unwrap();
+        let loaded = from_versioned_json::<CharacterDocument>(
+            &json,
+            &DOCUMENT_MIGRATIONS,
+        )
+        .unwrap()
+        .data;
+        assert!(loaded == document);
+    }
+
+    #[test]
+    fn rejects_compendiums() {
+        let data = no_open5e_data();
+        let compendium = "<compendium version=\"5\"><class><name>Fighter\
+            </name></class></compendium>";
+        let error = import_character(compendium, data).err().unwrap();
+        assert!(error.contains("compendium"));
+        assert!(import_character("<pc></pc>", data).is_err());
+        assert!(import_character("{}", data).is_err());
+    }
+}
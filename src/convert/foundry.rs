@@ -0,0 +1,780 @@
+//! Characters in Foundry VTT's dnd5e system, as the actor JSON
+//! Foundry reads and writes with its "Import Data" and "Export Data"
+//! options.
+//!
+//! Foundry's format has changed between versions of the system (i.e.
+//! `data` became `system`, and weapon damage and properties have
+//! been stored a few different ways), so everything is read from the
+//! raw JSON, trying each place a value has been kept.
+
+use serde_json::{json, Value};
+
+use super::*;
+use crate::api::api_model::Speed;
+use crate::character_model::{
+    AttackAction, AttackType, WeaponProperty, WeaponRange,
+};
+use crate::markdown::parse_markdown;
+
+/// Foundry's abbreviations for ability scores.
+const ABILITIES: [(&str, Ability); 6] = [
+    ("str", Ability::Strength),
+    ("dex", Ability::Dexterity),
+    ("con", Ability::Constitution),
+    ("wis", Ability::Wisdom),
+    ("int", Ability::Intelligence),
+    ("cha", Ability::Charisma),
+];
+
+/// Foundry's abbreviations for skills.
+const SKILLS: [(&str, Skill); 18] = [
+    ("acr", Skill::Acrobatics),
+    ("ani", Skill::AnimalHandling),
+    ("arc", Skill::Arcana),
+    ("ath", Skill::Athletics),
+    ("dec", Skill::Deception),
+    ("his", Skill::History),
+    ("ins", Skill::Insight),
+    ("itm", Skill::Intimidation),
+    ("inv", Skill::Investigation),
+    ("med", Skill::Medicine),
+    ("nat", Skill::Nature),
+    ("prc", Skill::Perception),
+    ("prf", Skill::Performance),
+    ("per", Skill::Persuasion),
+    ("rel", Skill::Religion),
+    ("slt", Skill::SleightOfHand),
+    ("ste", Skill::Stealth),
+    ("sur", Skill::Survival),
+];
+
+/// Read a Foundry dnd5e actor, matching everything we can to Open5e
+/// and keeping the rest as homebrew.
+pub fn import_actor(json: &str, data: Open5eData) -> Result<Imported, String> {
+    let actor = serde_json::from_str::<Value>(json)
+        .map_err(|e| format!("not valid JSON ({})", e))?;
+    let system = actor
+        .get("system")
+        .or_else(|| actor.get("data"))
+        .filter(|s| s.get("abilities").is_some())
+        .ok_or("not a Foundry VTT actor")?;
+    if let Some(kind) = actor.get("type").and_then(Value::as_str) {
+        if kind != "character" {
+            return Err(format!(
+                "a Foundry VTT {}, rather than a player character",
+                kind
+            ));
+        }
+    }
+    let items = actor
+        .get("items")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let items_of = |kind: &str| {
+        items
+            .iter()
+            .filter(|i| i.get("type").and_then(Value::as_str) == Some(kind))
+            .collect::<Vec<&Value>>()
+    };
+
+    let classes = items_of("class")
+        .into_iter()
+        .map(|c| {
+            let levels = item_system(c)
+                .and_then(|s| s.get("levels"))
+                .and_then(Value::as_i64)
+                .unwrap_or(1) as i32;
+            (homebrew_class(c), levels)
+        })
+        .collect();
+
+    // Species and background are items in newer versions of Foundry,
+    // and text in older ones
+    let race = items_of("race").first().copied();
+    let species = race
+        .map(|r| text(r, "/name"))
+        .unwrap_or_else(|| text(system, "/details/race"));
+    let background_item = items_of("background").first().copied();
+    let background = background_item
+        .map(|b| text(b, "/name"))
+        .unwrap_or_else(|| text(system, "/details/background"));
+
+    let ability_scores = ABILITIES
+        .iter()
+        .map(|(abbreviation, ability)| {
+            let score = system
+                .pointer(&format!("/abilities/{}/value", abbreviation))
+                .and_then(Value::as_i64)
+                .unwrap_or(10) as i32;
+            (ability.clone(), score)
+        })
+        .collect();
+    let proficiency = |abbreviation: &str| {
+        system
+            .pointer(&format!("/skills/{}/value", abbreviation))
+            .and_then(Value::as_f64)
+            .unwrap_or_default()
+    };
+    let skills_with = |level: f64| {
+        SKILLS
+            .iter()
+            .filter(|(abbreviation, _)| proficiency(abbreviation) >= level)
+            .map(|(_, skill)| *skill)
+            .collect::<Vec<Skill>>()
+    };
+
+    let character = ForeignCharacter {
+        name: text(&actor, "/name"),
+        classes,
+        subclasses: names(&items_of("subclass")),
+        xp: system
+            .pointer("/details/xp/value")
+            .and_then(Value::as_i64)
+            .unwrap_or_default() as i32,
+        species: Some(species)
+            .filter(|s| !s.is_empty())
+            .map(|s| homebrew_species(&s, race)),
+        background: Some(background)
+            .filter(|b| !b.is_empty())
+            .map(|b| homebrew_background(&b, background_item)),
+        ability_scores,
+        skills: skills_with(1.0),
+        expertise: skills_with(2.0),
+        feats: items_of("feat")
+            .into_iter()
+            .map(|f| HomebrewFeature {
+                name: text(f, "/name"),
+                desc: description(f),
+            })
+            .collect(),
+        weapons: items_of("weapon")
+            .into_iter()
+            .map(|w| (text(w, "/name"), custom_attack(w)))
+            .collect(),
+        spells: names(&items_of("spell")),
+        inventory: ["equipment", "consumable", "tool", "loot", "backpack"]
+            .iter()
+            .flat_map(|kind| names(&items_of(kind)))
+            .collect(),
+    };
+    Ok(import(character, data))
+}
+
+/// An Open5e-style class from Foundry's class item. Foundry keeps
+/// the class's features as separate items, so only the hit die and
+/// description come across.
+fn homebrew_class(class: &Value) -> Class {
+    let system = item_system(class).cloned().unwrap_or_default();
+    // "d10" in older versions, and split up in newer ones
+    let hit_die = system
+        .get("hitDice")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .or_else(|| {
+            system
+                .pointer("/hd/denomination")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+        .and_then(|d| d.trim_start_matches('d').parse::<i32>().ok())
+        .unwrap_or(8);
+    Class {
+        desc: description(class),
+        ..new_homebrew_class(&text(class, "/name"), hit_die)
+    }
+}
+
+/// An Open5e-style species from Foundry's race item, or just its name
+/// in older versions.
+fn homebrew_species(name: &str, race: Option<&Value>) -> Species {
+    let walk = race
+        .and_then(item_system)
+        .and_then(|s| s.pointer("/movement/walk"))
+        .and_then(Value::as_i64)
+        .unwrap_or(30);
+    Species {
+        name: name.to_string(),
+        slug: homebrew_slug(name),
+        desc: race.map(description).unwrap_or_default(),
+        size: "Medium".to_string(),
+        speed: Speed { walk: walk as i32 },
+        document_slug: "homebrew".to_string(),
+        document_title: "Homebrew".to_string(),
+        ..Default::default()
+    }
+}
+
+/// An Open5e-style background from Foundry's background item, or just
+/// its name in older versions.
+fn homebrew_background(name: &str, background: Option<&Value>) -> Background {
+    Background {
+        name: name.to_string(),
+        slug: homebrew_slug(name),
+        desc: background.map(description).unwrap_or_default(),
+        document_slug: "homebrew".to_string(),
+        document_title: "Homebrew".to_string(),
+        ..Default::default()
+    }
+}
+
+/// An attack for a weapon Open5e doesn't have, from Foundry's own
+/// description of it.
+fn custom_attack(weapon: &Value) -> AttackAction {
+    let name = text(weapon, "/name");
+    let system = item_system(weapon).cloned().unwrap_or_default();
+    let weapon_type = system
+        .get("weaponType")
+        .or_else(|| system.pointer("/type/value"))
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let has_property = |property: &str| match system.get("properties") {
+        Some(Value::Array(list)) => {
+            list.iter().any(|p| p.as_str() == Some(property))
+        }
+        Some(Value::Object(map)) => {
+            map.get(property).and_then(Value::as_bool) == Some(true)
+        }
+        _ => false,
+    };
+    let range = match (
+        system.pointer("/range/value").and_then(Value::as_i64),
+        system.pointer("/range/long").and_then(Value::as_i64),
+    ) {
+        (Some(normal), Some(long)) => Some(WeaponRange {
+            normal: normal as i32,
+            long: long as i32,
+        }),
+        _ => None,
+    };
+    let ranged = weapon_type.ends_with('R');
+
+    let mut properties = vec![];
+    for (abbreviation, property) in [
+        ("fin", WeaponProperty::Finesse),
+        ("lgt", WeaponProperty::Light),
+        ("hvy", WeaponProperty::Heavy),
+        ("rch", WeaponProperty::Reach),
+        ("two", WeaponProperty::TwoHanded),
+        ("lod", WeaponProperty::Loading),
+    ] {
+        if has_property(abbreviation) {
+            properties.push(property);
+        }
+    }
+    let (damage, damage_type, versatile) = damage(&system);
+    if has_property("ver") && !versatile.is_empty() {
+        properties.push(WeaponProperty::Versatile(versatile));
+    }
+    if let Some(range) = range {
+        if has_property("thr") {
+            properties.push(WeaponProperty::Thrown(range));
+        } else if has_property("amm") {
+            properties.push(WeaponProperty::Ammunition(range));
+        }
+    }
+
+    AttackAction {
+        slug: name.to_string(),
+        name,
+        ability: match system.get("ability").and_then(Value::as_str) {
+            Some("dex") => Ability::Dexterity,
+            Some("str") => Ability::Strength,
+            _ if ranged => Ability::Dexterity,
+            _ => Ability::Strength,
+        },
+        damage_base: damage,
+        // Left empty, so the character is assumed to be proficient
+        weapon: String::new(),
+        category: String::new(),
+        attack_type: if ranged {
+            AttackType::Ranged
+        } else if has_property("thr") {
+            AttackType::MeleeOrRanged
+        } else {
+            AttackType::Melee
+        },
+        reach: if has_property("rch") { 10 } else { 5 },
+        damage_type,
+        properties,
+    }
+}
+
+/// Damage dice, damage type and versatile damage dice of a weapon,
+/// without the ability modifier Foundry adds with `@mod`.
+fn damage(system: &Value) -> (String, String, String) {
+    let without_mod = |formula: &str| {
+        formula
+            .replace("+ @mod", "")
+            .replace("+@mod", "")
+            .trim()
+            .to_string()
+    };
+    // Newer versions describe the dice
+    if let Some(base) = system.pointer("/damage/base") {
+        let dice = |part: &Value| match (
+            part.get("number").and_then(Value::as_i64),
+            part.get("denomination").and_then(Value::as_i64),
+        ) {
+            (Some(number), Some(denomination)) => {
+                format!("{}d{}", number, denomination)
+            }
+            _ => String::new(),
+        };
+        let damage_type = base
+            .pointer("/types/0")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        let versatile = system
+            .pointer("/damage/versatile")
+            .map(dice)
+            .unwrap_or_default();
+        return (dice(base), damage_type, versatile);
+    }
+    // Older ones have a list of formulas and damage types
+    (
+        without_mod(
+            system
+                .pointer("/damage/parts/0/0")
+                .and_then(Value::as_str)
+                .unwrap_or_default(),
+        ),
+        system
+            .pointer("/damage/parts/0/1")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        without_mod(
+            system
+                .pointer("/damage/versatile")
+                .and_then(Value::as_str)
+                .unwrap_or_default(),
+        ),
+    )
+}
+
+/// What goes into a Foundry actor: the character, and everything the
+/// sheet works out from it and Open5e.
+pub struct FoundryExport<'a> {
+    pub document: &'a CharacterDocument,
+    pub class_name: String,
+    pub species_name: String,
+    pub background_name: String,
+    /// Scores with ASIs applied.
+    pub ability_scores: Vec<(Ability, i32)>,
+    pub saves: Vec<Ability>,
+    pub skills: Vec<Skill>,
+    pub max_hp: i32,
+    pub features: &'a [Feature],
+}
+
+/// Write a character as a Foundry dnd5e actor, for Foundry's
+/// "Import Data" option.
+pub fn export_actor(export: &FoundryExport) -> Value {
+    let details = &export.document.details;
+    let abbreviation = |ability: &Ability| {
+        ABILITIES
+            .iter()
+            .find(|(_, a)| a == ability)
+            .map(|(abbreviation, _)| *abbreviation)
+            .unwrap_or_default()
+    };
+
+    let mut abilities = serde_json::Map::new();
+    for (ability, score) in &export.ability_scores {
+        abilities.insert(
+            abbreviation(ability).to_string(),
+            json!({
+                "value": score,
+                "proficient": export.saves.contains(ability) as i32,
+            }),
+        );
+    }
+    let mut skills = serde_json::Map::new();
+    for (skill_abbreviation, skill) in SKILLS {
+        skills.insert(
+            skill_abbreviation.to_string(),
+            json!({
+                "value": export.skills.contains(&skill) as i32,
+                "ability": abbreviation(&skill.ability()),
+            }),
+        );
+    }
+
+    let mut items = vec![];
+    if !export.class_name.is_empty() {
+        items.push(json!({
+            "name": export.class_name,
+            "type": "class",
+            "system": {
+                "identifier": details.class,
+                "levels": details.level(),
+            },
+        }));
+    }
+    if !export.species_name.is_empty() {
+        items.push(json!({
+            "name": export.species_name,
+            "type": "race",
+            "system": {},
+        }));
+    }
+    if !export.background_name.is_empty() {
+        items.push(json!({
+            "name": export.background_name,
+            "type": "background",
+            "system": {},
+        }));
+    }
+    items.extend(export.document.attack_list.iter().map(weapon_item));
+    // Hidden features are the sheet's own bookkeeping, such as
+    // proficiencies, which are already covered above
+    items.extend(
+        export
+            .features
+            .iter()
+            .filter(|f| !f.hidden && !f.name.is_empty())
+            .filter(|f| {
+                !matches!(
+                    f.feature_type,
+                    FeatureType::Option(_) | FeatureType::Fluff
+                )
+            })
+            .map(|f| {
+                json!({
+                    "name": f.name,
+                    "type": "feat",
+                    "system": {
+                        "description": { "value": parse_markdown(&f.desc) },
+                        "requirements": "",
+                    },
+                })
+            }),
+    );
+
+    json!({
+        "name": details.name,
+        "type": "character",
+        "system": {
+            "abilities": abilities,
+            "skills": skills,
+            "attributes": {
+                "hp": { "value": export.max_hp, "max": export.max_hp },
+            },
+            "details": { "xp": { "value": details.xp } },
+        },
+        "items": items,
+    })
+}
+
+/// A weapon item for an attack. Foundry works out the attack and
+/// damage bonuses itself, from the ability and `@mod`.
+fn weapon_item(attack: &AttackAction) -> Value {
+    let category = if attack.category.contains("Simple") {
+        "simple"
+    } else if attack.category.contains("Martial") {
+        "martial"
+    } else {
+        // Custom attacks, which Foundry is always proficient with
+        "natural"
+    };
+    let ranged = attack.attack_type == AttackType::Ranged;
+    let mut properties = vec![];
+    let mut range = json!({ "value": attack.reach, "units": "ft" });
+    for property in &attack.properties {
+        let abbreviation = match property {
+            WeaponProperty::Finesse => "fin",
+            WeaponProperty::Light => "lgt",
+            WeaponProperty::Heavy => "hvy",
+            WeaponProperty::Reach => "rch",
+            WeaponProperty::TwoHanded => "two",
+            WeaponProperty::Loading => "lod",
+            WeaponProperty::Versatile(_) => "ver",
+            WeaponProperty::Thrown(_) => "thr",
+            WeaponProperty::Ammunition(_) => "amm",
+            WeaponProperty::Other(_) => continue,
+        };
+        properties.push(abbreviation);
+    }
+    if let Some(weapon_range) = attack.range() {
+        range = json!({
+            "value": weapon_range.normal,
+            "long": weapon_range.long,
+            "units": "ft",
+        });
+    }
+    let with_mod = |dice: &str| format!("{} + @mod", dice);
+    json!({
+        "name": attack.name,
+        "type": "weapon",
+        "system": {
+            "weaponType": format!(
+                "{}{}",
+                category,
+                if ranged { "R" } else { "M" }
+            ),
+            "actionType": if ranged { "rwak" } else { "mwak" },
+            // Left blank for finesse weapons, so Foundry picks the
+            // better of Strength and Dexterity like we do
+            "ability": match attack.ability {
+                _ if attack.is_finesse() => "",
+                Ability::Dexterity => "dex",
+                _ => "str",
+            },
+            "damage": {
+                "parts": [[with_mod(&attack.damage_base), attack.damage_type]],
+                "versatile": attack
+                    .versatile_damage()
+                    .map(|d| with_mod(&d))
+                    .unwrap_or_default(),
+            },
+            "properties": properties,
+            "range": range,
+            "equipped": true,
+            "proficient": 1,
+        },
+    })
+}
+
+fn item_system(item: &Value) -> Option<&Value> {
+    item.get("system").or_else(|| item.get("data"))
+}
+
+fn text(value: &Value, pointer: &str) -> String {
+    value
+        .pointer(pointer)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+fn names(items: &[&Value]) -> Vec<String> {
+    items.iter().map(|i| text(i, "/name")).collect()
+}
+
+/// An item's description. Foundry writes these as HTML, which the
+/// sheet's markdown leaves out, so only the text is kept, with a
+/// blank line between paragraphs.
+fn description(item: &Value) -> String {
+    let html = item_system(item)
+        .and_then(|s| s.pointer("/description/value"))
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let mut text = String::new();
+    let mut tag = None::<String>;
+    for c in html.chars() {
+        match (&mut tag, c) {
+            (None, '<') => tag = Some(String::new()),
+            (None, c) => text.push(c),
+            (Some(name), '>') => {
+                let name = name
+                    .trim_start_matches('/')
+                    .split(|c: char| !c.is_alphanumeric())
+                    .next()
+                    .unwrap_or_default()
+                    .to_lowercase();
+                if matches!(
+                    name.as_str(),
+                    "p" | "br" | "div" | "li" | "h1" | "h2" | "h3" | "h4"
+                ) {
+                    text.push_str("\n\n");
+                }
+                tag = None;
+            }
+            (Some(name), c) => name.push(c),
+        }
+    }
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<&str>>()
+        .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::test_data::*;
+
+    #[test]
+    fn imports_foundry_actor() {
+        let (classes, species, backgrounds, weapons) =
+            ([fighter()], [dwarf()], [soldier()], [longsword()]);
+        let data = Open5eData {
+            classes: &classes,
+            species: &species,
+            backgrounds: &backgrounds,
+            weapons: &weapons,
+        };
+        let imported = import_actor(
+            include_str!("../../tests/fixtures/foundry/actor.json"),
+            data,
+        )
+        .unwrap();
+        let document = imported.document;
+        let details = &document.details;
+        assert_eq!(details.name, "Bruenor");
+        assert_eq!(details.class, "fighter");
+        assert_eq!(details.level(), 3);
+        assert_eq!(details.species, "dwarf");
+        assert_eq!(details.subspecies, "hill-dwarf");
+        assert_eq!(details.background, "soldier");
+        // Species bonuses are taken back off
+        assert_eq!(details.ability_scores.base_con, 14);
+        assert_eq!(details.ability_scores.base_wis, 11);
+        assert_eq!(details.ability_scores.base_str, 16);
+
+        // Athletics and Intimidation come from the background, so only
+        // Perception and Survival are picked from the class's skills
+        assert_eq!(document.selected_optional_features.len(), 2);
+        assert!(document
+            .selected_optional_features
+            .iter()
+            .all(|s| s.slug == "class:fighter:class_skills"));
+
+        let longsword = &document.attack_list[0];
+        assert_eq!(longsword.weapon, "Longsword");
+        let axe = &document.attack_list[1];
+        assert_eq!(axe.name, "Grandfather's Axe");
+        assert!(axe.weapon.is_empty());
+        assert_eq!(axe.damage_base, "1d12");
+        assert_eq!(axe.damage_type, "slashing");
+        assert!(axe.properties.contains(&WeaponProperty::Heavy));
+
+        assert_eq!(
+            imported.report.unmapped,
+            vec![
+                "Subclasses aren't tracked yet: Champion",
+                "Expertise isn't tracked, so these are only proficient: \
+                Survival",
+                "Nothing on the sheet gives proficiency in: Arcana",
+                "Not on the sheet, so added as homebrew features: Lucky",
+                "Not on Open5e, so added as custom attacks: Grandfather's Axe",
+                "Inventory isn't tracked yet: Rope, Hempen (50 feet)",
+            ]
+        );
+    }
+
+    #[test]
+    fn exported_actors_import_again() {
+        let (classes, species, backgrounds, weapons) =
+            ([fighter()], [dwarf()], [soldier()], [longsword()]);
+        let data = Open5eData {
+            classes: &classes,
+            species: &species,
+            backgrounds: &backgrounds,
+            weapons: &weapons,
+        };
+        let original = import_actor(
+            include_str!("../../tests/fixtures/foundry/actor.json"),
+            data,
+        )
+        .unwrap()
+        .document;
+        let features = [Feature {
+            name: "Second Wind".to_string(),
+            desc: "Regain **1d10** hit points.".to_string(),
+            level: 1,
+            ..Default::default()
+        }];
+        let actor = export_actor(&FoundryExport {
+            document: &original,
+            class_name: "Fighter".to_string(),
+            species_name: "Hill Dwarf".to_string(),
+            background_name: "Soldier".to_string(),
+            ability_scores: vec![
+                (Ability::Strength, 16),
+                (Ability::Constitution, 16),
+                (Ability::Wisdom, 12),
+            ],
+            saves: vec![Ability::Strength, Ability::Constitution],
+            skills: vec![Skill::Athletics, Skill::Perception],
+            max_hp: 28,
+            features: &features,
+        });
+        assert_eq!(
+            actor.pointer("/system/abilities/str/proficient"),
+            Some(&json!(1))
+        );
+        assert_eq!(actor.pointer("/system/skills/prc/value"), Some(&json!(1)));
+        assert_eq!(actor.pointer("/system/skills/ste/value"), Some(&json!(0)));
+        assert_eq!(
+            actor.pointer("/system/attributes/hp/max"),
+            Some(&json!(28))
+        );
+        let feat = actor["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|i| i["type"] == "feat")
+            .unwrap();
+        assert_eq!(
+            feat.pointer("/system/description/value"),
+            Some(&json!("<p>Regain <strong>1d10</strong> hit points.</p>\n"))
+        );
+
+        let imported = import_actor(&actor.to_string(), data).unwrap();
+        let document = imported.document;
+        assert_eq!(document.details.class, "fighter");
+        assert_eq!(document.details.level(), 3);
+        assert_eq!(document.details.subspecies, "hill-dwarf");
+        assert_eq!(document.details.ability_scores.base_con, 14);
+        assert!(document.attack_list == original.attack_list);
+    }
+
+    #[test]
+    fn keeps_unmatched_entries_as_homebrew() {
+        let data = no_open5e_data();
+        let imported = import_actor(
+            include_str!("../../tests/fixtures/foundry/actor.json"),
+            data,
+        )
+        .unwrap();
+        let document = imported.document;
+        let (details, homebrew) = (&document.details, &document.homebrew);
+        assert_eq!(details.class, "homebrew-fighter");
+        assert_eq!(homebrew.classes[0].base_hp(), 10);
+        assert_eq!(details.species, "homebrew-hill-dwarf");
+        assert_eq!(homebrew.species[0].name, "Hill Dwarf");
+        assert_eq!(details.background, "homebrew-soldier");
+        assert_eq!(homebrew.backgrounds[0].slug, details.background);
+
+        let lucky = &homebrew.features[0];
+        assert_eq!(lucky.name, "Lucky");
+        assert_eq!(
+            lucky.desc,
+            "You have inexplicable luck.\n\nYou have 3 luck points."
+        );
+        for line in [
+            "Fighter isn't on Open5e, so it was added as a homebrew class",
+            "Hill Dwarf isn't on Open5e, so it was added as a homebrew \
+                species",
+            "Not on the sheet, so added as homebrew features: Lucky",
+        ] {
+            assert!(
+                imported.report.unmapped.contains(&line.to_string()),
+                "{:?}",
+                imported.report.unmapped
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let data = no_open5e_data();
+        assert!(import_actor("{}", data).is_err());
+        assert!(import_actor(
+            r#"{"type": "npc", "system": {"abilities": {}}}"#,
+            data
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,546 @@
+//! Converting characters to and from other character sheet apps.
+//!
+//! Other apps store whole rules entries where we store Open5e slugs,
+//! so importing means finding the Open5e entry that matches each
+//! thing the other app has. Anything that can't be matched is kept
+//! as a custom entry where the sheet has somewhere to put one, and
+//! listed in an `ImportReport` otherwise, so the player knows what
+//! to fill in by hand.
+
+pub mod fight_club;
+pub mod foundry;
+#[cfg(test)]
+mod test_data;
+
+use crate::api::api_extensions::{
+    Feature, FeatureOptionsSelection, FeatureType,
+};
+use crate::api::api_model::{Background, Class, Species, Subspecies, Weapon};
+use crate::character_model::{
+    normalize_weapon_name, Ability, AbilityScores, AttackAction,
+    CharacterDetails, CharacterDocument, Homebrew, HomebrewFeature, Skill,
+};
+
+/// The Open5e lists a character is matched against.
+#[derive(Clone, Copy)]
+pub struct Open5eData<'a> {
+    pub classes: &'a [Class],
+    pub species: &'a [Species],
+    pub backgrounds: &'a [Background],
+    pub weapons: &'a [Weapon],
+}
+
+impl<'a> Open5eData<'a> {
+    pub fn class(&self, name: &str) -> Option<&'a Class> {
+        find_by_name(self.classes, name, |c| (&c.name, &c.slug))
+    }
+    pub fn background(&self, name: &str) -> Option<&'a Background> {
+        find_by_name(self.backgrounds, name, |b| (&b.name, &b.slug))
+    }
+    pub fn weapon(&self, name: &str) -> Option<&'a Weapon> {
+        let name = normalize_weapon_name(name);
+        self.weapons
+            .iter()
+            .find(|w| normalize_weapon_name(&w.name) == name)
+    }
+    /// Species, and subspecies if there is one, from a name that may
+    /// be either, i.e. "Hill Dwarf" or "Dwarf".
+    pub fn species(
+        &self,
+        name: &str,
+    ) -> Option<(&'a Species, Option<&'a Subspecies>)> {
+        for species in self.species {
+            if let Some(subspecies) =
+                find_by_name(&species.subraces, name, |s| (&s.name, &s.slug))
+            {
+                return Some((species, Some(subspecies)));
+            }
+        }
+        if let Some(species) =
+            find_by_name(self.species, name, |s| (&s.name, &s.slug))
+        {
+            return Some((species, None));
+        }
+        // Names like "Half-Elf (Variant)" or "Wood Elf (Mark of...)"
+        self.species
+            .iter()
+            .find(|s| contains_word(name, &s.name))
+            .map(|s| (s, None))
+    }
+
+    /// Every feature the character gets from their class, species and
+    /// background at their level. Matches `get_base_features`, for
+    /// when there's no app state to read it from.
+    pub fn features(&self, details: &CharacterDetails) -> Vec<Feature> {
+        let mut features = vec![];
+        if let Some(species) =
+            self.species.iter().find(|s| s.slug == details.species)
+        {
+            features.append(&mut species.features());
+            if let Some(subspecies) = species
+                .subraces
+                .iter()
+                .find(|s| s.slug == details.subspecies)
+            {
+                features.append(&mut subspecies.features());
+            }
+        }
+        if let Some(class) =
+            self.classes.iter().find(|c| c.slug == details.class)
+        {
+            features.append(&mut class.features());
+        }
+        if let Some(background) = self
+            .backgrounds
+            .iter()
+            .find(|b| b.slug == details.background)
+        {
+            features.append(&mut background.features());
+        }
+        features.retain(|f| f.level <= details.level());
+        features
+    }
+}
+
+/// What couldn't be brought across from another app.
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct ImportReport {
+    /// One line for each thing that was left out or changed.
+    pub unmapped: Vec<String>,
+}
+
+impl ImportReport {
+    pub fn add(&mut self, line: impl Into<String>) {
+        self.unmapped.push(line.into());
+    }
+    /// One line for a list of things left out for the same reason,
+    /// i.e. "Spells aren't tracked: Fire Bolt, Shield".
+    pub fn add_list(&mut self, reason: &str, names: &[String]) {
+        if !names.is_empty() {
+            self.add(format!("{}: {}", reason, names.join(", ")));
+        }
+    }
+}
+
+/// A character brought in from another app.
+pub struct Imported {
+    pub document: CharacterDocument,
+    pub report: ImportReport,
+}
+
+/// A character as another app describes it, before anything has been
+/// matched to Open5e. Each importer reads its app's format into one
+/// of these, and `import` does the rest. Classes, species and
+/// backgrounds come with a homebrew version built from what the app
+/// has, for if Open5e doesn't have them.
+#[derive(Default)]
+pub struct ForeignCharacter {
+    pub name: String,
+    /// Each class, and the levels the character has in it.
+    pub classes: Vec<(Class, i32)>,
+    pub subclasses: Vec<String>,
+    pub xp: i32,
+    pub species: Option<Species>,
+    pub background: Option<Background>,
+    /// Scores with bonuses from species already added.
+    pub ability_scores: Vec<(Ability, i32)>,
+    pub skills: Vec<Skill>,
+    pub expertise: Vec<Skill>,
+    pub feats: Vec<HomebrewFeature>,
+    /// Each weapon's name, and an attack for it from the app's own
+    /// description, for if Open5e doesn't have it.
+    pub weapons: Vec<(String, AttackAction)>,
+    pub spells: Vec<String>,
+    pub inventory: Vec<String>,
+}
+
+/// Match a character from another app to Open5e, keeping anything
+/// that doesn't match as homebrew.
+pub fn import(character: ForeignCharacter, data: Open5eData) -> Imported {
+    let mut report = ImportReport::default();
+    let mut homebrew = Homebrew::default();
+    let mut document = CharacterDocument::default();
+    let details = &mut document.details;
+    details.name = character.name;
+
+    // Class and level. We only track one class, so multiclassed
+    // characters get the class they have the most levels in.
+    let mut classes = character.classes;
+    classes.sort_by_key(|(_, levels)| -levels);
+    let total_level = classes.iter().map(|(_, levels)| levels).sum::<i32>();
+    let mut classes = classes.into_iter();
+    if let Some((class, _)) = classes.next() {
+        details.class = match data.class(&class.name) {
+            Some(found) => found.slug.to_string(),
+            None => {
+                report.add(added_as_homebrew(&class.name, "class"));
+                let slug = class.slug.to_string();
+                homebrew.classes.push(class);
+                slug
+            }
+        };
+    }
+    for (class, levels) in classes {
+        report.add(format!(
+            "Only one class is tracked, so {} ({} levels) was left out",
+            class.name, levels
+        ));
+    }
+    details.set_level(total_level.max(1));
+    // Keep progress towards the next level
+    let level = details.level();
+    details.xp = details.xp.max(character.xp);
+    if details.level() != level {
+        details.set_level(level);
+    }
+    report.add_list("Subclasses aren't tracked yet", &character.subclasses);
+
+    if let Some(species) = character.species {
+        match data.species(&species.name) {
+            Some((found, subspecies)) => {
+                details.species = found.slug.to_string();
+                details.subspecies =
+                    subspecies.map(|s| s.slug.to_string()).unwrap_or_default();
+            }
+            None => {
+                report.add(added_as_homebrew(&species.name, "species"));
+                details.species = species.slug.to_string();
+                homebrew.species.push(species);
+            }
+        }
+    }
+    if let Some(background) = character.background {
+        details.background = match data.background(&background.name) {
+            Some(found) => found.slug.to_string(),
+            None => {
+                report.add(added_as_homebrew(&background.name, "background"));
+                let slug = background.slug.to_string();
+                homebrew.backgrounds.push(background);
+                slug
+            }
+        };
+    }
+
+    // Other apps' scores include bonuses from species, which we add
+    // on top of the base score
+    let features = Open5eData {
+        classes: &[data.classes, &homebrew.classes].concat(),
+        species: &[data.species, &homebrew.species].concat(),
+        backgrounds: &[data.backgrounds, &homebrew.backgrounds].concat(),
+        weapons: data.weapons,
+    }
+    .features(details);
+    for (ability, score) in character.ability_scores {
+        let scores = &mut details.ability_scores;
+        match ability {
+            Ability::Strength => scores.base_str = score,
+            Ability::Dexterity => scores.base_dex = score,
+            Ability::Constitution => scores.base_con = score,
+            Ability::Wisdom => scores.base_wis = score,
+            Ability::Intelligence => scores.base_int = score,
+            Ability::Charisma => scores.base_cha = score,
+        }
+    }
+    remove_asis(&mut details.ability_scores, &features);
+
+    // Skills become choices of the features that offer them
+    let selections = &mut document.selected_optional_features;
+    let unoffered = choose_skills(&features, &character.skills, selections);
+    report.add_list(
+        "Expertise isn't tracked, so these are only proficient",
+        &skill_names(&character.expertise),
+    );
+    report.add_list(
+        "Nothing on the sheet gives proficiency in",
+        &skill_names(&unoffered),
+    );
+
+    // Feats that are already on the sheet, or are one of the sheet's
+    // choices, come across; the rest are kept as homebrew
+    let feat_names = character
+        .feats
+        .iter()
+        .map(|f| f.name.to_string())
+        .collect::<Vec<String>>();
+    let chosen = choose_named_options(&features, &feat_names, selections);
+    keep_unmatched_features(
+        character.feats,
+        &features,
+        &chosen,
+        &mut homebrew,
+        &mut report,
+    );
+
+    // Weapons become attacks, custom ones if Open5e doesn't have them
+    let mut custom_weapons = vec![];
+    for (name, custom) in character.weapons {
+        let attack = match data.weapon(&name) {
+            Some(found) => found.to_attack(),
+            None => {
+                custom_weapons.push(name.to_string());
+                custom
+            }
+        };
+        document.attack_list.push(AttackAction { name, ..attack });
+    }
+    report
+        .add_list("Not on Open5e, so added as custom attacks", &custom_weapons);
+    report.add_list("Spells aren't tracked yet", &character.spells);
+    report.add_list("Inventory isn't tracked yet", &character.inventory);
+
+    document.homebrew = homebrew;
+    Imported { document, report }
+}
+
+fn skill_names(skills: &[Skill]) -> Vec<String> {
+    skills.iter().map(Skill::to_string).collect()
+}
+
+/// Other apps store ability scores with bonuses from species already
+/// added, where we store the score before them. Take the bonuses the
+/// character's features give back off.
+fn remove_asis(scores: &mut AbilityScores, features: &[Feature]) {
+    for feature in features {
+        if let FeatureType::Asi(asi) = &feature.feature_type {
+            let score = match asi.score {
+                Ability::Strength => &mut scores.base_str,
+                Ability::Dexterity => &mut scores.base_dex,
+                Ability::Constitution => &mut scores.base_con,
+                Ability::Wisdom => &mut scores.base_wis,
+                Ability::Intelligence => &mut scores.base_int,
+                Ability::Charisma => &mut scores.base_cha,
+            };
+            *score -= asi.amount;
+        }
+    }
+}
+
+/// Make feature choices that give the character the skills they're
+/// proficient in, for those they don't already get some other way.
+/// Returns the skills nothing on the sheet offers.
+fn choose_skills(
+    features: &[Feature],
+    skills: &[Skill],
+    selections: &mut Vec<FeatureOptionsSelection>,
+) -> Vec<Skill> {
+    let granted = |skill: &Skill| {
+        features.iter().any(|f| match &f.feature_type {
+            FeatureType::SkillProficency(name) => {
+                Skill::from_string(name) == Some(*skill)
+            }
+            _ => false,
+        })
+    };
+    let mut remaining = skills
+        .iter()
+        .filter(|s| !granted(s))
+        .copied()
+        .collect::<Vec<_>>();
+    for feature in features {
+        let FeatureType::Option(options) = &feature.feature_type else {
+            continue;
+        };
+        let slug = feature.feature_slug();
+        let mut chosen = selections.iter().filter(|s| s.slug == slug).count();
+        for (index, option) in options.options.iter().enumerate() {
+            if chosen >= options.num_choices as usize {
+                break;
+            }
+            let FeatureType::SkillProficency(name) = &option.feature_type
+            else {
+                continue;
+            };
+            let Some(position) = remaining
+                .iter()
+                .position(|s| Skill::from_string(name) == Some(*s))
+            else {
+                continue;
+            };
+            remaining.remove(position);
+            selections.push(FeatureOptionsSelection {
+                slug: slug.to_string(),
+                selection: index,
+            });
+            chosen += 1;
+        }
+    }
+    remaining
+}
+
+/// Make feature choices whose option is named the same as something
+/// the character has, i.e. a fighting style. Each name is only used
+/// once. Returns the names that were used.
+fn choose_named_options(
+    features: &[Feature],
+    names: &[String],
+    selections: &mut Vec<FeatureOptionsSelection>,
+) -> Vec<String> {
+    let mut used = vec![];
+    for feature in features {
+        let FeatureType::Option(options) = &feature.feature_type else {
+            continue;
+        };
+        let slug = feature.feature_slug();
+        for (index, option) in options.options.iter().enumerate() {
+            let chosen = selections.iter().filter(|s| s.slug == slug).count();
+            if chosen >= options.num_choices as usize {
+                break;
+            }
+            if let Some(name) = names
+                .iter()
+                .filter(|n| !used.contains(*n))
+                .find(|n| same_name(n, &option.name))
+            {
+                used.push(name.to_string());
+                selections.push(FeatureOptionsSelection {
+                    slug: slug.to_string(),
+                    selection: index,
+                });
+            }
+        }
+    }
+    used
+}
+
+/// Keep features the character has that aren't on the sheet as
+/// homebrew features, i.e. feats. `features` are the ones the sheet
+/// already gives them, and `chosen` the ones used for choices.
+fn keep_unmatched_features(
+    found: Vec<HomebrewFeature>,
+    features: &[Feature],
+    chosen: &[String],
+    homebrew: &mut Homebrew,
+    report: &mut ImportReport,
+) {
+    let unmatched = found
+        .into_iter()
+        .filter(|f| !chosen.contains(&f.name))
+        .filter(|f| {
+            !features
+                .iter()
+                .any(|feature| same_name(&f.name, &feature.name))
+        })
+        .collect::<Vec<HomebrewFeature>>();
+    report.add_list(
+        "Not on the sheet, so added as homebrew features",
+        &unmatched
+            .iter()
+            .map(|f| f.name.to_string())
+            .collect::<Vec<_>>(),
+    );
+    homebrew.features.extend(unmatched);
+}
+
+fn added_as_homebrew(name: &str, kind: &str) -> String {
+    format!(
+        "{} isn't on Open5e, so it was added as a homebrew {}",
+        name, kind
+    )
+}
+
+/// A homebrew class with just what the sheet needs to work out hit
+/// points. Importers fill in whatever else the other app has.
+pub fn new_homebrew_class(name: &str, hit_die: i32) -> Class {
+    Class {
+        name: name.to_string(),
+        slug: homebrew_slug(name),
+        hit_dice: format!("1d{}", hit_die),
+        hp_at_1st_level: format!("{} + your Constitution modifier", hit_die),
+        hp_at_higher_levels: format!(
+            "1d{} (or {}) + your Constitution modifier per {} level after 1st",
+            hit_die,
+            hit_die / 2 + 1,
+            name.to_lowercase()
+        ),
+        document_slug: "homebrew".to_string(),
+        document_title: "Homebrew".to_string(),
+        ..Default::default()
+    }
+}
+
+/// Slug for a homebrew entry, kept apart from Open5e's so the two
+/// can't clash, i.e. "homebrew-blood-hunter".
+pub fn homebrew_slug(name: &str) -> String {
+    let words = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<String>>();
+    format!("homebrew-{}", words.join("-"))
+}
+
+/// Names match ignoring case, punctuation and spacing, so that
+/// "Sleight of Hand" matches "sleight-of-hand".
+pub fn same_name(a: &str, b: &str) -> bool {
+    let simplify = |s: &str| {
+        s.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect::<String>()
+    };
+    let a = simplify(a);
+    !a.is_empty() && a == simplify(b)
+}
+
+fn contains_word(haystack: &str, word: &str) -> bool {
+    haystack
+        .split(|c: char| !c.is_alphanumeric() && c != '-')
+        .any(|w| same_name(w, word))
+}
+
+/// Find an entry by its name, or failing that its slug.
+fn find_by_name<'a, T>(
+    list: &'a [T],
+    name: &str,
+    name_and_slug: impl Fn(&T) -> (&String, &String),
+) -> Option<&'a T> {
+    list.iter()
+        .find(|t| same_name(name_and_slug(t).0, name))
+        .or_else(|| list.iter().find(|t| same_name(name_and_slug(t).1, name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::api_extensions::FeatureOptions;
+
+    fn fighting_style(source_slug: &str) -> Feature {
+        let style = |name: &str| Feature {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        Feature {
+            name: "Fighting Style".to_string(),
+            source_slug: source_slug.to_string(),
+            feature_type: FeatureType::Option(FeatureOptions {
+                num_choices: 1,
+                options: vec![style("Defense"), style("Archery")],
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn uses_each_name_once() {
+        // Two features offer the same option, but the character only
+        // has it once
+        let features = [
+            fighting_style("class:fighter"),
+            fighting_style("feat:adept"),
+        ];
+        let mut selections = vec![];
+        let used = choose_named_options(
+            &features,
+            &["Archery".to_string()],
+            &mut selections,
+        );
+        assert_eq!(used, vec!["Archery"]);
+        assert_eq!(
+            selections,
+            vec![FeatureOptionsSelection {
+                slug: "class:fighter:fighting_style".to_string(),
+                selection: 1,
+            }]
+        );
+    }
+}
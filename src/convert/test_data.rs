@@ -0,0 +1,71 @@
+//! Open5e entries for the importers' tests.
+
+use super::Open5eData;
+use crate::api::api_model::{
+    ApiAsi, Background, Class, Species, Subspecies, Weapon,
+};
+
+pub fn fighter() -> Class {
+    Class {
+        name: "Fighter".to_string(),
+        slug: "fighter".to_string(),
+        prof_skills: "Choose two skills from Acrobatics, Animal \
+            Handling, Athletics, History, Insight, Intimidation, \
+            Perception, and Survival"
+            .to_string(),
+        prof_weapons: "Simple weapons, martial weapons".to_string(),
+        ..Default::default()
+    }
+}
+
+pub fn dwarf() -> Species {
+    Species {
+        name: "Dwarf".to_string(),
+        slug: "dwarf".to_string(),
+        asi: vec![ApiAsi {
+            attributes: vec!["Constitution".to_string()],
+            value: 2,
+        }],
+        subraces: vec![Subspecies {
+            name: "Hill Dwarf".to_string(),
+            slug: "hill-dwarf".to_string(),
+            asi: vec![ApiAsi {
+                attributes: vec!["Wisdom".to_string()],
+                value: 1,
+            }],
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
+
+pub fn soldier() -> Background {
+    Background {
+        name: "Soldier".to_string(),
+        slug: "soldier".to_string(),
+        skill_proficiencies: Some("Athletics, Intimidation".to_string()),
+        ..Default::default()
+    }
+}
+
+pub fn longsword() -> Weapon {
+    Weapon {
+        name: "Longsword".to_string(),
+        slug: "longsword".to_string(),
+        category: "Martial Melee Weapons".to_string(),
+        damage_dice: "1d8".to_string(),
+        damage_type: "slashing".to_string(),
+        properties: Some(vec!["versatile (1d10)".to_string()]),
+        ..Default::default()
+    }
+}
+
+/// Nothing to match against, i.e. before Open5e has loaded.
+pub fn no_open5e_data() -> Open5eData<'static> {
+    Open5eData {
+        classes: &[],
+        species: &[],
+        backgrounds: &[],
+        weapons: &[],
+    }
+}
@@ -1,6 +1,11 @@
-import DiceBox from "https://unpkg.com/@3d-dice/dice-box@1.0.8/dist/dice-box.es.min.js";
+// The dice box library is copied into the build by Trunk (see Trunk.toml),
+// so that rolling works without a connection to a CDN.
+const DICE_BOX_PATH = "dice-box/";
 
 let Box = null
+// Set if the dice box couldn't be loaded, so we don't keep retrying
+// (and waiting on) it for every roll.
+let BoxFailed = false
 const colors = [
     "#348888",
     "#22BABB",
@@ -17,26 +22,43 @@ function get_random(list) {
     return list[Math.floor(Math.random() * list.length)];
 }
 
+async function init_box() {
+    const origin = new URL(DICE_BOX_PATH, document.baseURI).href;
+    // Import the library lazily, so that if it fails to load the rest
+    // of the app still works and rolls fall back to Rust.
+    const { default: DiceBox } = await import(origin + "dice-box.es.min.js");
+    const box = new DiceBox("#dice-box", {
+        assetPath: "assets/",
+        origin: origin,
+        theme: "default",
+        themeColor: "#feea03",
+        offscreen: true,
+        scale: 6
+    });
+    await box.init();
+    return box
+}
+
+// Rolls the dice in the 3D dice box, and returns the result of each die.
+// Returns null if the dice box isn't available.
 export async function roll_dice(dice_string) {
+    if (BoxFailed) {
+        return null
+    }
     let options = {
         themeColor: get_random(colors)
     };
-    // Initialize the DiceBox runtime if we haven't initialized it already
-    if (Box == null) {
-        Box = new DiceBox("#dice-box", {
-            assetPath: "assets/",
-            origin: "https://unpkg.com/@3d-dice/dice-box@1.0.8/dist/",
-            theme: "default",
-            themeColor: "#feea03",
-            offscreen: true,
-            scale: 6
-        });
-        // We need to set the tab index for the canvas dice box will render on
-        // to -1, so that it renders above items with a tab index of 0 (such
-        // as certain list elements in Bootstrap)
-        await Box.init();
+    try {
+        // Initialize the DiceBox runtime if we haven't initialized it already
+        if (Box == null) {
+            Box = await init_box();
+        }
+        let results = await Box.roll([dice_string], options);
+        // console.log(results);
+        return results
+    } catch (error) {
+        console.warn("3D dice unavailable, rolling without them:", error);
+        BoxFailed = true
+        return null
     }
-    let results = await Box.roll([dice_string], options);
-    // console.log(results);
-    return results
 }
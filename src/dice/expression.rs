@@ -0,0 +1,544 @@
+//! Parsing and rolling of dice expressions, such as `2d6+1d4+3`,
+//! `4d6kh3`, `2d20kl1` or `1d8!`.
+//!
+//! This module doesn't depend on the browser, so rolls can be made
+//! anywhere a random number generator is available.
+
+use std::fmt::Display;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::roll_mode::RollMode;
+
+/// Upper limits to keep a typo like "1000000d6" from locking up the page.
+const MAX_DICE: u32 = 1000;
+const MAX_SIDES: u32 = 1000;
+/// How many extra dice a single exploding die may add.
+const MAX_EXPLOSIONS: u32 = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiceError {
+    Empty,
+    UnexpectedCharacter(char),
+    ExpectedNumber(usize),
+    TooManyDice(u32),
+    TooManySides(u32),
+    ZeroSides,
+    /// Asked to keep more dice than were rolled.
+    InvalidKeep(u32, u32),
+    /// A d1 that explodes would never stop rolling.
+    InvalidExplode,
+}
+impl Display for DiceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiceError::Empty => write!(f, "Empty dice expression"),
+            DiceError::UnexpectedCharacter(c) => {
+                write!(f, "Unexpected character '{}'", c)
+            }
+            DiceError::ExpectedNumber(pos) => {
+                write!(f, "Expected a number at position {}", pos)
+            }
+            DiceError::TooManyDice(n) => {
+                write!(f, "Can't roll {} dice (max {})", n, MAX_DICE)
+            }
+            DiceError::TooManySides(n) => {
+                write!(f, "Can't roll a d{} (max d{})", n, MAX_SIDES)
+            }
+            DiceError::ZeroSides => write!(f, "Dice need at least one side"),
+            DiceError::InvalidKeep(keep, count) => {
+                write!(f, "Can't keep {} of {} dice", keep, count)
+            }
+            DiceError::InvalidExplode => write!(f, "A d1 can't explode"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Keep {
+    Highest(u32),
+    Lowest(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiceTerm {
+    Dice {
+        count: u32,
+        sides: u32,
+        keep: Option<Keep>,
+        explode: bool,
+    },
+    Constant(i64),
+}
+impl Display for DiceTerm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiceTerm::Dice {
+                count,
+                sides,
+                keep,
+                explode,
+            } => {
+                write!(f, "{}d{}", count, sides)?;
+                match keep {
+                    Some(Keep::Highest(n)) => write!(f, "kh{}", n)?,
+                    Some(Keep::Lowest(n)) => write!(f, "kl{}", n)?,
+                    None => {}
+                }
+                if *explode {
+                    write!(f, "!")?;
+                }
+                Ok(())
+            }
+            DiceTerm::Constant(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+/// A parsed dice expression: a sum of dice terms and constants.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiceExpression {
+    /// Each term paired with its sign; `true` for terms
+    /// that are subtracted.
+    pub terms: Vec<(bool, DiceTerm)>,
+}
+
+impl DiceExpression {
+    pub fn parse(input: &str) -> Result<DiceExpression, DiceError> {
+        Parser::new(input).parse()
+    }
+
+    /// A d20 roll with a flat modifier, such as an ability check.
+    /// Advantage and disadvantage roll two d20s and keep one.
+    pub fn d20(modifier: i64, mode: RollMode) -> DiceExpression {
+        let (count, keep) = match mode {
+            RollMode::Normal => (1, None),
+            RollMode::Advantage => (2, Some(Keep::Highest(1))),
+            RollMode::Disadvantage => (2, Some(Keep::Lowest(1))),
+        };
+        let terms = vec![(
+            false,
+            DiceTerm::Dice {
+                count,
+                sides: 20,
+                keep,
+                explode: false,
+            },
+        )];
+        DiceExpression { terms }.plus(modifier)
+    }
+
+    /// Add a flat modifier to the end of the expression.
+    pub fn plus(mut self, modifier: i64) -> DiceExpression {
+        if modifier != 0 {
+            self.terms
+                .push((modifier < 0, DiceTerm::Constant(modifier.abs())));
+        }
+        self
+    }
+
+    /// Double the number of dice rolled, as for a critical hit.
+    /// Flat modifiers are left alone.
+    pub fn doubled(&self) -> DiceExpression {
+        let terms = self
+            .terms
+            .iter()
+            .map(|(negative, term)| match term {
+                DiceTerm::Dice {
+                    count,
+                    sides,
+                    keep,
+                    explode,
+                } => (
+                    *negative,
+                    DiceTerm::Dice {
+                        count: count * 2,
+                        sides: *sides,
+                        keep: keep.map(|k| match k {
+                            Keep::Highest(n) => Keep::Highest(n * 2),
+                            Keep::Lowest(n) => Keep::Lowest(n * 2),
+                        }),
+                        explode: *explode,
+                    },
+                ),
+                DiceTerm::Constant(n) => (*negative, DiceTerm::Constant(*n)),
+            })
+            .collect();
+        DiceExpression { terms }
+    }
+
+    /// Roll the expression with the given source of random numbers.
+    pub fn roll<R: DieRoller>(&self, roller: &mut R) -> RollResult {
+        let mut dice = vec![];
+        let mut modifier = 0;
+        for (group, (negative, term)) in self.terms.iter().enumerate() {
+            match term {
+                DiceTerm::Constant(n) => {
+                    modifier += if *negative { -n } else { *n };
+                }
+                DiceTerm::Dice {
+                    count,
+                    sides,
+                    keep,
+                    explode,
+                } => {
+                    let mut rolled = vec![];
+                    for _ in 0..*count {
+                        let mut value = roller.roll_die(*sides);
+                        rolled.push(DieRoll::new(group, *sides, value, false));
+                        let mut explosions = 0;
+                        while *explode
+                            && value == i64::from(*sides)
+                            && explosions < MAX_EXPLOSIONS
+                        {
+                            value = roller.roll_die(*sides);
+                            rolled
+                                .push(DieRoll::new(group, *sides, value, true));
+                            explosions += 1;
+                        }
+                    }
+                    if let Some(keep) = keep {
+                        apply_keep(&mut rolled, *keep);
+                    }
+                    for die in rolled.iter_mut() {
+                        die.negative = *negative;
+                    }
+                    dice.append(&mut rolled);
+                }
+            }
+        }
+        let dice_total: i64 = dice
+            .iter()
+            .filter(|d| d.kept)
+            .map(|d| if d.negative { -d.value } else { d.value })
+            .sum();
+        RollResult {
+            expression: self.to_string(),
+            dice,
+            modifier,
+            total: dice_total + modifier,
+        }
+    }
+}
+
+impl Display for DiceExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, (negative, term)) in self.terms.iter().enumerate() {
+            match (i, negative) {
+                (0, true) => write!(f, "-")?,
+                (0, false) => {}
+                (_, true) => write!(f, "-")?,
+                (_, false) => write!(f, "+")?,
+            }
+            write!(f, "{}", term)?;
+        }
+        Ok(())
+    }
+}
+
+/// Mark the dice that aren't kept. Ties are broken by roll order,
+/// so the earlier die is kept.
+fn apply_keep(rolled: &mut [DieRoll], keep: Keep) {
+    let mut order = (0..rolled.len()).collect::<Vec<usize>>();
+    let kept = match keep {
+        Keep::Highest(n) => {
+            order.sort_by_key(|&i| std::cmp::Reverse(rolled[i].value));
+            n
+        }
+        Keep::Lowest(n) => {
+            order.sort_by_key(|&i| rolled[i].value);
+            n
+        }
+    };
+    for &i in order.iter().skip(kept as usize) {
+        rolled[i].kept = false;
+    }
+}
+
+/// Anything that can roll a single die.
+pub trait DieRoller {
+    /// Roll a die with the given number of sides, returning
+    /// a value between 1 and `sides` inclusive.
+    fn roll_die(&mut self, sides: u32) -> i64;
+}
+impl<R: Rng> DieRoller for R {
+    fn roll_die(&mut self, sides: u32) -> i64 {
+        i64::from(self.gen_range(1..=sides))
+    }
+}
+
+/// The result of a single die within a roll.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DieRoll {
+    /// Index of the term in the expression this die belongs to.
+    pub group: usize,
+    pub sides: u32,
+    pub value: i64,
+    /// False for dice dropped by a keep highest/lowest.
+    pub kept: bool,
+    /// True for extra dice added by an exploding die.
+    pub exploded: bool,
+    pub negative: bool,
+}
+impl DieRoll {
+    fn new(group: usize, sides: u32, value: i64, exploded: bool) -> Self {
+        Self {
+            group,
+            sides,
+            value,
+            kept: true,
+            exploded,
+            negative: false,
+        }
+    }
+    pub fn is_nat_20(&self) -> bool {
+        self.sides == 20 && self.kept && self.value == 20
+    }
+    pub fn is_nat_1(&self) -> bool {
+        self.sides == 20 && self.kept && self.value == 1
+    }
+}
+
+/// The outcome of rolling a `DiceExpression`, with a
+/// breakdown of every die rolled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RollResult {
+    pub expression: String,
+    pub dice: Vec<DieRoll>,
+    /// Sum of all the flat modifiers in the expression.
+    pub modifier: i64,
+    pub total: i64,
+}
+impl RollResult {
+    /// The value of the first d20 kept in this roll, if any.
+    /// Used to spot natural 1s and 20s.
+    pub fn natural_d20(&self) -> Option<i64> {
+        self.dice
+            .iter()
+            .find(|d| d.sides == 20 && d.kept)
+            .map(|d| d.value)
+    }
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    len: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            chars: input.char_indices().peekable(),
+            len: input.len(),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    fn position(&mut self) -> usize {
+        self.chars.peek().map(|(i, _)| *i).unwrap_or(self.len)
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        let mut digits = String::new();
+        while let Some((_, c)) = self.chars.next_if(|(_, c)| c.is_ascii_digit())
+        {
+            digits.push(c);
+        }
+        // Saturate rather than fail so oversized numbers get
+        // a "too many dice" error instead of a confusing one.
+        if digits.is_empty() {
+            None
+        } else {
+            Some(digits.parse::<u32>().unwrap_or(u32::MAX))
+        }
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        self.chars
+            .next_if(|(_, c)| c.eq_ignore_ascii_case(&expected))
+            .is_some()
+    }
+
+    fn parse(mut self) -> Result<DiceExpression, DiceError> {
+        let mut terms = vec![];
+        self.skip_whitespace();
+        if self.chars.peek().is_none() {
+            return Err(DiceError::Empty);
+        }
+        let mut negative = self.eat('-');
+        loop {
+            self.skip_whitespace();
+            terms.push((negative, self.term()?));
+            self.skip_whitespace();
+            match self.chars.next() {
+                None => break,
+                Some((_, '+')) => negative = false,
+                Some((_, '-')) => negative = true,
+                Some((_, c)) => return Err(DiceError::UnexpectedCharacter(c)),
+            }
+        }
+        Ok(DiceExpression { terms })
+    }
+
+    fn term(&mut self) -> Result<DiceTerm, DiceError> {
+        let start = self.position();
+        let count = self.number();
+        if !self.eat('d') {
+            return match count {
+                Some(n) => Ok(DiceTerm::Constant(i64::from(n))),
+                None => Err(DiceError::ExpectedNumber(start)),
+            };
+        }
+        // "d20" is shorthand for "1d20"
+        let count = count.unwrap_or(1);
+        let position = self.position();
+        let sides = self.number().ok_or(DiceError::ExpectedNumber(position))?;
+        if count > MAX_DICE {
+            return Err(DiceError::TooManyDice(count));
+        }
+        if sides == 0 {
+            return Err(DiceError::ZeroSides);
+        }
+        if sides > MAX_SIDES {
+            return Err(DiceError::TooManySides(sides));
+        }
+
+        let mut keep = None;
+        if self.eat('k') {
+            let lowest = if self.eat('l') {
+                true
+            } else {
+                // "k3" is shorthand for "kh3"
+                self.eat('h');
+                false
+            };
+            let position = self.position();
+            let n = self.number().ok_or(DiceError::ExpectedNumber(position))?;
+            if n > count {
+                return Err(DiceError::InvalidKeep(n, count));
+            }
+            keep = Some(if lowest {
+                Keep::Lowest(n)
+            } else {
+                Keep::Highest(n)
+            });
+        }
+
+        let explode = self.eat('!');
+        if explode && sides < 2 {
+            return Err(DiceError::InvalidExplode);
+        }
+        Ok(DiceTerm::Dice {
+            count,
+            sides,
+            keep,
+            explode,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rolls the given values in order, whatever the die.
+    struct FixedRoller(Vec<i64>);
+    impl DieRoller for FixedRoller {
+        fn roll_die(&mut self, _sides: u32) -> i64 {
+            self.0.remove(0)
+        }
+    }
+
+    fn roll(expression: &str, values: &[i64]) -> RollResult {
+        DiceExpression::parse(expression)
+            .unwrap()
+            .roll(&mut FixedRoller(values.to_vec()))
+    }
+
+    #[test]
+    fn parses_expressions() {
+        assert_eq!(
+            DiceExpression::parse("2d6 + d4 - 3").unwrap().to_string(),
+            "2d6+1d4-3"
+        );
+        assert_eq!(
+            DiceExpression::parse("4D6k3").unwrap().to_string(),
+            "4d6kh3"
+        );
+        assert_eq!(
+            DiceExpression::parse("-1d8!").unwrap().to_string(),
+            "-1d8!"
+        );
+        assert_eq!(DiceExpression::parse("5").unwrap().to_string(), "5");
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        let error = |input| DiceExpression::parse(input).unwrap_err();
+        assert_eq!(error(""), DiceError::Empty);
+        assert_eq!(error("  "), DiceError::Empty);
+        assert_eq!(error("1d6x"), DiceError::UnexpectedCharacter('x'));
+        assert_eq!(error("1d"), DiceError::ExpectedNumber(2));
+        assert_eq!(error("1d6+"), DiceError::ExpectedNumber(4));
+        assert_eq!(error("1d0"), DiceError::ZeroSides);
+        assert_eq!(error("1001d6"), DiceError::TooManyDice(1001));
+        assert_eq!(error("1d1001"), DiceError::TooManySides(1001));
+        assert_eq!(error("2d20kh3"), DiceError::InvalidKeep(3, 2));
+        assert_eq!(error("1d1!"), DiceError::InvalidExplode);
+    }
+
+    #[test]
+    fn keeps_highest_and_lowest() {
+        let result = roll("4d6kh3", &[3, 1, 6, 3]);
+        assert_eq!(result.total, 12);
+        assert_eq!(
+            result.dice.iter().map(|d| d.kept).collect::<Vec<_>>(),
+            vec![true, false, true, true]
+        );
+        // Ties keep the earlier die
+        let result = roll("2d20kl1", &[7, 7]);
+        assert_eq!(result.total, 7);
+        assert!(result.dice[0].kept && !result.dice[1].kept);
+        assert_eq!(
+            DiceExpression::d20(2, RollMode::Advantage)
+                .roll(&mut FixedRoller(vec![4, 15]))
+                .total,
+            17
+        );
+    }
+
+    #[test]
+    fn explodes_dice() {
+        let result = roll("2d6!", &[6, 6, 2, 3]);
+        assert_eq!(result.total, 17);
+        assert_eq!(
+            result.dice.iter().map(|d| d.exploded).collect::<Vec<_>>(),
+            vec![false, true, true, false]
+        );
+        // Exploding stops eventually, even on a roller that always
+        // rolls the maximum
+        let result = roll("1d4!", &[4; MAX_EXPLOSIONS as usize + 1]);
+        assert_eq!(result.dice.len(), MAX_EXPLOSIONS as usize + 1);
+    }
+
+    #[test]
+    fn adds_modifiers() {
+        let result = roll("1d8+2d4-3", &[5, 1, 4]);
+        assert_eq!(result.modifier, -3);
+        assert_eq!(result.total, 7);
+        let result = roll("10-1d4", &[3]);
+        assert_eq!(result.total, 7);
+        assert!(result.dice[0].negative);
+        assert_eq!(
+            DiceExpression::parse("1d8+3")
+                .unwrap()
+                .doubled()
+                .to_string(),
+            "2d8+3"
+        );
+    }
+}
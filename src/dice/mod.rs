@@ -0,0 +1,240 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use leptos::logging::log;
+use leptos::{RwSignal, SignalUpdate};
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+pub mod expression;
+pub mod roll_mode;
+
+use expression::{DiceExpression, DieRoll, DieRoller, RollResult};
+use roll_mode::RollMode;
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+//#[serde(rename_all = "camelCase")]
+pub struct DiceResult {
+    #[serde(rename = "groupId")]
+    pub group_id: i64,
+    #[serde(rename = "rollId")]
+    pub roll_id: i64,
+    pub sides: String,
+    pub theme: String,
+    #[serde(rename = "themeColor")]
+    pub theme_color: String,
+    pub value: i64,
+}
+
+#[wasm_bindgen(module = "/src/dice.js")]
+extern "C" {
+    pub async fn roll_dice(s: &str) -> JsValue;
+}
+
+/// Whether rolls are shown in the 3D dice box. Turned off through
+/// the settings; see `set_dice_box_enabled`.
+static DICE_BOX_ENABLED: AtomicBool = AtomicBool::new(true);
+
+pub fn set_dice_box_enabled(enabled: bool) {
+    DICE_BOX_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn dice_box_enabled() -> bool {
+    DICE_BOX_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Roll dice in the 3D dice box. Returns no results if the dice box
+/// is turned off or couldn't be loaded.
+pub async fn get_dice_result(s: &str) -> Vec<DiceResult> {
+    if !dice_box_enabled() {
+        return vec![];
+    }
+    let result = roll_dice(s).await;
+    // The dice box gives us null when it isn't available, which
+    // isn't an error; the caller rolls the dice itself instead.
+    if result.is_null() {
+        return vec![];
+    }
+    if let Ok(data) = serde_wasm_bindgen::from_value::<Vec<DiceResult>>(result)
+    {
+        data
+    } else {
+        log!("Could not deserialize dice results!!");
+        vec![]
+    }
+}
+
+#[wasm_bindgen]
+pub fn rust_test(_input: JsValue) {
+    log!("Hello from Rust land!")
+}
+
+/// How many rolls are kept in the roll history.
+pub const ROLL_HISTORY_LENGTH: usize = 50;
+
+/// A finished roll, ready to be shown to the player and
+/// stored in the roll history.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RollOutcome {
+    /// What the roll was for, i.e. "Longsword: to hit"
+    pub label: String,
+    /// The expression that was rolled, i.e. "1d20+5"
+    pub expression: String,
+    pub dice: Vec<DieRoll>,
+    pub modifier: i64,
+    pub total: i64,
+    /// Set on damage rolls for critical hits, as well as
+    /// on d20 rolls that came up 20.
+    pub critical: bool,
+    /// Set if the roll couldn't be made, i.e. because the
+    /// expression was invalid.
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Effects that might apply to the roll, for the player to judge,
+    /// i.e. "Advantage on saving throws against poison (Dwarven
+    /// Resilience)".
+    #[serde(default)]
+    pub notes: Vec<String>,
+}
+impl RollOutcome {
+    fn from_result(label: String, result: &RollResult) -> Self {
+        Self {
+            label,
+            expression: result.expression.to_string(),
+            dice: result.dice.clone(),
+            modifier: result.modifier,
+            total: result.total,
+            critical: result.natural_d20() == Some(20),
+            error: None,
+            notes: vec![],
+        }
+    }
+    fn error(label: String, expression: String, error: String) -> Self {
+        Self {
+            label,
+            expression,
+            error: Some(error),
+            ..Default::default()
+        }
+    }
+}
+
+/// Add new rolls to the front of the roll history, dropping the
+/// oldest rolls once it's full.
+pub fn record_rolls(
+    history: RwSignal<Vec<RollOutcome>>,
+    mut outcomes: Vec<RollOutcome>,
+) {
+    history.update(|history| {
+        outcomes.append(history);
+        outcomes.truncate(ROLL_HISTORY_LENGTH);
+        *history = outcomes;
+    });
+}
+
+/// Random number generator for rolls. For repeatable results, roll
+/// with any other `DieRoller` instead.
+pub fn rng() -> SmallRng {
+    SmallRng::from_entropy()
+}
+
+/// Rolls dice using values that were already rolled elsewhere (i.e.
+/// by the 3D dice box), and falls back to a random number generator
+/// once those run out, or for dice that don't match.
+pub struct QueuedRoller<R: DieRoller> {
+    pub queue: VecDeque<(u32, i64)>,
+    pub fallback: R,
+}
+impl<R: DieRoller> DieRoller for QueuedRoller<R> {
+    fn roll_die(&mut self, sides: u32) -> i64 {
+        match self.queue.front() {
+            Some((s, value)) if *s == sides => {
+                let value = *value;
+                self.queue.pop_front();
+                value
+            }
+            _ => self.fallback.roll_die(sides),
+        }
+    }
+}
+
+/// Roll an expression, showing its dice in the 3D dice box. Extra dice
+/// from exploding dice, and any dice the box fails to roll, are rolled
+/// natively instead. With the dice box off or unavailable, every die
+/// is rolled natively.
+pub async fn roll_expression(expression: &DiceExpression) -> RollResult {
+    let mut queue = VecDeque::new();
+    for (_, term) in &expression.terms {
+        if let expression::DiceTerm::Dice { count, sides, .. } = term {
+            let results =
+                get_dice_result(&format!("{}d{}", count, sides)).await;
+            // If the box didn't give us one value per die, ignore them
+            // so that dice can't end up matched with the wrong term.
+            if results.len() == *count as usize {
+                queue.extend(results.iter().map(|r| (*sides, r.value)));
+            }
+        }
+    }
+    expression.roll(&mut QueuedRoller {
+        queue,
+        fallback: rng(),
+    })
+}
+
+#[derive(Clone)]
+pub struct AttackRollParams {
+    pub name: String,
+    pub to_hit: i64,
+    pub damage_dice: String,
+    pub damage_mod: i64,
+    pub damage_type: String,
+    pub mode: RollMode,
+}
+
+/// Label for a d20 roll, noting advantage or disadvantage.
+fn d20_label(label: String, mode: RollMode) -> String {
+    match mode {
+        RollMode::Normal => label,
+        _ => format!("{} ({})", label, mode.to_string().to_lowercase()),
+    }
+}
+
+/// Roll the d20 for an attack, then its damage. A natural 20
+/// doubles the damage dice.
+pub async fn roll_attack(params: AttackRollParams) -> Vec<RollOutcome> {
+    let to_hit =
+        roll_expression(&DiceExpression::d20(params.to_hit, params.mode)).await;
+    let critical = to_hit.natural_d20() == Some(20);
+    let to_hit_label =
+        d20_label(format!("{}: to hit", params.name), params.mode);
+    let damage_label =
+        format!("{}: {} damage", params.name, params.damage_type);
+    let damage = match DiceExpression::parse(&params.damage_dice) {
+        Ok(damage) => {
+            let damage = if critical { damage.doubled() } else { damage };
+            let result = roll_expression(&damage.plus(params.damage_mod)).await;
+            RollOutcome {
+                critical,
+                ..RollOutcome::from_result(damage_label, &result)
+            }
+        }
+        Err(e) => RollOutcome::error(
+            damage_label,
+            params.damage_dice.to_string(),
+            e.to_string(),
+        ),
+    };
+    vec![RollOutcome::from_result(to_hit_label, &to_hit), damage]
+}
+
+/// Roll a d20 with a modifier, such as an ability check.
+pub async fn roll_check(
+    label: String,
+    modifier: i64,
+    mode: RollMode,
+) -> RollOutcome {
+    let result = roll_expression(&DiceExpression::d20(modifier, mode)).await;
+    RollOutcome::from_result(d20_label(label, mode), &result)
+}
@@ -0,0 +1,215 @@
+//! Advantage and disadvantage, and the rules for which rolls they
+//! apply to.
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use web_sys::MouseEvent;
+
+use crate::character_model::Ability;
+
+#[derive(Default, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum RollMode {
+    #[default]
+    Normal,
+    Advantage,
+    Disadvantage,
+}
+impl RollMode {
+    /// Per-roll override from the keys held while clicking a roll:
+    /// Alt for advantage, Ctrl (or Cmd) for disadvantage.
+    pub fn from_click(e: &MouseEvent) -> Option<RollMode> {
+        if e.alt_key() {
+            Some(RollMode::Advantage)
+        } else if e.ctrl_key() || e.meta_key() {
+            Some(RollMode::Disadvantage)
+        } else {
+            None
+        }
+    }
+    /// Combine every mode that applies to a roll. If there's at least
+    /// one source of advantage and one of disadvantage, they cancel
+    /// out, no matter how many of each there are.
+    pub fn combine(modes: &[RollMode]) -> RollMode {
+        let advantage = modes.contains(&RollMode::Advantage);
+        let disadvantage = modes.contains(&RollMode::Disadvantage);
+        match (advantage, disadvantage) {
+            (true, false) => RollMode::Advantage,
+            (false, true) => RollMode::Disadvantage,
+            _ => RollMode::Normal,
+        }
+    }
+}
+impl Display for RollMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            RollMode::Normal => "Normal",
+            RollMode::Advantage => "Advantage",
+            RollMode::Disadvantage => "Disadvantage",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The kind of d20 roll being made.
+#[derive(Clone, PartialEq, Debug)]
+pub enum RollKind {
+    Attack,
+    Check(Ability),
+    Save(Ability),
+}
+
+/// Which rolls an effect applies to.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum RollTarget {
+    Attacks,
+    AllChecks,
+    AllSaves,
+    Save(Ability),
+}
+impl RollTarget {
+    pub fn applies_to(&self, kind: &RollKind) -> bool {
+        match (self, kind) {
+            (RollTarget::Attacks, RollKind::Attack) => true,
+            (RollTarget::AllChecks, RollKind::Check(_)) => true,
+            (RollTarget::AllSaves, RollKind::Save(_)) => true,
+            (RollTarget::Save(a), RollKind::Save(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+impl Display for RollTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RollTarget::Attacks => write!(f, "attack rolls"),
+            RollTarget::AllChecks => write!(f, "ability checks"),
+            RollTarget::AllSaves => write!(f, "saving throws"),
+            RollTarget::Save(a) => write!(f, "{} saving throws", a.to_string()),
+        }
+    }
+}
+
+/// Advantage or disadvantage granted by a feature or condition.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct RollModeEffect {
+    pub mode: RollMode,
+    pub target: RollTarget,
+    /// When the effect only applies in some situations, i.e. "against
+    /// poison". These aren't applied automatically, since we can't
+    /// know what a save is against, and are shown as reminders instead.
+    pub situation: Option<String>,
+    /// Name of the feature or condition this effect came from.
+    pub source: String,
+}
+impl RollModeEffect {
+    pub fn new(mode: RollMode, target: RollTarget, source: &str) -> Self {
+        Self {
+            mode,
+            target,
+            situation: None,
+            source: source.to_string(),
+        }
+    }
+    /// Whether this effect should be applied to the roll automatically.
+    pub fn forces(&self, kind: &RollKind) -> bool {
+        self.situation.is_none() && self.target.applies_to(kind)
+    }
+    /// Whether this effect might apply to the roll, depending on what
+    /// it's for. It's noted on the roll for the player to judge.
+    pub fn might_apply(&self, kind: &RollKind) -> bool {
+        self.situation.is_some() && self.target.applies_to(kind)
+    }
+}
+impl Display for RollModeEffect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} on {}", self.mode, self.target)?;
+        if let Some(situation) = &self.situation {
+            write!(f, " {}", situation)?;
+        }
+        write!(f, " ({})", self.source)
+    }
+}
+
+/// Work out the mode for a roll from the mode the player asked for,
+/// and the effects currently on the character.
+pub fn resolve_roll_mode(
+    requested: RollMode,
+    kind: &RollKind,
+    effects: &[RollModeEffect],
+) -> RollMode {
+    let mut modes = vec![requested];
+    modes.extend(effects.iter().filter(|e| e.forces(kind)).map(|e| e.mode));
+    RollMode::combine(&modes)
+}
+
+/// Notes for a roll on the effects that might apply to it, i.e.
+/// "Advantage on saving throws against poison (Dwarven Resilience)".
+pub fn roll_mode_notes(
+    kind: &RollKind,
+    effects: &[RollModeEffect],
+) -> Vec<String> {
+    effects
+        .iter()
+        .filter(|e| e.might_apply(kind))
+        .map(|e| e.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combines_modes() {
+        use RollMode::*;
+        assert_eq!(RollMode::combine(&[Normal]), Normal);
+        assert_eq!(RollMode::combine(&[Normal, Advantage]), Advantage);
+        assert_eq!(RollMode::combine(&[Disadvantage, Normal]), Disadvantage);
+        // Any advantage cancels any amount of disadvantage
+        assert_eq!(
+            RollMode::combine(&[Advantage, Disadvantage, Disadvantage]),
+            Normal
+        );
+    }
+
+    #[test]
+    fn resolves_roll_mode() {
+        let poisoned = RollModeEffect::new(
+            RollMode::Disadvantage,
+            RollTarget::AllChecks,
+            "Poisoned",
+        );
+        let resilience = RollModeEffect {
+            situation: Some("against poison".to_string()),
+            ..RollModeEffect::new(
+                RollMode::Advantage,
+                RollTarget::AllSaves,
+                "Dwarven Resilience",
+            )
+        };
+        let effects = [poisoned, resilience];
+        let check = RollKind::Check(Ability::Strength);
+        let save = RollKind::Save(Ability::Constitution);
+
+        assert_eq!(
+            resolve_roll_mode(RollMode::Normal, &check, &effects),
+            RollMode::Disadvantage
+        );
+        assert_eq!(
+            resolve_roll_mode(RollMode::Advantage, &check, &effects),
+            RollMode::Normal
+        );
+        // Situational effects aren't applied, only noted
+        assert_eq!(
+            resolve_roll_mode(RollMode::Normal, &save, &effects),
+            RollMode::Normal
+        );
+        assert_eq!(
+            roll_mode_notes(&save, &effects),
+            vec!["Advantage on saving throws against poison \
+                    (Dwarven Resilience)"
+                .to_string()]
+        );
+        assert!(roll_mode_notes(&check, &effects).is_empty());
+    }
+}
@@ -0,0 +1,84 @@
+//! Saving files to, and reading files from, the player's computer,
+//! and copying text to the clipboard.
+
+use std::time::Duration;
+
+use leptos::{logging::log, set_timeout};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, BlobPropertyBag, File, HtmlAnchorElement, Url};
+
+/// How long to keep a downloaded file's object URL around for.
+const REVOKE_DELAY: Duration = Duration::from_secs(10);
+
+/// Have the browser download some text as a file.
+pub fn download_text_file(
+    file_name: &str,
+    mime_type: &str,
+    contents: &str,
+) -> Result<(), JsValue> {
+    let parts = js_sys::Array::of1(&JsValue::from_str(contents));
+    let options = BlobPropertyBag::new();
+    options.set_type(mime_type);
+    let blob = Blob::new_with_str_sequence_and_options(&parts, &options)?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or_else(|| JsValue::from_str("No document"))?;
+    let link = document
+        .create_element("a")?
+        .dyn_into::<HtmlAnchorElement>()?;
+    link.set_href(&url);
+    link.set_download(file_name);
+    link.click();
+    // The download may not have started by the time click() returns,
+    // so only free the blob once the browser has had a chance to
+    set_timeout(
+        move || {
+            if let Err(e) = Url::revoke_object_url(&url) {
+                log!("Could not revoke {}: {:?}", url, e);
+            }
+        },
+        REVOKE_DELAY,
+    );
+    Ok(())
+}
+
+/// Read a file the player picked as text.
+pub async fn read_text_file(file: &File) -> Result<String, String> {
+    let text = JsFuture::from(file.text())
+        .await
+        .map_err(|_| format!("Could not read {}", file.name()))?;
+    text.as_string()
+        .ok_or_else(|| format!("{} isn't a text file", file.name()))
+}
+
+/// File name for a character, i.e. "Grog Strongjaw.json".
+pub fn character_file_name(name: &str, extension: &str) -> String {
+    let name = name
+        .chars()
+        .filter(|c| !r#"/\\:*?"<>|"#.contains(*c))
+        .collect::<String>();
+    let name = name.trim();
+    if name.is_empty() {
+        format!("character.{}", extension)
+    } else {
+        format!("{}.{}", name, extension)
+    }
+}
+
+/// Copy some text to the clipboard.
+pub async fn copy_to_clipboard(text: &str) -> Result<(), JsValue> {
+    // The Clipboard API is still unstable in web-sys, so call it by name
+    let navigator = web_sys::window()
+        .ok_or_else(|| JsValue::from_str("No window"))?
+        .navigator();
+    let clipboard = js_sys::Reflect::get(&navigator, &"clipboard".into())?;
+    let write_text = js_sys::Reflect::get(&clipboard, &"writeText".into())?
+        .dyn_into::<js_sys::Function>()?;
+    let promise = write_text
+        .call1(&clipboard, &JsValue::from_str(text))?
+        .dyn_into::<js_sys::Promise>()?;
+    JsFuture::from(promise).await.map(|_| ())
+}
@@ -0,0 +1,264 @@
+use crate::character_model::{Ability, CharacterDocument};
+
+/// Most changes kept in the timeline. The oldest are dropped first.
+const MAX_ENTRIES: usize = 200;
+
+/// The character as it was after a change, and what the change was.
+#[derive(Clone)]
+pub struct HistoryEntry {
+    pub label: String,
+    pub document: CharacterDocument,
+}
+
+/// Every change made to the open character this session, oldest
+/// first. Undoing moves back along the timeline without dropping
+/// anything, so undone changes can be redone until a new change
+/// is made.
+#[derive(Clone)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+    position: usize,
+}
+
+impl History {
+    /// Start a timeline for a character that was just opened.
+    pub fn new(document: CharacterDocument) -> Self {
+        Self {
+            entries: vec![HistoryEntry {
+                label: "Opened character".to_string(),
+                document,
+            }],
+            position: 0,
+        }
+    }
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+    /// Index of the entry the character is currently at.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+    pub fn current(&self) -> &CharacterDocument {
+        &self.entries[self.position].document
+    }
+    /// Add the character as it is after a change, replacing any
+    /// changes that had been undone.
+    pub fn record(&mut self, document: &CharacterDocument) {
+        if document == self.current() {
+            return;
+        }
+        let label = describe_change(self.current(), document);
+        self.entries.truncate(self.position + 1);
+        self.entries.push(HistoryEntry {
+            label,
+            document: document.clone(),
+        });
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+        self.position = self.entries.len() - 1;
+    }
+    pub fn can_undo(&self) -> bool {
+        self.position > 0
+    }
+    pub fn can_redo(&self) -> bool {
+        self.position + 1 < self.entries.len()
+    }
+    /// Step back one change, returning the character as it was.
+    pub fn undo(&mut self) -> Option<CharacterDocument> {
+        if !self.can_undo() {
+            return None;
+        }
+        self.jump_to(self.position - 1)
+    }
+    pub fn redo(&mut self) -> Option<CharacterDocument> {
+        if !self.can_redo() {
+            return None;
+        }
+        self.jump_to(self.position + 1)
+    }
+    /// Go back (or forward) to the character as it was after
+    /// the entry at `index`.
+    pub fn jump_to(&mut self, index: usize) -> Option<CharacterDocument> {
+        if index >= self.entries.len() || index == self.position {
+            return None;
+        }
+        self.position = index;
+        Some(self.current().clone())
+    }
+}
+
+/// Describe what changed between two versions of a character, i.e.
+/// "Set class to Wizard". When several things changed at once, such
+/// as the species and the subspecies it clears, the first is used.
+pub fn describe_change(
+    before: &CharacterDocument,
+    after: &CharacterDocument,
+) -> String {
+    let (old, new) = (&before.details, &after.details);
+    if old.name != new.name {
+        return if new.name.is_empty() {
+            "Cleared name".to_string()
+        } else {
+            format!("Renamed to {}", new.name)
+        };
+    }
+    let slugs = [
+        ("class", &old.class, &new.class),
+        ("species", &old.species, &new.species),
+        ("subspecies", &old.subspecies, &new.subspecies),
+        ("background", &old.background, &new.background),
+    ];
+    for (field, old, new) in slugs {
+        if old != new {
+            return if new.is_empty() {
+                format!("Cleared {}", field)
+            } else {
+                format!("Set {} to {}", field, slug_to_name(new))
+            };
+        }
+    }
+    if old.level() != new.level() {
+        return format!("Set level to {}", new.level());
+    }
+    if old.xp != new.xp {
+        return format!("Set XP to {}", new.xp);
+    }
+    for ability in Ability::ALL {
+        let (old_score, new_score) = (
+            old.ability_scores.base_score(&ability),
+            new.ability_scores.base_score(&ability),
+        );
+        if old_score != new_score {
+            return format!("Set {} to {}", ability.to_string(), new_score);
+        }
+    }
+    for condition in &new.conditions {
+        if !old.conditions.contains(condition) {
+            return format!("Added condition {}", condition);
+        }
+    }
+    for condition in &old.conditions {
+        if !new.conditions.contains(condition) {
+            return format!("Removed condition {}", condition);
+        }
+    }
+    let (old_attacks, new_attacks) = (&before.attack_list, &after.attack_list);
+    for attack in new_attacks {
+        if !old_attacks.iter().any(|a| a.slug == attack.slug) {
+            return format!("Added attack {}", attack.name);
+        }
+    }
+    for attack in old_attacks {
+        if !new_attacks.iter().any(|a| a.slug == attack.slug) {
+            return format!("Removed attack {}", attack.name);
+        }
+    }
+    if old_attacks != new_attacks {
+        return "Edited attacks".to_string();
+    }
+    if before.selected_optional_features != after.selected_optional_features {
+        return "Changed a feature choice".to_string();
+    }
+    "Edited character".to_string()
+}
+
+/// Readable name for an Open5e slug, i.e. "hill-dwarf" to "Hill Dwarf".
+fn slug_to_name(slug: &str) -> String {
+    slug.split('-')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(name: &str) -> CharacterDocument {
+        let mut document = CharacterDocument::default();
+        document.details.name = name.to_string();
+        document
+    }
+
+    fn labels(history: &History) -> Vec<String> {
+        history
+            .entries()
+            .iter()
+            .map(|e| e.label.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn records_changes() {
+        let mut history = History::new(named(""));
+        assert!(!history.can_undo() && !history.can_redo());
+
+        history.record(&named("Grog"));
+        // Nothing changed, so nothing is recorded
+        history.record(&named("Grog"));
+        history.record(&named("Grog Strongjaw"));
+        assert_eq!(
+            labels(&history),
+            vec![
+                "Opened character",
+                "Renamed to Grog",
+                "Renamed to Grog Strongjaw"
+            ]
+        );
+        assert_eq!(history.position(), 2);
+        assert!(history.current() == &named("Grog Strongjaw"));
+    }
+
+    #[test]
+    fn undoes_and_redoes() {
+        let mut history = History::new(named(""));
+        history.record(&named("Grog"));
+        history.record(&named("Pike"));
+
+        assert!(history.undo() == Some(named("Grog")));
+        assert!(history.undo() == Some(named("")));
+        assert!(history.undo().is_none());
+        assert!(history.can_redo());
+
+        assert!(history.redo() == Some(named("Grog")));
+        assert!(history.redo() == Some(named("Pike")));
+        assert!(history.redo().is_none());
+
+        assert!(history.jump_to(0) == Some(named("")));
+        assert!(history.jump_to(0).is_none());
+        assert!(history.jump_to(3).is_none());
+    }
+
+    #[test]
+    fn new_change_clears_redo() {
+        let mut history = History::new(named(""));
+        history.record(&named("Grog"));
+        history.record(&named("Pike"));
+        history.undo();
+        history.record(&named("Vex"));
+        assert!(!history.can_redo());
+        assert_eq!(
+            labels(&history),
+            vec!["Opened character", "Renamed to Grog", "Renamed to Vex"]
+        );
+    }
+
+    #[test]
+    fn drops_oldest_changes() {
+        let mut history = History::new(named(""));
+        for i in 0..MAX_ENTRIES + 10 {
+            history.record(&named(&i.to_string()));
+        }
+        assert_eq!(history.entries().len(), MAX_ENTRIES);
+        assert_eq!(history.position(), MAX_ENTRIES - 1);
+        assert_eq!(history.entries()[0].label, "Renamed to 10");
+        assert!(history.current() == &named(&(MAX_ENTRIES + 9).to_string()));
+    }
+}
@@ -0,0 +1,190 @@
+//! A record of the character at each level they've passed, so the
+//! player can see what they gained and chose at each one, and undo a
+//! level up that went wrong.
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::api::api_extensions::FeatureOptionsSelection;
+use crate::character_model::CharacterDocument;
+
+/// The character as they were at one level, kept when they level up
+/// past it.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+pub struct LevelSnapshot {
+    pub level: i32,
+    pub max_hp: i32,
+    /// Names of the features the player can see at this level.
+    pub features: Vec<String>,
+    pub selected_optional_features: Vec<FeatureOptionsSelection>,
+}
+
+impl LevelSnapshot {
+    /// What changed between this level and a later one.
+    pub fn diff(&self, later: &LevelSnapshot) -> LevelDiff {
+        LevelDiff {
+            gained: missing_from(&later.features, &self.features),
+            lost: missing_from(&self.features, &later.features),
+            hp: later.max_hp - self.max_hp,
+        }
+    }
+}
+
+/// What a character gained or lost between two levels.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct LevelDiff {
+    pub gained: Vec<String>,
+    pub lost: Vec<String>,
+    pub hp: i32,
+}
+
+/// i.e. "gained: Extra Attack, +7 HP".
+impl Display for LevelDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = vec![];
+        if !self.gained.is_empty() {
+            parts.push(format!("gained: {}", self.gained.join(", ")));
+        }
+        if !self.lost.is_empty() {
+            parts.push(format!("lost: {}", self.lost.join(", ")));
+        }
+        if self.hp != 0 {
+            parts.push(format!("{:+} HP", self.hp));
+        }
+        if parts.is_empty() {
+            write!(f, "no changes")
+        } else {
+            write!(f, "{}", parts.join(", "))
+        }
+    }
+}
+
+/// Names in `names` that aren't in `other`. Features can share a name,
+/// i.e. "Ability Score Improvement", so each one in `other` only
+/// accounts for one in `names`.
+fn missing_from(names: &[String], other: &[String]) -> Vec<String> {
+    let mut other = other.to_vec();
+    names
+        .iter()
+        .filter(|name| match other.iter().position(|o| o == *name) {
+            Some(position) => {
+                other.remove(position);
+                false
+            }
+            None => true,
+        })
+        .cloned()
+        .collect()
+}
+
+/// Change the character's level. Going up keeps `current`, the
+/// character as they are before the change, in their level history.
+/// Going down drops the history from the new level up, as the
+/// character is no longer past those levels.
+pub fn change_level(
+    document: &mut CharacterDocument,
+    current: LevelSnapshot,
+    level: i32,
+) {
+    if level > document.details.level() {
+        // Anything at this level or above is from a level up that was
+        // since undone or rolled back
+        document.level_history.retain(|s| s.level < current.level);
+        document.level_history.push(current);
+    } else {
+        document.level_history.retain(|s| s.level < level);
+    }
+    document.details.set_level(level);
+}
+
+/// Put the character back how they were at an earlier level, with
+/// the choices they'd made then. Returns false if there's no record
+/// of that level.
+pub fn roll_back_to(document: &mut CharacterDocument, level: i32) -> bool {
+    let Some(snapshot) =
+        document.level_history.iter().find(|s| s.level == level)
+    else {
+        return false;
+    };
+    document.selected_optional_features =
+        snapshot.selected_optional_features.clone();
+    document.details.set_level(level);
+    document.level_history.retain(|s| s.level < level);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(level: i32, max_hp: i32, features: &[&str]) -> LevelSnapshot {
+        LevelSnapshot {
+            level,
+            max_hp,
+            features: features.iter().map(|f| f.to_string()).collect(),
+            selected_optional_features: vec![FeatureOptionsSelection {
+                slug: "class:fighter:fighting_style".to_string(),
+                selection: level as usize,
+            }],
+        }
+    }
+
+    #[test]
+    fn describes_what_changed() {
+        let four =
+            snapshot(4, 36, &["Action Surge", "Ability Score Improvement"]);
+        let five = snapshot(
+            5,
+            43,
+            &["Action Surge", "Ability Score Improvement", "Extra Attack"],
+        );
+        assert_eq!(four.diff(&five).to_string(), "gained: Extra Attack, +7 HP");
+        assert_eq!(five.diff(&four).to_string(), "lost: Extra Attack, -7 HP");
+        // A second feature with the same name still counts
+        let eight = snapshot(
+            8,
+            64,
+            &["Ability Score Improvement", "Ability Score Improvement"],
+        );
+        assert_eq!(
+            four.diff(&eight).gained,
+            vec!["Ability Score Improvement".to_string()]
+        );
+        assert_eq!(four.diff(&four).to_string(), "no changes");
+    }
+
+    #[test]
+    fn levels_up_and_rolls_back() {
+        let mut document = CharacterDocument::default();
+        document.details.set_level(3);
+        change_level(&mut document, snapshot(3, 28, &[]), 4);
+        document.selected_optional_features.clear();
+        change_level(&mut document, snapshot(4, 36, &[]), 5);
+        assert_eq!(document.details.level(), 5);
+        assert_eq!(
+            document
+                .level_history
+                .iter()
+                .map(|s| s.level)
+                .collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+
+        // Going down records nothing, and forgets the level it's now at
+        change_level(&mut document, snapshot(5, 43, &[]), 4);
+        assert_eq!(document.level_history.len(), 1);
+        assert_eq!(document.level_history[0].level, 3);
+        assert!(!roll_back_to(&mut document, 4));
+        // Going up again records level 4 as it is now
+        change_level(&mut document, snapshot(4, 40, &[]), 5);
+        assert_eq!(document.level_history.len(), 2);
+        assert_eq!(document.level_history[1].max_hp, 40);
+
+        assert!(roll_back_to(&mut document, 3));
+        assert_eq!(document.details.level(), 3);
+        assert_eq!(document.selected_optional_features[0].selection, 3);
+        assert!(document.level_history.is_empty());
+        assert!(!roll_back_to(&mut document, 2));
+    }
+}
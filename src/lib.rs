@@ -3,13 +3,40 @@
 mod api;
 mod character_model;
 mod components;
+mod convert;
 mod dice;
+mod files;
+mod history;
+mod level_history;
 mod markdown;
+mod migrations;
 mod panels;
+mod persistence;
+mod print;
+mod proficiencies;
+mod roster;
+mod save_file;
+mod settings;
+mod share;
+mod stat_block;
+pub mod storage;
+mod sync;
+#[cfg(test)]
+mod test_data;
 
 use crate::api::FuturesWrapper;
 use crate::character_model::*;
 use crate::components::*;
+use crate::dice::roll_mode::*;
+use crate::dice::{record_rolls, roll_check, RollOutcome};
+use crate::history::History;
+use crate::level_history::LevelSnapshot;
+use crate::persistence::*;
+use crate::proficiencies::*;
+use crate::roster::Roster;
+use crate::settings::Settings;
+use crate::storage::backend;
+use crate::sync::SyncStatus;
 use api::api_extensions::*;
 use api::api_model;
 use api::api_model::Background;
@@ -18,107 +45,20 @@ use api::api_model::Subspecies;
 use panels::center_panel::CenterPanel;
 use panels::feature_panel::*;
 use panels::header_panel::HeaderPanel;
+use panels::history_panel::HistoryModal;
+use panels::level_history_panel::LevelHistoryModal;
+use panels::open5e_panel::{DataSourceModal, OfflineBanner};
+use panels::print_panel::PrintView;
 use panels::proficencies_panel::ProfPanel;
+use panels::roll_panel::RollHistoryPanel;
+use panels::roster_panel::RosterModal;
+use panels::share_panel::*;
+use panels::stat_block_panel::StatBlockModal;
 use panels::stats_panel::StatsPanel;
+use panels::sync_panel::{create_syncer, SyncModal};
 
 use leptos::{html::*, *};
-
-const CHAR_STORAGE_KEY: &str = "char_sheet_character";
-const OPTIONS_STORAGE_KEY: &str = "char_sheet_selected_optional_features";
-const ATTACKS_STORAGE_KEY: &str = "char_sheet_attack_actions";
-
-fn load_character() -> CharacterDetails {
-    if let Some(window) = web_sys::window() {
-        if let Ok(Some(local_storage)) = window.local_storage() {
-            if let Ok(Some(data)) = local_storage.get_item(CHAR_STORAGE_KEY) {
-                if let Ok(character) =
-                    serde_json::from_str::<CharacterDetails>(&data)
-                {
-                    return character;
-                }
-            }
-        }
-    }
-    CharacterDetails::new()
-}
-
-fn write_character_to_local_storage(character: RwSignal<CharacterDetails>) {
-    // Make sure we can actually correctly access local storage
-    if let Some(window) = web_sys::window() {
-        if let Ok(Some(local_storage)) = window.local_storage() {
-            character.with(|char| {
-                // Serialize the character to json
-                if let Ok(json) = serde_json::to_string(char) {
-                    // Store the json
-                    let _ = local_storage.set_item(CHAR_STORAGE_KEY, &json);
-                }
-            })
-        }
-    }
-}
-
-fn load_selected_optional_features() -> Vec<FeatureOptionsSelection> {
-    if let Some(window) = web_sys::window() {
-        if let Ok(Some(local_storage)) = window.local_storage() {
-            if let Ok(Some(data)) = local_storage.get_item(OPTIONS_STORAGE_KEY)
-            {
-                if let Ok(options) =
-                    serde_json::from_str::<Vec<FeatureOptionsSelection>>(&data)
-                {
-                    return options;
-                }
-            }
-        }
-    }
-    vec![]
-}
-
-fn write_optional_features_to_local_storage(
-    options: RwSignal<Vec<FeatureOptionsSelection>>,
-) {
-    // Make sure we can actually correctly access local storage
-    if let Some(window) = web_sys::window() {
-        if let Ok(Some(local_storage)) = window.local_storage() {
-            options.with(|options| {
-                // Serialize the character to json
-                if let Ok(json) = serde_json::to_string(options) {
-                    // Store the json
-                    let _ = local_storage.set_item(OPTIONS_STORAGE_KEY, &json);
-                }
-            })
-        }
-    }
-}
-fn write_attack_list_to_local_storage(options: RwSignal<Vec<AttackAction>>) {
-    // Make sure we can actually correctly access local storage
-    if let Some(window) = web_sys::window() {
-        if let Ok(Some(local_storage)) = window.local_storage() {
-            options.with(|options| {
-                // Serialize the character to json
-                if let Ok(json) = serde_json::to_string(options) {
-                    // Store the json
-                    let _ = local_storage.set_item(ATTACKS_STORAGE_KEY, &json);
-                }
-            })
-        }
-    }
-}
-
-fn load_attack_list() -> Vec<AttackAction> {
-    if let Some(window) = web_sys::window() {
-        if let Ok(Some(local_storage)) = window.local_storage() {
-            if let Ok(Some(data)) = local_storage.get_item(ATTACKS_STORAGE_KEY)
-            {
-                if let Ok(options) =
-                    serde_json::from_str::<Vec<AttackAction>>(&data)
-                {
-                    return options;
-                }
-            }
-        }
-    }
-    vec![]
-}
+use wasm_bindgen::JsCast;
 
 #[derive(Clone, Copy)]
 pub struct FeaturesWrapper {
@@ -127,71 +67,232 @@ pub struct FeaturesWrapper {
 }
 
 pub fn App() -> impl IntoView {
-    // Create reactive signal to store character state
-    let character = create_rw_signal(load_character());
-    // Store that state globally
-    provide_context(character);
-
-    // Update local storage whenever the character details change
-    create_effect(move |_| write_character_to_local_storage(character));
+    // A character opened from a share link is shown read-only, and
+    // kept apart from the player's own characters unless imported
+    let shared = open_shared_character();
+    let read_only = create_rw_signal(shared.is_some());
+    provide_context(ReadOnly(read_only));
+
+    // App preferences, kept separately from the character. Loaded
+    // before the roster, which is saved even for a new player, as
+    // whether there are characters yet decides the default data source.
+    // They're the player's own, so a shared character being open
+    // doesn't swap them for the defaults.
+    let home = shared
+        .as_ref()
+        .map_or_else(backend, |(_, home)| home.clone());
+    let settings: RwSignal<Settings> = create_rw_signal(load_settings(&home));
+    provide_context(settings);
+    create_effect(move |_| {
+        settings.with(|s| {
+            write_settings(&home, s);
+            dice::set_dice_box_enabled(s.dice_3d);
+        });
+    });
 
-    // Create wrapper for async access to data from Open5e
-    // and store it globally
-    provide_context(FuturesWrapper::new());
+    // Every character stored in the browser, and which one is open
+    let roster: RwSignal<Roster> = create_rw_signal(load_roster(&backend()));
+    provide_context(roster);
+    create_effect(move |_| roster.with(|r| write_roster(&backend(), r)));
+    // Id of the open character, for the storage effects below. Don't
+    // track it: when switching characters, the roster changes before
+    // the character data is swapped, and we don't want to write the
+    // old character's data under the new id.
+    let current_id = move || roster.with_untracked(|r| r.current.to_string());
+
+    // Create reactive signal to store character state. Everything
+    // about the character lives in this one document, so that it can
+    // be saved, exported or swapped out as a unit.
+    let document = create_rw_signal(match &shared {
+        Some((shared, _)) => shared.clone(),
+        None => load_document(&backend(), &current_id()),
+    });
+    // Store that state globally
+    provide_context(document);
 
     // TODO: Fix bug where selected optional features for a class are
     // retained if you change classes?
-    let selected_optional_features: RwSignal<Vec<FeatureOptionsSelection>> =
-        create_rw_signal(load_selected_optional_features());
-    provide_context(selected_optional_features);
 
-    // Update local storage when the selected optional features change
+    // Changes made to the character this session, for undo and redo
+    let history = create_rw_signal(History::new(document.get_untracked()));
+    provide_context(history);
     create_effect(move |_| {
-        write_optional_features_to_local_storage(selected_optional_features)
+        document.with(|d| {
+            // Undoing sets the document to the current point in the
+            // history, so there's nothing to record
+            if history.with_untracked(|h| h.current() != d) {
+                history.update(|h| h.record(d));
+            }
+        })
+    });
+    // Ctrl+Z to undo, Ctrl+Shift+Z to redo
+    let _ = window_event_listener(ev::keydown, move |e| {
+        if !(e.ctrl_key() || e.meta_key()) || !e.key().eq_ignore_ascii_case("z")
+        {
+            return;
+        }
+        // Leave undo in text boxes to the browser
+        let in_text_box = e
+            .target()
+            .and_then(|t| t.dyn_into::<web_sys::Element>().ok())
+            .is_some_and(|t| {
+                matches!(t.tag_name().as_str(), "INPUT" | "TEXTAREA")
+            });
+        if in_text_box || read_only.get_untracked() {
+            return;
+        }
+        e.prevent_default();
+        if e.shift_key() {
+            time_travel(history, document, History::redo)
+        } else {
+            time_travel(history, document, History::undo)
+        }
     });
 
+    // Update local storage whenever the character changes
+    create_effect(move |_| {
+        document.with(|d| write_document(&backend(), &current_id(), d))
+    });
+
+    // Keep the name in the roster in sync with the character
+    create_effect(move |_| {
+        let name = document.with(|d| d.details.name.to_string());
+        let id = current_id();
+        let changed = roster
+            .with_untracked(|r| r.entry(&id).is_some_and(|e| e.name != name));
+        if changed {
+            roster.update(|r| r.rename(&id, &name));
+        }
+    });
+
+    // Create wrapper for async access to the bundled SRD, or to
+    // Open5e if that's picked, and store it globally
+    let open5e_url =
+        create_memo(move |_| settings.with(|s| s.data.open5e_base_url()));
+    provide_context(FuturesWrapper::new(open5e_url.into()));
+
     provide_context(AbilityScoresReactive {
-        ability_scores: create_read_slice(character, |c| {
-            c.ability_scores.clone()
+        ability_scores: create_read_slice(document, |d| {
+            d.details.ability_scores.clone()
         }),
         asis: get_current_asis(),
     });
 
-    let attack_list: RwSignal<Vec<AttackAction>> =
-        create_rw_signal(load_attack_list());
-    provide_context(attack_list);
-    create_effect(move |_| write_attack_list_to_local_storage(attack_list));
+    // Syncing with a sync server, if one is set up
+    let sync_status: RwSignal<SyncStatus> = create_rw_signal(SyncStatus::Idle);
+    provide_context(sync_status);
+    provide_context(create_syncer());
+
+    // Default roll mode, used unless a roll is made with a modifier key
+    let roll_mode: RwSignal<RollMode> = create_rw_signal(RollMode::Normal);
+    provide_context(roll_mode);
+
+    // Recent rolls, newest first, shown in the roll history panel
+    let roll_history: RwSignal<Vec<RollOutcome>> =
+        create_rw_signal(load_roll_history(&backend()));
+    provide_context(roll_history);
+    create_effect(move |_| {
+        roll_history.with(|h| write_roll_history(&backend(), h))
+    });
+
+    // Whether the sheet is shown laid out for printing
+    let printing = create_rw_signal(false);
 
     // ==============
     // RENDER
     // ==============
     vec![
-        div()
-            .id("dice-box")
-            .classes("position-absolute top-0 start-0 w-100 h-100 pe-none"),
-        div().classes("position-absolute top-0 start-0").child(
-            a().child("Reset").on(ev::click, move |_| {
-                // Reset all global data to default
-                character.update(|c| *c = CharacterDetails::new());
-                selected_optional_features.update(|s| *s = vec![]);
-                attack_list.update(|a| *a = vec![])
-            }),
+        div().id("dice-box").classes(
+            "position-absolute top-0 start-0 w-100 h-100 pe-none d-print-none",
         ),
-        HeaderPanel(),
-        // Stats row
-        div().classes("container").child(StatsPanel()),
-        div().attr("class", "container").child(
-            GridRow()
-                // Left column
-                .child(GridCol().child(ProfPanel()))
-                // Center column
-                .child(CenterColumn())
-                // Right column
-                .child(RightColumn()),
+        div()
+            .classes(
+                "position-absolute top-0 start-0 d-flex gap-3 d-print-none",
+            )
+            .class("d-none", move || read_only() || printing())
+            .child(
+                a().child("Characters")
+                    .attr("role", "button")
+                    .attr("data-bs-toggle", "modal")
+                    .attr("data-bs-target", "#rosterModal"),
+            )
+            .child(
+                a().child("History")
+                    .attr("role", "button")
+                    .attr("data-bs-toggle", "modal")
+                    .attr("data-bs-target", "#historyModal"),
+            )
+            .child(
+                a().child("Levels")
+                    .attr("role", "button")
+                    .attr("data-bs-toggle", "modal")
+                    .attr("data-bs-target", "#levelHistoryModal"),
+            )
+            .child(
+                a().child("Sync")
+                    .attr("role", "button")
+                    .attr("data-bs-toggle", "modal")
+                    .attr("data-bs-target", "#syncModal"),
+            )
+            .child(
+                a().child("Share")
+                    .attr("role", "button")
+                    .attr("data-bs-toggle", "modal")
+                    .attr("data-bs-target", "#shareModal"),
+            )
+            .child(
+                a().child("Stat Block")
+                    .attr("role", "button")
+                    .attr("data-bs-toggle", "modal")
+                    .attr("data-bs-target", "#statBlockModal"),
+            )
+            .child(
+                a().child("Data")
+                    .attr("role", "button")
+                    .attr("data-bs-toggle", "modal")
+                    .attr("data-bs-target", "#dataSourceModal"),
+            )
+            .child(
+                a().child("Print")
+                    .attr("role", "button")
+                    .on(ev::click, move |_| printing.set(true)),
+            ),
+        RosterModal(),
+        HistoryModal(),
+        SyncModal(),
+        ShareModal(),
+        StatBlockModal(),
+        LevelHistoryModal(),
+        DataSourceModal(),
+        match shared {
+            Some((_, home)) => SharedCharacterBanner(home),
+            None => div(),
+        },
+        OfflineBanner(),
+        // Everything on the sheet is disabled for shared characters
+        div().class("d-none", printing).child(
+            fieldset()
+                .prop("disabled", read_only)
+                .child(HeaderPanel())
+                // Stats row
+                .child(div().classes("container").child(StatsPanel()))
+                .child(
+                    div().attr("class", "container").child(
+                        GridRow()
+                            // Left column
+                            .child(GridCol().child(ProfPanel()))
+                            // Center column
+                            .child(CenterColumn())
+                            // Right column
+                            .child(RightColumn()),
+                    ),
+                ),
         ),
+        div().child(move || printing().then(|| PrintView(printing))),
         // OptionSelectionModal(),
+        RollHistoryPanel(),
         div()
-            .classes("position-absolute bottom-0 end-0 p-3 z-2")
+            .classes("position-absolute bottom-0 end-0 p-3 z-2 d-print-none")
             .child(
                 a().child(
                     img()
@@ -207,11 +308,14 @@ pub fn App() -> impl IntoView {
 
 pub fn CenterColumn() -> HtmlDiv {
     GridCol()
-        .child(
-            div()
-                .classes("container border rounded pt-2 mb-2")
-                .child(GridRow().child([ProfBonusBox(), ACBox(), HPBox()])),
-        )
+        .child(div().classes("container border rounded pt-2 mb-2").child(
+            GridRow().child([
+                ProfBonusBox(),
+                InitiativeBox(),
+                ACBox(),
+                HPBox(),
+            ]),
+        ))
         .child(
             BoxedColumnFlexible()
                 .style("height", "49.5vh")
@@ -239,6 +343,34 @@ fn ProfBonusBox() -> HtmlElement<Div> {
             )
         )
 }
+fn InitiativeBox() -> HtmlElement<Div> {
+    let ability_scores = expect_context::<AbilityScoresReactive>();
+    let initiative = Signal::derive(move || ability_scores.dex_mod());
+    let roll = roll_check_on_click(
+        "Initiative".to_string(),
+        initiative,
+        RollKind::Check(Ability::Dexterity),
+    );
+    GridCol().child(
+        div()
+            .classes("d-flex flex-column align-items-center")
+            .child("Initiative")
+            .child(
+                div()
+                .classes("border rounded my-auto d-flex align-items-center justify-content-center")
+                .style("width", "4rem")
+                .style("height", "4rem")
+                .style("text-align", "center")
+                .style("cursor", "pointer")
+                .attr("title", "Alt+click: advantage, Ctrl+click: disadvantage")
+                .on(ev::click, roll)
+                .child(
+                    h2()
+                        .child(move || format!("{:+}", initiative()))
+                        .style("margin-top", "-10%")),
+            )
+    )
+}
 fn HPBox() -> HtmlElement<Div> {
     GridCol().child(
         div()
@@ -304,46 +436,115 @@ pub fn RightColumn() -> HtmlDiv {
     )
 }
 
+/// Open a character from the roster, swapping its document into
+/// the character context.
+pub fn get_character_switcher() -> impl Fn(&str) + Copy {
+    let roster = expect_context::<RwSignal<Roster>>();
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    let history = expect_context::<RwSignal<History>>();
+    move |id: &str| {
+        roster.update(|r| r.current = id.to_string());
+        // Each character gets its own history, which starts when
+        // it's opened
+        let opened = load_document(&backend(), id);
+        history.set(History::new(opened.clone()));
+        document.set(opened);
+    }
+}
+
+/// Move through the open character's change history, e.g. to undo,
+/// and show the character as it was at that point.
+pub fn time_travel(
+    history: RwSignal<History>,
+    document: RwSignal<CharacterDocument>,
+    step: impl FnOnce(&mut History) -> Option<CharacterDocument>,
+) {
+    let mut target = None;
+    history.update(|h| target = step(h));
+    if let Some(target) = target {
+        document.set(target);
+    }
+}
+
 pub fn get_prof_bonus() -> Signal<i32> {
-    let character = expect_context::<RwSignal<CharacterDetails>>();
-    create_read_slice(character, CharacterDetails::prof_bonus)
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    create_read_slice(document, |d| d.details.prof_bonus())
 }
 
 pub fn get_level() -> Signal<i32> {
-    let character = expect_context::<RwSignal<CharacterDetails>>();
-    create_read_slice(character, CharacterDetails::level)
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    create_read_slice(document, |d| d.details.level())
 }
 
 pub fn get_species() -> Signal<String> {
-    let character = expect_context::<RwSignal<CharacterDetails>>();
-    create_read_slice(character, |c| c.species.to_string())
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    create_read_slice(document, |d| d.details.species.to_string())
 }
 
 pub fn get_subspecies() -> Signal<String> {
-    let character = expect_context::<RwSignal<CharacterDetails>>();
-    create_read_slice(character, |c| c.subspecies.to_string())
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    create_read_slice(document, |d| d.details.subspecies.to_string())
 }
 
 pub fn set_subspecies() -> SignalSetter<String> {
-    let character = expect_context::<RwSignal<CharacterDetails>>();
-    create_write_slice(character, |c, v| c.subspecies = v)
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    create_write_slice(document, |d, v| d.details.subspecies = v)
 }
 
 pub fn get_class() -> Signal<String> {
-    let character = expect_context::<RwSignal<CharacterDetails>>();
-    create_read_slice(character, |c| c.class.to_string())
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    create_read_slice(document, |d| d.details.class.to_string())
 }
 
 pub fn get_background() -> Signal<String> {
-    let character = expect_context::<RwSignal<CharacterDetails>>();
-    create_read_slice(character, |c| c.background.to_string())
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    create_read_slice(document, |d| d.details.background.to_string())
 }
 
-pub fn get_current_species() -> Signal<Option<Species>> {
+/// Every species that can be picked: the SRD's or Open5e's, then the
+/// character's homebrew ones. `None` until they've loaded.
+pub fn get_species_list() -> Signal<Option<Vec<Species>>> {
     let api_data = expect_context::<FuturesWrapper>();
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    Signal::derive(move || {
+        let mut species_list = api_data.species.get()?;
+        document
+            .with(|d| species_list.extend(d.homebrew.species.iter().cloned()));
+        Some(species_list)
+    })
+}
+
+/// Every class that can be picked: the SRD's or Open5e's, then the
+/// character's homebrew ones. `None` until they've loaded.
+pub fn get_class_list() -> Signal<Option<Vec<api_model::Class>>> {
+    let api_data = expect_context::<FuturesWrapper>();
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    Signal::derive(move || {
+        let mut class_list = api_data.classes.get()?;
+        document
+            .with(|d| class_list.extend(d.homebrew.classes.iter().cloned()));
+        Some(class_list)
+    })
+}
+
+/// Every background that can be picked: the SRD's or Open5e's, then
+/// the character's homebrew ones. `None` until they've loaded.
+pub fn get_background_list() -> Signal<Option<Vec<Background>>> {
+    let api_data = expect_context::<FuturesWrapper>();
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    Signal::derive(move || {
+        let mut background_list = api_data.backgrounds.get()?;
+        document.with(|d| {
+            background_list.extend(d.homebrew.backgrounds.iter().cloned())
+        });
+        Some(background_list)
+    })
+}
+
+pub fn get_current_species() -> Signal<Option<Species>> {
     Signal::derive(move || {
         let species = get_species()();
-        if let Some(species_list) = api_data.species.get() {
+        if let Some(species_list) = get_species_list()() {
             species_list.into_iter().find(|s| s.slug == species)
         } else {
             None
@@ -365,10 +566,9 @@ pub fn get_current_subspecies() -> Signal<Option<Subspecies>> {
 }
 
 pub fn get_current_class() -> Signal<Option<api_model::Class>> {
-    let api_data = expect_context::<FuturesWrapper>();
     Signal::derive(move || {
         let class = get_class()();
-        if let Some(class_list) = api_data.classes.get() {
+        if let Some(class_list) = get_class_list()() {
             class_list.into_iter().find(|s| s.slug == class)
         } else {
             None
@@ -377,10 +577,9 @@ pub fn get_current_class() -> Signal<Option<api_model::Class>> {
 }
 
 pub fn get_current_background() -> Signal<Option<Background>> {
-    let api_data = expect_context::<FuturesWrapper>();
     Signal::derive(move || {
         let background = get_background()();
-        if let Some(background_list) = api_data.backgrounds.get() {
+        if let Some(background_list) = get_background_list()() {
             background_list.into_iter().find(|s| s.slug == background)
         } else {
             None
@@ -409,6 +608,25 @@ pub fn get_max_hp() -> Signal<i32> {
     get_current_hp()
 }
 
+/// The character as they are now, to keep when they level up.
+pub fn get_level_snapshot() -> Signal<LevelSnapshot> {
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    let (features, max_hp) = (get_current_features(), get_max_hp());
+    Signal::derive(move || LevelSnapshot {
+        level: get_level()(),
+        max_hp: max_hp(),
+        features: features()
+            .into_iter()
+            .filter(|f| {
+                !f.hidden && !matches!(f.feature_type, FeatureType::Option(_))
+            })
+            .map(|f| f.name)
+            .collect(),
+        selected_optional_features: document
+            .with(|d| d.selected_optional_features.clone()),
+    })
+}
+
 pub fn get_base_features() -> Signal<Vec<Feature>> {
     Signal::derive(move || {
         let mut features_out: Vec<Feature> = vec![];
@@ -463,14 +681,13 @@ pub fn get_optional_features() -> Signal<Vec<(String, FeatureOptions)>> {
 }
 
 pub fn get_current_features() -> Signal<Vec<Feature>> {
-    let selected_optional_features =
-        expect_context::<RwSignal<Vec<FeatureOptionsSelection>>>();
+    let document = expect_context::<RwSignal<CharacterDocument>>();
 
     Signal::derive(move || {
         let mut features_out: Vec<Feature> = get_base_features()();
 
-        selected_optional_features.with(|selected| {
-            for select in selected {
+        document.with(|d| {
+            for select in &d.selected_optional_features {
                 let op_features = get_optional_features()();
                 let option = op_features.iter().find_map(|(slug, op_feat)| {
                     if select.slug.contains(&slug.to_string()) {
@@ -488,6 +705,9 @@ pub fn get_current_features() -> Signal<Vec<Feature>> {
                     }
                 }
             }
+            features_out.extend(
+                d.homebrew.features.iter().map(HomebrewFeature::to_feature),
+            );
         });
         features_out
     })
@@ -508,17 +728,120 @@ pub fn get_current_asis() -> Signal<Vec<CharacterAsi>> {
     })
 }
 
+/// Abilities the character is proficient in saving throws for.
+pub fn get_save_proficiencies() -> Signal<Vec<Ability>> {
+    let features = get_current_features();
+    Signal::derive(move || features.with(|f| save_proficiencies(f)))
+}
+
+/// Every skill proficiency, including ones that aren't standard skills.
 pub fn get_skill_proficencies() -> Signal<Vec<String>> {
+    let features = get_current_features();
+    Signal::derive(move || features.with(|f| skill_proficiency_names(f)))
+}
+
+pub fn get_weapon_proficiencies() -> Signal<Vec<WeaponProficiency>> {
     Signal::derive(move || {
         get_current_features()()
             .into_iter()
             .filter_map(|f| {
-                if let FeatureType::SkillProficency(prof) = f.feature_type {
+                if let FeatureType::WeaponProficiency(prof) = f.feature_type {
                     Some(prof)
                 } else {
                     None
                 }
             })
-            .collect::<Vec<String>>()
+            .collect::<Vec<WeaponProficiency>>()
+    })
+}
+
+/// Advantage and disadvantage from the character's features
+/// and current conditions.
+pub fn get_roll_mode_effects() -> Signal<Vec<RollModeEffect>> {
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    Signal::derive(move || {
+        let mut effects = get_current_features()()
+            .into_iter()
+            .filter_map(|f| {
+                if let FeatureType::RollMode(effect) = f.feature_type {
+                    Some(effect)
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<RollModeEffect>>();
+        document.with(|d| {
+            for condition in &d.details.conditions {
+                effects.append(&mut condition.roll_mode_effects());
+            }
+        });
+        effects
     })
 }
+
+/// Mode to use for a roll made by clicking on something. Modifier
+/// keys held during the click override the default roll mode, and
+/// effects on the character are applied on top of that. The kind of
+/// roll can be a signal, i.e. for a check whose ability is picked
+/// per roll.
+pub fn roll_mode_on_click(
+    kind: impl Into<MaybeSignal<RollKind>>,
+) -> impl Fn(&ev::MouseEvent) -> RollMode + Clone {
+    let kind = kind.into();
+    let default_mode = expect_context::<RwSignal<RollMode>>();
+    let effects = get_roll_mode_effects();
+    move |e| {
+        let requested =
+            RollMode::from_click(e).unwrap_or(default_mode.get_untracked());
+        resolve_roll_mode(
+            requested,
+            &kind.get_untracked(),
+            &effects.get_untracked(),
+        )
+    }
+}
+
+/// Click handler that rolls a d20 check with the given bonus and
+/// records it in the roll history.
+pub fn roll_check_on_click(
+    label: String,
+    bonus: Signal<i32>,
+    kind: impl Into<MaybeSignal<RollKind>>,
+) -> impl Fn(ev::MouseEvent) + Clone {
+    let kind = kind.into();
+    let roll_history = expect_context::<RwSignal<Vec<RollOutcome>>>();
+    let effects = get_roll_mode_effects();
+    let roll_mode = roll_mode_on_click(kind.clone());
+    let notes = move || {
+        roll_mode_notes(&kind.get_untracked(), &effects.get_untracked())
+    };
+    let roll = create_action(
+        move |(label, bonus, mode, notes): &(
+            String,
+            i64,
+            RollMode,
+            Vec<String>,
+        )| {
+            // Get a copy of the input values so that we can move them into
+            // the async block below.
+            let (label, bonus, mode, notes) =
+                (label.clone(), *bonus, *mode, notes.clone());
+            async move {
+                let outcome = roll_check(label, bonus, mode).await;
+                record_rolls(
+                    roll_history,
+                    vec![RollOutcome { notes, ..outcome }],
+                );
+            }
+        },
+    );
+    move |e| {
+        let mode = roll_mode(&e);
+        roll.dispatch((
+            label.clone(),
+            i64::from(bonus.get_untracked()),
+            mode,
+            notes(),
+        ))
+    }
+}
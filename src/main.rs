@@ -4,5 +4,9 @@ use leptos::*;
 fn main() {
     _ = console_log::init_with_level(log::Level::Debug);
     console_error_panic_hook::set_once();
-    mount_to_body(App)
+    // Storage has to be ready before the app loads the character
+    spawn_local(async {
+        goblin_sheet::storage::open_preferred_backend().await;
+        mount_to_body(App)
+    })
 }
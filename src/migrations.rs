@@ -0,0 +1,383 @@
+//! Versioning for saved characters. Everything we persist about a
+//! character is stored with the schema version it was written with,
+//! and older data is brought up to date by running it through a
+//! chain of migrations, one per version.
+//!
+//! To change the format of saved data: bump `SCHEMA_VERSION`, add a
+//! migration from the previous version to each list below, and add
+//! fixtures of the previous version to `tests/fixtures`.
+
+use std::fmt::Display;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Version of the format characters are currently saved in.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Turns data saved with one schema version into the next version.
+pub type Migration = fn(Value) -> Result<Value, String>;
+
+/// Migrations for the `CharacterDocument`. The migration at index `i`
+/// takes version `i` to version `i + 1`.
+///
+/// Before version 2 the document was stored as three separate parts;
+/// see `assemble_split_document`.
+pub const DOCUMENT_MIGRATIONS: [Migration; SCHEMA_VERSION as usize] =
+    [document_v0_to_v1, document_v1_to_v2];
+/// Migrations for exported character files.
+pub const SAVE_FILE_MIGRATIONS: [Migration; SCHEMA_VERSION as usize] =
+    [save_file_v0_to_v1, save_file_v1_to_v2];
+
+/// Data loaded from storage, along with the version it was saved with.
+pub struct Migrated<T> {
+    pub data: T,
+    pub from_version: u32,
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    NotJson(serde_json::Error),
+    /// Saved by a newer version of the app than this one.
+    TooNew(u32),
+    Migration(u32, String),
+    Invalid(serde_json::Error),
+}
+impl Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::NotJson(e) => write!(f, "not valid JSON ({})", e),
+            LoadError::TooNew(version) => write!(
+                f,
+                "saved by a newer version of Goblin Sheet (schema version \
+                {}, this version understands up to {})",
+                version, SCHEMA_VERSION
+            ),
+            LoadError::Migration(version, e) => write!(
+                f,
+                "could not upgrade from schema version {}: {}",
+                version, e
+            ),
+            LoadError::Invalid(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Wrap data with the current schema version, for storage.
+pub fn to_versioned_json<T: Serialize>(
+    data: &T,
+) -> Result<String, serde_json::Error> {
+    serde_json::to_string(&to_versioned_value(data)?)
+}
+
+pub fn to_versioned_value<T: Serialize>(
+    data: &T,
+) -> Result<Value, serde_json::Error> {
+    Ok(json!({
+        "version": SCHEMA_VERSION,
+        "data": serde_json::to_value(data)?,
+    }))
+}
+
+/// Read data written by `to_versioned_json`, migrating it if it was
+/// saved with an older schema. Data from before versioning was added
+/// isn't wrapped, and is treated as version 0.
+pub fn from_versioned_json<T: DeserializeOwned>(
+    raw: &str,
+    migrations: &[Migration],
+) -> Result<Migrated<T>, LoadError> {
+    let value =
+        serde_json::from_str::<Value>(raw).map_err(LoadError::NotJson)?;
+    from_versioned_value(value, migrations)
+}
+
+/// As `from_versioned_json`, for data that's already been parsed.
+pub fn from_versioned_value<T: DeserializeOwned>(
+    value: Value,
+    migrations: &[Migration],
+) -> Result<Migrated<T>, LoadError> {
+    let (version, data) = unwrap_versioned(value);
+    let data = migrate(data, version, migrations)?;
+    Ok(Migrated {
+        data: serde_json::from_value::<T>(data).map_err(LoadError::Invalid)?,
+        from_version: version,
+    })
+}
+
+/// Split stored data into its version and the data itself.
+pub fn unwrap_versioned(value: Value) -> (u32, Value) {
+    match value {
+        Value::Object(mut map)
+            if map.len() == 2 && map.contains_key("data") =>
+        {
+            match map.get("version").and_then(Value::as_u64) {
+                Some(version) => {
+                    (version as u32, map.remove("data").unwrap_or_default())
+                }
+                None => (0, Value::Object(map)),
+            }
+        }
+        other => (0, other),
+    }
+}
+
+/// Before version 2, the details, option selections and attacks were
+/// each stored on their own. Put them together into a document, with
+/// the version of the oldest part, so that the result can be run
+/// through `DOCUMENT_MIGRATIONS`.
+pub fn assemble_split_document(
+    details: Value,
+    options: Option<Value>,
+    attacks: Option<Value>,
+) -> (u32, Value) {
+    let (mut version, details) = unwrap_versioned(details);
+    let mut part = |value: Option<Value>| match value {
+        Some(value) => {
+            let (part_version, data) = unwrap_versioned(value);
+            version = version.min(part_version);
+            data
+        }
+        None => json!([]),
+    };
+    let options = part(options);
+    let attacks = part(attacks);
+    let document = json!({
+        "details": details,
+        "selected_optional_features": options,
+        "attack_list": attacks,
+    });
+    (version.min(1), document)
+}
+
+/// Run data saved with the given version through every migration
+/// needed to bring it up to the current version.
+pub fn migrate(
+    mut data: Value,
+    version: u32,
+    migrations: &[Migration],
+) -> Result<Value, LoadError> {
+    if version > SCHEMA_VERSION {
+        return Err(LoadError::TooNew(version));
+    }
+    for (from, migration) in
+        migrations.iter().enumerate().skip(version as usize)
+    {
+        data = migration(data)
+            .map_err(|e| LoadError::Migration(from as u32, e))?;
+    }
+    Ok(data)
+}
+
+fn as_object(
+    value: &mut Value,
+) -> Result<&mut serde_json::Map<String, Value>, String> {
+    value
+        .as_object_mut()
+        .ok_or_else(|| "expected an object".to_string())
+}
+
+fn field<'a>(
+    value: &'a mut Value,
+    name: &str,
+) -> Result<&'a mut Value, String> {
+    as_object(value)?
+        .get_mut(name)
+        .ok_or_else(|| format!("missing field `{}`", name))
+}
+
+/*====================================
+ *
+ *  VERSION 0 -> 1
+ *
+ *===================================*/
+
+fn document_v0_to_v1(mut document: Value) -> Result<Value, String> {
+    character_v0_to_v1(field(&mut document, "details")?)?;
+    attacks_v0_to_v1(field(&mut document, "attack_list")?)?;
+    Ok(document)
+}
+
+/// Version 1 added conditions to the character.
+fn character_v0_to_v1(character: &mut Value) -> Result<(), String> {
+    as_object(character)?
+        .entry("conditions")
+        .or_insert(json!([]));
+    Ok(())
+}
+
+/// Version 1 replaced the `proficient` flag on attacks with the weapon
+/// and its category, which proficiency is now worked out from, and
+/// added the weapon's properties.
+fn attacks_v0_to_v1(attacks: &mut Value) -> Result<(), String> {
+    let list = attacks
+        .as_array_mut()
+        .ok_or_else(|| "expected a list of attacks".to_string())?;
+    for attack in list {
+        let attack = as_object(attack)?;
+        attack.remove("proficient");
+        attack.entry("weapon").or_insert(json!(""));
+        attack.entry("category").or_insert(json!(""));
+        attack.entry("properties").or_insert(json!([]));
+    }
+    Ok(())
+}
+
+/// Exported files got a version number in version 1. The files
+/// exported before that were already in the version 1 format.
+fn save_file_v0_to_v1(mut save: Value) -> Result<Value, String> {
+    as_object(&mut save)?.insert("version".to_string(), json!(1));
+    Ok(save)
+}
+
+/*====================================
+ *
+ *  VERSION 1 -> 2
+ *
+ *===================================*/
+
+/// Version 2 stores the document as one unit, rather than three
+/// parts. Their contents didn't change.
+fn document_v1_to_v2(document: Value) -> Result<Value, String> {
+    Ok(document)
+}
+
+/// Version 2 exports files with the same fields as the document.
+fn save_file_v1_to_v2(mut save: Value) -> Result<Value, String> {
+    let save_map = as_object(&mut save)?;
+    for (old, new) in [("character", "details"), ("attacks", "attack_list")] {
+        if let Some(value) = save_map.remove(old) {
+            save_map.insert(new.to_string(), value);
+        }
+    }
+    save_map.insert("version".to_string(), json!(2));
+    Ok(save)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character_model::{AttackType, CharacterDocument};
+    use crate::save_file::SaveFile;
+
+    /// Load a document stored in parts, the way it was before
+    /// version 2, from a directory of fixtures.
+    fn load_split(
+        details: &str,
+        options: &str,
+        attacks: &str,
+    ) -> Migrated<CharacterDocument> {
+        let parse = |raw: &str| serde_json::from_str::<Value>(raw).unwrap();
+        let (version, document) = assemble_split_document(
+            parse(details),
+            Some(parse(options)),
+            Some(parse(attacks)),
+        );
+        let document = migrate(document, version, &DOCUMENT_MIGRATIONS)
+            .unwrap_or_else(|e| panic!("fixture failed to migrate: {}", e));
+        Migrated {
+            data: serde_json::from_value(document)
+                .unwrap_or_else(|e| panic!("fixture failed to load: {}", e)),
+            from_version: version,
+        }
+    }
+
+    fn load_save_file(raw: &str) -> SaveFile {
+        SaveFile::from_json(raw)
+            .unwrap_or_else(|e| panic!("fixture failed to load: {}", e))
+    }
+
+    /// What every fixture should contain, whatever version it's from.
+    fn check_grog(document: &CharacterDocument) {
+        let details = &document.details;
+        assert_eq!(details.name, "Grog");
+        assert_eq!(details.class, "barbarian");
+        assert_eq!(details.subspecies, "hill-dwarf");
+        assert_eq!(details.xp, 2700);
+        assert_eq!(details.ability_scores.base_str, 18);
+        assert_eq!(document.attack_list[0].name, "Greataxe");
+        assert_eq!(document.attack_list[0].damage_base, "1d12");
+        assert_eq!(
+            document.selected_optional_features[0].slug,
+            "class:barbarian:class-skills:0"
+        );
+    }
+
+    #[test]
+    fn loads_v0_storage() {
+        let migrated = load_split(
+            include_str!("../tests/fixtures/v0/character.json"),
+            include_str!("../tests/fixtures/v0/options.json"),
+            include_str!("../tests/fixtures/v0/attacks.json"),
+        );
+        assert_eq!(migrated.from_version, 0);
+        let document = migrated.data;
+        check_grog(&document);
+        assert!(document.details.conditions.is_empty());
+        assert_eq!(document.selected_optional_features[1].selection, 3);
+        assert_eq!(document.attack_list[1].attack_type, AttackType::Ranged);
+        assert!(document.attack_list.iter().all(|a| a.properties.is_empty()));
+    }
+
+    #[test]
+    fn loads_v1_storage() {
+        let migrated = load_split(
+            include_str!("../tests/fixtures/v1/character.json"),
+            include_str!("../tests/fixtures/v1/options.json"),
+            include_str!("../tests/fixtures/v1/attacks.json"),
+        );
+        assert_eq!(migrated.from_version, 1);
+        let document = migrated.data;
+        check_grog(&document);
+        assert_eq!(document.details.conditions.len(), 1);
+        assert_eq!(document.attack_list[0].properties.len(), 2);
+    }
+
+    #[test]
+    fn loads_v0_save_file() {
+        let save =
+            load_save_file(include_str!("../tests/fixtures/v0/save_file.json"));
+        assert_eq!(save.version, SCHEMA_VERSION);
+        check_grog(&save.document);
+        assert_eq!(save.document.attack_list.len(), 1);
+    }
+
+    #[test]
+    fn loads_v1_save_file() {
+        let save =
+            load_save_file(include_str!("../tests/fixtures/v1/save_file.json"));
+        assert_eq!(save.version, SCHEMA_VERSION);
+        check_grog(&save.document);
+        assert_eq!(save.document.details.conditions.len(), 1);
+    }
+
+    #[test]
+    fn current_version_round_trips() {
+        let mut document = CharacterDocument::default();
+        document.details.name = "Pike".to_string();
+        let raw = to_versioned_json(&document).unwrap();
+        let migrated = from_versioned_json::<CharacterDocument>(
+            &raw,
+            &DOCUMENT_MIGRATIONS,
+        )
+        .unwrap();
+        assert_eq!(migrated.from_version, SCHEMA_VERSION);
+        assert_eq!(migrated.data.details.name, "Pike");
+
+        let save = SaveFile::new(migrated.data);
+        assert_eq!(
+            load_save_file(&save.to_json()).document.details.name,
+            "Pike"
+        );
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let raw = json!({ "version": SCHEMA_VERSION + 1, "data": {} });
+        let result = from_versioned_json::<CharacterDocument>(
+            &raw.to_string(),
+            &DOCUMENT_MIGRATIONS,
+        );
+        assert!(matches!(result, Err(LoadError::TooNew(_))));
+    }
+}
@@ -5,9 +5,17 @@ use crate::character_model::Ability;
 use crate::character_model::AbilityScoresReactive;
 use crate::character_model::AttackAction;
 use crate::character_model::AttackType;
+use crate::character_model::CharacterDocument;
+use crate::character_model::Condition;
+use crate::character_model::WeaponProperty;
 use crate::components::*;
+use crate::dice::roll_mode::{RollKind, RollMode};
+use crate::dice::{record_rolls, roll_attack, AttackRollParams, RollOutcome};
 use crate::get_prof_bonus;
+use crate::get_weapon_proficiencies;
 use crate::markdown::parse_markdown;
+use crate::roll_mode_on_click;
+use leptos::create_action;
 use leptos::create_signal;
 use leptos::ev;
 use leptos::event_target_value;
@@ -29,6 +37,7 @@ pub fn CenterPanel() -> HtmlDiv {
                     Tab("action-tab", true, "Actions"),
                     Tab("spell-tab", false, "Spells"),
                     Tab("equipment-tab", false, "Equipment"),
+                    Tab("conditions-tab", false, "Conditions"),
                 ]),
         )
         .child(
@@ -41,13 +50,60 @@ pub fn CenterPanel() -> HtmlDiv {
                         TabPanel("action-tab", true, ActionsTab()),
                         //TabPanel("spell-tab", true, SpellsTab()),
                         //TabPanel("equipment-tab", true, EquipmentTab()),
+                        TabPanel("conditions-tab", false, ConditionsTab()),
                     ]),
             ),
         )
 }
 
+/// Toggles for the conditions that affect the character's rolls.
+fn ConditionsTab() -> HtmlDiv {
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    div().classes("list-group").child(
+        Condition::ALL
+            .iter()
+            .map(|condition| {
+                let condition = *condition;
+                let id = format!("condition-{}", condition).to_lowercase();
+                let active = move || {
+                    document.with(|d| d.details.conditions.contains(&condition))
+                };
+                let effects = condition
+                    .roll_mode_effects()
+                    .iter()
+                    .map(|e| format!("{} on {}", e.mode, e.target))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                label()
+                    .classes("list-group-item d-flex gap-2")
+                    .attr("for", &id)
+                    .child(
+                        input()
+                            .classes("form-check-input flex-shrink-0")
+                            .attr("type", "checkbox")
+                            .id(id.clone())
+                            .prop("checked", active)
+                            .on(ev::change, move |_| {
+                                document.update(|d| {
+                                    let conditions = &mut d.details.conditions;
+                                    if conditions.contains(&condition) {
+                                        conditions.retain(|x| *x != condition)
+                                    } else {
+                                        conditions.push(condition)
+                                    }
+                                })
+                            }),
+                    )
+                    .child(span().child(condition.to_string()).child(
+                        small().classes("d-block text-muted").child(effects),
+                    ))
+            })
+            .collect::<Vec<HtmlElement<Label>>>(),
+    )
+}
+
 fn ActionsTab() -> HtmlDiv {
-    let attack_list = expect_context::<RwSignal<Vec<AttackAction>>>();
+    let document = expect_context::<RwSignal<CharacterDocument>>();
     div()
         .child(
             div()
@@ -66,8 +122,9 @@ fn ActionsTab() -> HtmlDiv {
                 .classes("accordion mb-2")
                 .id("featuresAccordion")
                 .child(move || {
-                    attack_list.with(|list| {
-                        list.iter()
+                    document.with(|d| {
+                        d.attack_list
+                            .iter()
                             .map(AttackActionDisplay)
                             .collect::<DivList>()
                     })
@@ -79,19 +136,55 @@ fn ActionsTab() -> HtmlDiv {
 fn AttackActionDisplay(attack: &AttackAction) -> HtmlDiv {
     let attack_name = attack.name.to_string();
     let attack_slug = attack.slug.to_string();
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    let ability_scores = expect_context::<AbilityScoresReactive>();
+    let weapon_profs = get_weapon_proficiencies();
+    let owned_attack = attack.clone();
+    let prof_bonus = get_prof_bonus();
+    let attack_roll_mode = roll_mode_on_click(RollKind::Attack);
+    let roll_params = move |mode: RollMode| AttackRollParams {
+        name: owned_attack.name.to_string(),
+        to_hit: i64::from(owned_attack.to_hit(
+            prof_bonus,
+            ability_scores,
+            &weapon_profs(),
+        )),
+        damage_dice: owned_attack.damage_base.to_string(),
+        damage_mod: i64::from(owned_attack.damage_mod(ability_scores)),
+        damage_type: owned_attack.damage_type.to_string(),
+        mode,
+    };
+    let roll_history = expect_context::<RwSignal<Vec<RollOutcome>>>();
+    let attack_roll = create_action(move |params: &AttackRollParams| {
+        let params = params.clone();
+        async move { record_rolls(roll_history, roll_attack(params).await) }
+    });
     AccordionItem(
         div().child(attack_name),
         div()
             .inner_html(parse_markdown(&attack.generate_description(
                 get_prof_bonus(),
-                expect_context::<AbilityScoresReactive>(),
+                ability_scores,
+                &weapon_profs(),
             )))
+            .child(
+                button()
+                    .classes("btn btn-sm btn-outline-primary me-2")
+                    .child("Roll")
+                    .attr(
+                        "title",
+                        "Alt+click: advantage, Ctrl+click: disadvantage",
+                    )
+                    .on(ev::click, move |e| {
+                        let mode = attack_roll_mode(&e);
+                        attack_roll.dispatch(roll_params(mode))
+                    }),
+            )
             .child(div().style("cursor", "pointer").child("[Remove]").on(
                 ev::click,
                 move |_| {
-                    let attack_list =
-                        expect_context::<RwSignal<Vec<AttackAction>>>();
-                    attack_list.update(|current| {
+                    document.update(|d| {
+                        let current = &mut d.attack_list;
                         let idx =
                             current.iter().position(|e| e.slug == attack_slug);
                         if let Some(idx) = idx {
@@ -144,25 +237,36 @@ fn AttackCreationModal() -> HtmlElement<Div> {
 }
 
 fn CreateAttackForm() -> HtmlElement<Form> {
-    let attack_list = expect_context::<RwSignal<Vec<AttackAction>>>();
+    let document = expect_context::<RwSignal<CharacterDocument>>();
     // Declare the values to be used in the form below
     let (name, set_name) = create_signal(String::new());
     let (damage_base, set_damage_base) = create_signal(String::new());
     let (reach, set_reach) = create_signal(0);
     let (damage_type, set_damage_type) = create_signal(String::new());
+    // Set when a weapon template is picked, so that proficiency can be
+    // worked out from the weapon rather than the (editable) name.
+    let (weapon, set_weapon) = create_signal(String::new());
+    let (category, set_category) = create_signal(String::new());
+    let (ability, set_ability) = create_signal(Ability::Strength);
+    let (attack_type, set_attack_type) = create_signal(AttackType::Melee);
+    let (properties, set_properties) =
+        create_signal(Vec::<WeaponProperty>::new());
     let on_submit = move |e: SubmitEvent| {
         log!("Submit event called!");
         let mut new_action = AttackAction {
             name: name(),
             slug: name(),
-            ability: Ability::Strength,
+            ability: ability(),
             damage_base: damage_base(),
-            proficient: true,
-            attack_type: AttackType::Melee,
+            weapon: weapon(),
+            category: category(),
+            attack_type: attack_type(),
             reach: reach(),
             damage_type: damage_type(),
+            properties: properties(),
         };
-        attack_list.update(|list| {
+        document.update(|d| {
+            let list = &mut d.attack_list;
             // If an attack sharing the same slug as this attack
             // already exists in the attack list, add a unique
             // itendifier to the slug of this attack. Repeat
@@ -207,14 +311,16 @@ fn CreateAttackForm() -> HtmlElement<Form> {
                         if let Some(weapons) = weapons {
                             let weapon = weapons.iter().find(|w| w.slug == val);
                             if let Some(weapon) = weapon {
-                                set_name(weapon.name.to_string());
-                                set_damage_base(weapon.damage_dice.to_string());
-                                set_reach(if weapon.is_reach() {
-                                    10
-                                } else {
-                                    5
-                                });
-                                set_damage_type(weapon.damage_type.to_string())
+                                let attack = weapon.to_attack();
+                                set_name(attack.name);
+                                set_damage_base(attack.damage_base);
+                                set_reach(attack.reach);
+                                set_damage_type(attack.damage_type);
+                                set_weapon(attack.weapon);
+                                set_category(attack.category);
+                                set_ability(attack.ability);
+                                set_attack_type(attack.attack_type);
+                                set_properties(attack.properties);
                             }
                         }
                     });
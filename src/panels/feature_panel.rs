@@ -6,7 +6,7 @@ use crate::api::api_model::Species;
 use crate::api::api_model::Subspecies;
 use crate::character_model::Ability;
 use crate::character_model::CharacterAsi;
-use crate::character_model::CharacterDetails;
+use crate::character_model::CharacterDocument;
 use crate::components::*;
 use crate::get_current_features;
 use crate::get_current_species;
@@ -115,13 +115,26 @@ fn FeatureOptionDropdown(
     // Don't track here, because we don't want this
     // element to refresh when we change our
     // selection.
-    let selected_optional_features =
-        expect_context::<RwSignal<Vec<FeatureOptionsSelection>>>();
-    selected_optional_features.with_untracked(move |selected| {
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    document.with_untracked(move |d| {
+        let selected = &d.selected_optional_features;
         if let Some(thing) = selected.iter().find(matches_slug) {
             *selected_index_ptr = thing.selection;
         }
     });
+    // Follow the selection when it's changed from somewhere else,
+    // i.e. by undo, without rebuilding the element.
+    let current_selection = {
+        let slug = slug.to_string();
+        create_memo(move |_| {
+            document.with(|d| {
+                d.selected_optional_features
+                    .iter()
+                    .find(|f| f.slug == slug)
+                    .map_or(99, |f| f.selection)
+            })
+        })
+    };
     CustomSelect()
         .child(option().child("Select...").attr("value", 99))
         .child(
@@ -133,20 +146,22 @@ fn FeatureOptionDropdown(
                 .map(|i| SelectFeatureOption(i, selected_index))
                 .collect::<OptionList>(),
         )
+        .prop("value", move || current_selection().to_string())
         .on(ev::change, move |event| {
-            change_selected_feature(&slug, event, selected_optional_features);
+            change_selected_feature(&slug, event, document);
         })
 }
 
 fn change_selected_feature(
     slug: &str,
     e: web_sys::Event,
-    selected_optional_features: RwSignal<Vec<FeatureOptionsSelection>>,
+    document: RwSignal<CharacterDocument>,
 ) {
     let feature_option_slug = slug;
     let val = event_target_value(&e);
     if let Ok(index) = str::parse::<usize>(&val) {
-        selected_optional_features.update(|selected| {
+        document.update(|d| {
+            let selected = &mut d.selected_optional_features;
             selected.retain(|s| s.slug != feature_option_slug);
             selected.push(FeatureOptionsSelection {
                 slug: feature_option_slug.to_string(),
@@ -216,9 +231,9 @@ pub fn SpeciesTab() -> HtmlDiv {
 }
 
 fn SpeciesDisplay(species: Species) -> HtmlDiv {
-    let character = expect_context::<RwSignal<CharacterDetails>>();
+    let document = expect_context::<RwSignal<CharacterDocument>>();
     let get_subspecies =
-        create_read_slice(character, |c| c.subspecies.to_string());
+        create_read_slice(document, |d| d.details.subspecies.to_string());
     //let subspecies_list = species.subraces.clone();
     let mut features = species.features();
     let my_subspecies =
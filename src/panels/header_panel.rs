@@ -1,38 +1,48 @@
 use leptos::{html::*, *};
 
 use crate::{
-    api::api_model::Class, api::*, character_model::CharacterDetails,
-    components::*,
+    api::api_model::Class, character_model::CharacterDocument, components::*,
+    get_background_list, get_class_list, get_level_snapshot, get_species_list,
+    level_history::change_level,
 };
 
 pub fn HeaderPanel() -> HtmlElement<Div> {
-    let character = expect_context::<RwSignal<CharacterDetails>>();
+    let document = expect_context::<RwSignal<CharacterDocument>>();
     let (species, set_species) = create_slice(
-        character,
-        |c| c.species.to_string(),
-        |c, n| {
-            c.species = n;
+        document,
+        |d| d.details.species.to_string(),
+        |d, n| {
+            d.details.species = n;
             // Clear the subspecies when the species changes
-            c.subspecies = String::new();
+            d.details.subspecies = String::new();
         },
     );
-    let (class, set_class) =
-        create_slice(character, |c| c.class.to_string(), |c, v| c.class = v);
+    let (class, set_class) = create_slice(
+        document,
+        |d| d.details.class.to_string(),
+        |d, v| d.details.class = v,
+    );
     let (background, set_background) = create_slice(
-        character,
-        |c| c.background.to_string(),
-        |c, v| c.background = v,
+        document,
+        |d| d.details.background.to_string(),
+        |d, v| d.details.background = v,
     );
 
-    let (name, set_name) =
-        create_slice(character, |c| c.name.to_string(), |c, n| c.name = n);
-
-    let (level, set_level) = create_slice(
-        character,
-        CharacterDetails::level,
-        CharacterDetails::set_level,
+    let (name, set_name) = create_slice(
+        document,
+        |d| d.details.name.to_string(),
+        |d, n| d.details.name = n,
     );
 
+    // Levelling up keeps the character as they were in their level
+    // history, in the same change as the level
+    let level = create_read_slice(document, |d| d.details.level());
+    let snapshot = get_level_snapshot();
+    let set_level = SignalSetter::map(move |level| {
+        let current = snapshot.get_untracked();
+        document.update(|d| change_level(d, current, level))
+    });
+
     div().classes("container").child(
         HorizontalPanel().child(
             GridRow()
@@ -72,7 +82,7 @@ fn SpeciesDropdown(
     species: Signal<String>,
     set_species: SignalSetter<String>,
 ) -> impl IntoView {
-    let future = expect_context::<FuturesWrapper>().species;
+    let species_list = get_species_list();
     let change_species = move |e| {
         set_species(event_target_value(&e));
     };
@@ -83,7 +93,7 @@ fn SpeciesDropdown(
         .attr("placeholder", "Species")
         .child(option().prop("value", "").child("Select a species..."))
         .child(move || {
-            future.with(|species_list| {
+            species_list.with(|species_list| {
                 if let Some(species_list) = species_list {
                     species_list
                         .iter()
@@ -120,13 +130,13 @@ fn ClassDropdown(
     class: Signal<String>,
     set_class: SignalSetter<String>,
 ) -> impl IntoView {
-    let future = expect_context::<FuturesWrapper>().classes;
+    let class_list = get_class_list();
     CustomSelect()
         .prop("value", class)
         .on(ev::change, move |e| set_class(event_target_value(&e)))
         .child(option().child("Select a class...").prop("value", ""))
         .child(move || {
-            future.with(|c| {
+            class_list.with(|c| {
                 if let Some(c) = c {
                     ClassOptionList(c, class)
                 } else {
@@ -140,13 +150,13 @@ fn BackgroundDropdown(
     background: Signal<String>,
     set_background: SignalSetter<String>,
 ) -> HtmlElement<Select> {
-    let future = expect_context::<FuturesWrapper>().backgrounds;
+    let background_list = get_background_list();
     CustomSelect()
         .prop("value", background)
         .on(ev::change, move |e| set_background(event_target_value(&e)))
         .child(option().child("Select a background...").prop("value", ""))
         .child(move || {
-            future.with(|bg| {
+            background_list.with(|bg| {
                 if let Some(bg) = bg {
                     bg.iter()
                         .map(|c| {
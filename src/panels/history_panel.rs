@@ -0,0 +1,108 @@
+use leptos::{html::*, *};
+
+use crate::{
+    character_model::CharacterDocument, components::*, history::History,
+    time_travel,
+};
+
+/// Modal with undo and redo buttons, and a timeline of every change
+/// made to the character this session that can be jumped back to.
+pub fn HistoryModal() -> HtmlElement<Div> {
+    div()
+        .classes("modal fade")
+        .id("historyModal")
+        .attr("tabindex", "-1")
+        .attr("aria-labelledby", "historyModalLabel")
+        .attr("aria-hidden", "true")
+        .child(
+            div()
+                .classes("modal-dialog modal-dialog-centered modal-dialog-scrollable")
+                .child(
+                    div()
+                        .classes("modal-content")
+                        .child(
+                            div()
+                                .classes("modal-header")
+                                .child(
+                                    h1().classes("modal-title fs-5")
+                                        .id("historyModalLabel")
+                                        .child("History"),
+                                )
+                                .child(
+                                    button()
+                                        .attr("type", "button")
+                                        .classes("btn-close")
+                                        .attr("data-bs-dismiss", "modal")
+                                        .attr("aria-label", "Close"),
+                                ),
+                        )
+                        .child(
+                            div()
+                                .classes("modal-body")
+                                .child(UndoRedoButtons())
+                                .child(Timeline()),
+                        ),
+                ),
+        )
+}
+
+fn UndoRedoButtons() -> HtmlDiv {
+    let history = expect_context::<RwSignal<History>>();
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    div()
+        .classes("d-flex gap-2 mb-3")
+        .child(
+            button()
+                .attr("type", "button")
+                .classes("btn btn-outline-secondary")
+                .attr("title", "Ctrl+Z")
+                .prop("disabled", move || history.with(|h| !h.can_undo()))
+                .child("Undo")
+                .on(ev::click, move |_| {
+                    time_travel(history, document, History::undo)
+                }),
+        )
+        .child(
+            button()
+                .attr("type", "button")
+                .classes("btn btn-outline-secondary")
+                .attr("title", "Ctrl+Shift+Z")
+                .prop("disabled", move || history.with(|h| !h.can_redo()))
+                .child("Redo")
+                .on(ev::click, move |_| {
+                    time_travel(history, document, History::redo)
+                }),
+        )
+}
+
+/// Every change, newest first. Clicking one goes back to the
+/// character as it was right after that change. Changes that
+/// have been undone are greyed out.
+fn Timeline() -> HtmlElement<Ul> {
+    let history = expect_context::<RwSignal<History>>();
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    ul().classes("list-group").child(move || {
+        history.with(|h| {
+            h.entries()
+                .iter()
+                .enumerate()
+                .rev()
+                .map(|(index, entry)| {
+                    let classes = if index == h.position() {
+                        "list-group-item list-group-item-action active"
+                    } else if index > h.position() {
+                        "list-group-item list-group-item-action text-muted"
+                    } else {
+                        "list-group-item list-group-item-action"
+                    };
+                    li().classes(classes)
+                        .attr("role", "button")
+                        .child(entry.label.to_string())
+                        .on(ev::click, move |_| {
+                            time_travel(history, document, |h| h.jump_to(index))
+                        })
+                })
+                .collect::<Vec<HtmlElement<Li>>>()
+        })
+    })
+}
@@ -0,0 +1,94 @@
+use leptos::{html::*, *};
+
+use crate::{
+    character_model::CharacterDocument, components::*, get_level_snapshot,
+    level_history::roll_back_to,
+};
+
+/// Modal with what the character gained at each level up, and a way
+/// to roll a mistaken one back.
+pub fn LevelHistoryModal() -> HtmlElement<Div> {
+    div()
+        .classes("modal fade")
+        .id("levelHistoryModal")
+        .attr("tabindex", "-1")
+        .attr("aria-labelledby", "levelHistoryModalLabel")
+        .attr("aria-hidden", "true")
+        .child(
+            div()
+                .classes("modal-dialog modal-dialog-centered modal-dialog-scrollable")
+                .child(
+                    div()
+                        .classes("modal-content")
+                        .child(
+                            div()
+                                .classes("modal-header")
+                                .child(
+                                    h1().classes("modal-title fs-5")
+                                        .id("levelHistoryModalLabel")
+                                        .child("Levels"),
+                                )
+                                .child(
+                                    button()
+                                        .attr("type", "button")
+                                        .classes("btn-close")
+                                        .attr("data-bs-dismiss", "modal")
+                                        .attr("aria-label", "Close"),
+                                ),
+                        )
+                        .child(div().classes("modal-body").child(LevelUps())),
+                ),
+        )
+}
+
+/// Every level up, newest first, with what changed from the level
+/// before. Rolling back to a level puts back the choices made at it.
+fn LevelUps() -> HtmlDiv {
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    let current = get_level_snapshot();
+    div().child(move || {
+        let snapshots = document.with(|d| d.level_history.clone());
+        if snapshots.is_empty() {
+            return p()
+                .classes("text-muted mb-0")
+                .child("Level up to keep a record of what changed.")
+                .into_view();
+        }
+        let current = current();
+        let level_ups = snapshots
+            .iter()
+            .enumerate()
+            .map(|(index, before)| {
+                let after = snapshots.get(index + 1).unwrap_or(&current);
+                let level = before.level;
+                li().classes("list-group-item")
+                    .child(
+                        div()
+                            .classes("d-flex justify-content-between align-items-center gap-2")
+                            .child(strong().child(format!(
+                                "Level {} → {}",
+                                before.level, after.level
+                            )))
+                            .child(
+                                button()
+                                    .attr("type", "button")
+                                    .classes("btn btn-sm btn-outline-secondary")
+                                    .child(format!("Roll back to level {}", level))
+                                    .on(ev::click, move |_| {
+                                        document.update(|d| {
+                                            roll_back_to(d, level);
+                                        })
+                                    }),
+                            ),
+                    )
+                    .child(
+                        div()
+                            .classes("text-muted small")
+                            .child(before.diff(after).to_string()),
+                    )
+            })
+            .rev()
+            .collect::<Vec<HtmlElement<Li>>>();
+        ul().classes("list-group").child(level_ups).into_view()
+    })
+}
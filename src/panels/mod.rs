@@ -1,5 +1,14 @@
 pub mod center_panel;
 pub mod feature_panel;
 pub mod header_panel;
+pub mod history_panel;
+pub mod level_history_panel;
+pub mod open5e_panel;
+pub mod print_panel;
 pub mod proficencies_panel;
+pub mod roll_panel;
+pub mod roster_panel;
+pub mod share_panel;
+pub mod stat_block_panel;
 pub mod stats_panel;
+pub mod sync_panel;
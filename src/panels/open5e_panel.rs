@@ -0,0 +1,134 @@
+use leptos::{html::*, *};
+use wasm_bindgen::JsValue;
+
+use crate::{
+    api::FuturesWrapper,
+    components::*,
+    settings::{Settings, OPEN5E_URL},
+};
+
+/// Shown when Open5e can't be reached and the lists of classes,
+/// species and so on come from the cache instead.
+pub fn OfflineBanner() -> HtmlDiv {
+    let offline_cache_date =
+        expect_context::<FuturesWrapper>().offline_cache_date;
+    div().child(move || {
+        offline_cache_date().map(|date| {
+            let date =
+                js_sys::Date::new(&JsValue::from_f64(date as f64 * 1000.0));
+            div()
+                .classes("container alert alert-warning py-2 mt-4 d-print-none")
+                .attr("role", "status")
+                .child(format!(
+                    "Offline – using cached data from {}",
+                    String::from(
+                        date.to_locale_date_string(
+                            "default",
+                            &JsValue::UNDEFINED
+                        )
+                    )
+                ))
+        })
+    })
+}
+
+/// Modal for picking where classes, species and so on come from: the
+/// SRD bundled with the app, or an Open5e instance.
+pub fn DataSourceModal() -> HtmlElement<Div> {
+    div()
+        .classes("modal fade")
+        .id("dataSourceModal")
+        .attr("tabindex", "-1")
+        .attr("aria-labelledby", "dataSourceModalLabel")
+        .attr("aria-hidden", "true")
+        .child(
+            div().classes("modal-dialog modal-dialog-centered").child(
+                div()
+                    .classes("modal-content")
+                    .child(
+                        div()
+                            .classes("modal-header")
+                            .child(
+                                h1().classes("modal-title fs-5")
+                                    .id("dataSourceModalLabel")
+                                    .child("Data"),
+                            )
+                            .child(
+                                button()
+                                    .attr("type", "button")
+                                    .classes("btn-close")
+                                    .attr("data-bs-dismiss", "modal")
+                                    .attr("aria-label", "Close"),
+                            ),
+                    )
+                    .child(div().classes("modal-body").child(DataSourceForm())),
+            ),
+        )
+}
+
+fn DataSourceForm() -> HtmlDiv {
+    let settings = expect_context::<RwSignal<Settings>>();
+    let (use_open5e, set_use_open5e) = create_slice(
+        settings,
+        |s| s.data.use_open5e,
+        |s, v| s.data.use_open5e = v,
+    );
+    let (open5e_url, set_open5e_url) = create_slice(
+        settings,
+        |s| s.data.open5e_url.to_string(),
+        |s, v| s.data.open5e_url = v,
+    );
+
+    div()
+        .child(p().classes("text-muted").child(
+            "Classes, species, backgrounds and weapons come from the 5e \
+                SRD, which is built into the app and works offline. \
+                Open5e has more, from other sources, but needs a \
+                connection the first time.",
+        ))
+        .child(
+            div()
+                .classes("form-check form-switch mb-3")
+                .child(
+                    input()
+                        .classes("form-check-input")
+                        .attr("type", "checkbox")
+                        .attr("role", "switch")
+                        .id("useOpen5eToggle")
+                        .prop("checked", use_open5e)
+                        .on(ev::change, move |e| {
+                            set_use_open5e(event_target_checked(&e))
+                        }),
+                )
+                .child(
+                    label()
+                        .classes("form-check-label")
+                        .attr("for", "useOpen5eToggle")
+                        .child("Use Open5e instead of the bundled SRD"),
+                ),
+        )
+        .child(
+            div()
+                .child(
+                    label()
+                        .classes("form-label")
+                        .attr("for", "open5eUrl")
+                        .child("Open5e address"),
+                )
+                .child(
+                    input()
+                        .classes("form-control")
+                        .id("open5eUrl")
+                        .attr("placeholder", OPEN5E_URL)
+                        .prop("disabled", move || !use_open5e())
+                        .prop("value", open5e_url)
+                        .on(ev::change, move |e| {
+                            set_open5e_url(event_target_value(&e))
+                        }),
+                )
+                .child(div().classes("form-text").child(
+                    "Leave empty for Open5e's own, or give the address \
+                        of a self-hosted instance.",
+                )),
+        )
+}
@@ -0,0 +1,208 @@
+use leptos::{html::*, *};
+
+use crate::{
+    api::api_extensions::FeatureType, character_model::*, components::*,
+    get_current_background, get_current_class, get_current_features,
+    get_current_species, get_current_subspecies, get_max_hp, get_prof_bonus,
+    get_weapon_proficiencies, print::*, proficiencies::*,
+};
+
+/// Everything the printed sheet shows, worked out from the open
+/// character.
+pub fn get_print_sheet() -> Signal<PrintSheet> {
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    let ability_scores = expect_context::<AbilityScoresReactive>();
+    let (class, species, subspecies, background) = (
+        get_current_class(),
+        get_current_species(),
+        get_current_subspecies(),
+        get_current_background(),
+    );
+    let (features, max_hp, prof_bonus, weapon_profs) = (
+        get_current_features(),
+        get_max_hp(),
+        get_prof_bonus(),
+        get_weapon_proficiencies(),
+    );
+    Signal::derive(move || {
+        let features = features();
+        let details = document.with(|d| d.details.clone());
+        let class_name = class().map(|c| c.name).unwrap_or_default();
+        let species_name = subspecies()
+            .map(|s| s.name)
+            .or_else(|| species().map(|s| s.name))
+            .unwrap_or_default();
+        let background_name = background().map(|b| b.name).unwrap_or_default();
+
+        let saves = save_proficiencies(&features);
+        let skill_profs = skill_proficiencies(&features);
+        let skills = Skill::ALL
+            .into_iter()
+            .map(|skill| {
+                let proficient = skill_profs.contains(&skill);
+                PrintSkill {
+                    skill,
+                    bonus: ability_scores.get_ability_mod(&skill.ability())
+                        + if proficient { prof_bonus() } else { 0 },
+                    proficient,
+                }
+            })
+            .collect::<Vec<PrintSkill>>();
+        let passive_perception = 10
+            + skills
+                .iter()
+                .find(|s| s.skill == Skill::Perception)
+                .map(|s| s.bonus)
+                .unwrap_or_default();
+
+        let weapon_profs = weapon_profs();
+        let attacks = document.with(|d| {
+            d.attack_list
+                .iter()
+                .map(|attack| {
+                    let mut notes = attack
+                        .properties
+                        .iter()
+                        .map(|p| p.to_string())
+                        .collect::<Vec<String>>();
+                    if let Some(range) = attack.range() {
+                        notes.insert(0, range.to_string());
+                    }
+                    PrintAttack {
+                        name: attack.name.to_string(),
+                        to_hit: attack.to_hit(
+                            prof_bonus,
+                            ability_scores,
+                            &weapon_profs,
+                        ),
+                        damage: format!(
+                            "{}{:+} {}",
+                            attack.damage_base,
+                            attack.damage_mod(ability_scores),
+                            attack.damage_type
+                        ),
+                        notes: notes.join(", "),
+                        description: attack.generate_description(
+                            prof_bonus,
+                            ability_scores,
+                            &weapon_profs,
+                        ),
+                    }
+                })
+                .collect()
+        });
+
+        let proficiencies = features
+            .iter()
+            .filter_map(|f| match &f.feature_type {
+                FeatureType::OtherProficency(prof) => {
+                    Some(prof.trim().to_string())
+                }
+                FeatureType::WeaponProficiency(prof) => Some(prof.to_string()),
+                _ => None,
+            })
+            .collect::<Vec<String>>();
+        let senses = senses(&features);
+        let species_languages =
+            species().map(|s| s.languages).unwrap_or_default();
+        let languages = languages(&species_languages, &features);
+
+        // Features the player reads, rather than the ones that only
+        // change numbers or offer choices
+        let print_features = features
+            .iter()
+            .filter(|f| !f.hidden)
+            .filter(|f| {
+                !matches!(
+                    f.feature_type,
+                    FeatureType::Option(_) | FeatureType::Fluff
+                )
+            })
+            .map(|f| PrintFeature {
+                source: match f.source_slug.split(':').next() {
+                    Some("class") => class_name.to_string(),
+                    Some("species") | Some("subspecies") => {
+                        species_name.to_string()
+                    }
+                    Some("background") => background_name.to_string(),
+                    _ => "Other".to_string(),
+                },
+                name: f.name.to_string(),
+                desc: f.desc.to_string(),
+            })
+            .collect();
+
+        PrintSheet {
+            name: details.name.to_string(),
+            level: details.level(),
+            xp: details.xp,
+            proficiency_bonus: prof_bonus(),
+            abilities: Ability::ALL
+                .into_iter()
+                .map(|ability| {
+                    let modifier = ability_scores.get_ability_mod(&ability);
+                    let save_proficient = saves.contains(&ability);
+                    PrintAbility {
+                        score: ability_scores.get_ability_score(&ability),
+                        modifier,
+                        save: modifier
+                            + if save_proficient { prof_bonus() } else { 0 },
+                        save_proficient,
+                        ability,
+                    }
+                })
+                .collect(),
+            skills,
+            passive_perception,
+            initiative: ability_scores.dex_mod(),
+            speed: species().map(|s| s.speed.walk).unwrap_or_default(),
+            max_hp: max_hp(),
+            hit_dice: class()
+                .and_then(|c| {
+                    c.hit_dice
+                        .split_once('d')
+                        .map(|(_, die)| format!("{}d{}", details.level(), die))
+                })
+                .unwrap_or_default(),
+            attacks,
+            proficiencies,
+            senses,
+            languages,
+            features: print_features,
+            class: class_name,
+            species: species_name,
+            background: background_name,
+        }
+    })
+}
+
+/// The open character as a paper sheet, with buttons to print it or
+/// go back. The buttons aren't printed.
+pub fn PrintView(printing: RwSignal<bool>) -> HtmlDiv {
+    let sheet = get_print_sheet();
+    div()
+        .classes("print-sheet container bg-body text-body py-3")
+        // Dark text on white, whatever the app's theme is
+        .attr("data-bs-theme", "light")
+        .child(
+            div()
+                .classes("d-flex gap-2 mb-3 d-print-none")
+                .child(
+                    button()
+                        .attr("type", "button")
+                        .classes("btn btn-primary")
+                        .child("Print")
+                        .on(ev::click, |_| {
+                            let _ = window().print();
+                        }),
+                )
+                .child(
+                    button()
+                        .attr("type", "button")
+                        .classes("btn btn-outline-secondary")
+                        .child("Back to sheet")
+                        .on(ev::click, move |_| printing.set(false)),
+                ),
+        )
+        .child(move || div().inner_html(sheet.with(render_print_sheet)))
+}
@@ -1,29 +1,28 @@
 use crate::{
-    api::api_extensions::FeatureType, character_model::*, components::*,
-    get_current_features, get_prof_bonus,
+    api::api_extensions::{Feature, FeatureType},
+    character_model::*,
+    components::*,
+    dice::roll_mode::{RollKind, RollTarget},
+    get_current_features, get_prof_bonus, get_roll_mode_effects,
+    get_save_proficiencies, get_skill_proficencies, roll_check_on_click,
 };
-use leptos::{expect_context, html::*, prelude::*};
+use leptos::{
+    create_signal, ev, event_target_value, expect_context, html::*, prelude::*,
+};
+
+const CLICK_TO_ROLL: &str =
+    "Click to roll. Alt+click: advantage, Ctrl+click: disadvantage";
 
 pub fn ProfPanel() -> HtmlElement<Div> {
     let features = get_current_features();
-    let saves = Signal::derive(move || {
+    let (saves, skills) = (get_save_proficiencies(), get_skill_proficencies());
+    let tools = Signal::derive(move || {
         features()
             .into_iter()
+            .filter(is_tool_proficiency)
             .filter_map(|f| {
-                if let FeatureType::SavingThrow(ability) = f.feature_type {
-                    Some(ability)
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<Ability>>()
-    });
-    let skills = Signal::derive(move || {
-        features()
-            .into_iter()
-            .filter_map(|f| {
-                if let FeatureType::SkillProficency(prof) = f.feature_type {
-                    Some(prof)
+                if let FeatureType::OtherProficency(prof) = f.feature_type {
+                    Some(prof.trim().to_string())
                 } else {
                     None
                 }
@@ -33,12 +32,11 @@ pub fn ProfPanel() -> HtmlElement<Div> {
     let other_profs = Signal::derive(move || {
         features()
             .into_iter()
-            .filter_map(|f| {
-                if let FeatureType::OtherProficency(prof) = f.feature_type {
-                    Some(prof)
-                } else {
-                    None
-                }
+            .filter(|f| !is_tool_proficiency(f))
+            .filter_map(|f| match f.feature_type {
+                FeatureType::OtherProficency(prof) => Some(prof),
+                FeatureType::WeaponProficiency(prof) => Some(prof.to_string()),
+                _ => None,
             })
             .collect::<Vec<String>>()
     });
@@ -65,7 +63,7 @@ pub fn ProfPanel() -> HtmlElement<Div> {
                         TabPanel(
                             "other-tab",
                             false,
-                            OtherProfsTab(other_profs),
+                            OtherProfsTab(other_profs, tools),
                         ),
                     ]),
             ),
@@ -74,35 +72,78 @@ pub fn ProfPanel() -> HtmlElement<Div> {
 
 pub fn SavesDisplay(saves: Signal<Vec<Ability>>) -> HtmlDiv {
     let ability_scores = expect_context::<AbilityScoresReactive>();
-    div().child(
-        ul().classes("list-group").child(
-            [
-                Ability::Strength,
-                Ability::Dexterity,
-                Ability::Constitution,
-                Ability::Wisdom,
-                Ability::Intelligence,
-                Ability::Charisma,
-            ]
-            .iter()
-            .map(|ability| {
-                li().classes("list-group-item").child(
-                    div()
-                        .classes("d-flex justify-content-between")
-                        .child(div().child(ability.to_string().to_string()))
-                        .child(div().child(move || {
+    let effects = get_roll_mode_effects();
+    let prof_bonus = get_prof_bonus();
+    // Effects that only apply to some saves, i.e. against poison.
+    // These aren't applied to rolls, so list them as reminders.
+    let reminders = move || {
+        effects()
+            .into_iter()
+            .filter(|e| {
+                e.situation.is_some()
+                    && matches!(e.target, RollTarget::AllSaves)
+            })
+            .map(|e| li().child(e.to_string()))
+            .collect::<Vec<HtmlElement<Li>>>()
+    };
+    div()
+        .child(
+            ul().classes("list-group").child(
+                Ability::ALL
+                    .iter()
+                    .map(|ability| {
+                        let save_bonus = Signal::derive(move || {
                             calc_save(
                                 ability_scores,
                                 saves,
                                 ability,
-                                get_prof_bonus(),
+                                prof_bonus,
                             )
-                        })),
-                )
-            })
-            .collect::<Vec<HtmlElement<Li>>>(),
-        ),
-    )
+                        });
+                        let roll = roll_check_on_click(
+                            format!("{} save", ability.to_string()),
+                            save_bonus,
+                            RollKind::Save(ability.clone()),
+                        );
+                        let target = RollTarget::Save(ability.clone());
+                        let save_notes = move || {
+                            effects()
+                                .iter()
+                                .filter(|e| e.target == target)
+                                .map(|e| e.to_string())
+                                .collect::<Vec<String>>()
+                                .join("\n")
+                        };
+                        li().classes("list-group-item list-group-item-action")
+                            .style("cursor", "pointer")
+                            .attr("title", CLICK_TO_ROLL)
+                            .on(ev::click, roll)
+                            .child(
+                                div()
+                                    .classes("d-flex justify-content-between")
+                                    .child(
+                                        div()
+                                            .child(
+                                                ability.to_string().to_string(),
+                                            )
+                                            .child(move || {
+                                                let notes = save_notes();
+                                                if notes.is_empty() {
+                                                    span()
+                                                } else {
+                                                    span()
+                                                        .attr("title", notes)
+                                                        .child(" *")
+                                                }
+                                            }),
+                                    )
+                                    .child(div().child(save_bonus)),
+                            )
+                    })
+                    .collect::<Vec<HtmlElement<Li>>>(),
+            ),
+        )
+        .child(ul().classes("small text-muted mt-2").child(reminders))
 }
 
 fn calc_save(
@@ -119,26 +160,144 @@ fn calc_save(
     ability_scores.get_ability_mod(ability) + bonus
 }
 
+fn is_tool_proficiency(f: &Feature) -> bool {
+    matches!(&f.feature_type, FeatureType::OtherProficency(prof)
+        if f.name == "Tool Proficency" && !prof.contains("of your choice"))
+}
+
+/// Every skill, with the character's bonus for it. Proficiencies that
+/// don't match a standard skill are listed after, but can't be rolled.
 pub fn SkillsTab(skills: Signal<Vec<String>>) -> HtmlDiv {
-    div().child(ul().classes("list-group").child(move || {
+    let unmatched = move || {
         skills()
             .iter()
+            .filter(|s| Skill::from_string(s).is_none())
             .map(|skill| {
                 li().classes("list-group-item")
                     .child(div().child(skill.to_string()))
             })
             .collect::<Vec<HtmlElement<Li>>>()
-    }))
+    };
+    div().child(
+        ul().classes("list-group")
+            .child(
+                Skill::ALL
+                    .iter()
+                    .map(|skill| SkillRow(*skill, skills))
+                    .collect::<Vec<HtmlElement<Li>>>(),
+            )
+            .child(unmatched),
+    )
 }
 
-pub fn OtherProfsTab(other_profs: Signal<Vec<String>>) -> HtmlDiv {
-    div().child(ul().classes("list-group").child(move || {
-        other_profs()
+fn SkillRow(skill: Skill, skills: Signal<Vec<String>>) -> HtmlElement<Li> {
+    let ability_scores = expect_context::<AbilityScoresReactive>();
+    let prof_bonus = get_prof_bonus();
+    let ability = skill.ability();
+    let proficient = Signal::derive(move || {
+        skills()
             .iter()
-            .map(|prof| {
-                li().classes("list-group-item")
-                    .child(div().child(prof.to_string()))
-            })
-            .collect::<Vec<HtmlElement<Li>>>()
-    }))
+            .any(|s| Skill::from_string(s) == Some(skill))
+    });
+    let bonus = {
+        let ability = ability.clone();
+        Signal::derive(move || {
+            let prof = if proficient() { prof_bonus() } else { 0 };
+            ability_scores.get_ability_mod(&ability) + prof
+        })
+    };
+    let roll = roll_check_on_click(
+        format!("{} check", skill),
+        bonus,
+        RollKind::Check(ability.clone()),
+    );
+    li().classes("list-group-item list-group-item-action")
+        .style("cursor", "pointer")
+        .attr("title", CLICK_TO_ROLL)
+        .on(ev::click, roll)
+        .child(
+            div()
+                .classes("d-flex justify-content-between")
+                .child(
+                    div()
+                        .class("fw-bold", proficient)
+                        .child(skill.to_string())
+                        .child(
+                            small().classes("text-muted ms-1").child(format!(
+                                "({})",
+                                &ability.to_string()[..3]
+                            )),
+                        ),
+                )
+                .child(div().child(bonus)),
+        )
+}
+
+pub fn OtherProfsTab(
+    other_profs: Signal<Vec<String>>,
+    tools: Signal<Vec<String>>,
+) -> HtmlDiv {
+    div()
+        .child(ul().classes("list-group mb-2").child(move || {
+            tools()
+                .into_iter()
+                .map(ToolRow)
+                .collect::<Vec<HtmlElement<Li>>>()
+        }))
+        .child(ul().classes("list-group").child(move || {
+            other_profs()
+                .iter()
+                .map(|prof| {
+                    li().classes("list-group-item")
+                        .child(div().child(prof.to_string()))
+                })
+                .collect::<Vec<HtmlElement<Li>>>()
+        }))
+}
+
+/// A tool proficiency. The ability used for a tool check depends on
+/// what the tool is being used for, so it can be picked per roll.
+fn ToolRow(tool: String) -> HtmlElement<Li> {
+    let ability_scores = expect_context::<AbilityScoresReactive>();
+    let prof_bonus = get_prof_bonus();
+    let (ability, set_ability) = create_signal(Ability::Intelligence);
+    let bonus = Signal::derive(move || {
+        ability_scores.get_ability_mod(&ability()) + prof_bonus()
+    });
+    let roll = roll_check_on_click(
+        format!("{} check", tool),
+        bonus,
+        Signal::derive(move || RollKind::Check(ability())),
+    );
+    li().classes("list-group-item d-flex align-items-center gap-2")
+        .child(div().classes("me-auto").child(tool))
+        .child(
+            CustomSelect()
+                .classes("form-select-sm w-auto")
+                .on(ev::change, move |e| {
+                    if let Some(a) =
+                        Ability::from_string(&event_target_value(&e))
+                    {
+                        set_ability(a)
+                    }
+                })
+                .child(
+                    Ability::ALL
+                        .iter()
+                        .map(|a| {
+                            option()
+                                .prop("value", a.to_string())
+                                .prop("selected", *a == Ability::Intelligence)
+                                .child(&a.to_string()[..3])
+                        })
+                        .collect::<OptionList>(),
+                ),
+        )
+        .child(
+            button()
+                .classes("btn btn-sm btn-outline-primary")
+                .attr("title", CLICK_TO_ROLL)
+                .child(move || format!("{:+}", bonus()))
+                .on(ev::click, roll),
+        )
 }
@@ -0,0 +1,194 @@
+use leptos::{html::*, *};
+
+use crate::{
+    components::*,
+    dice::{expression::DieRoll, roll_mode::RollMode, RollOutcome},
+    settings::Settings,
+};
+
+/// History of recent rolls, shown in the corner of the screen.
+/// Collapsed, it only shows the most recent roll.
+pub fn RollHistoryPanel() -> HtmlElement<Div> {
+    let roll_history = expect_context::<RwSignal<Vec<RollOutcome>>>();
+    let (expanded, set_expanded) = create_signal(false);
+    div()
+        .classes("position-fixed bottom-0 start-0 p-3 z-2 d-print-none")
+        .style("width", "22rem")
+        .child(
+            div()
+                .classes("d-flex align-items-center gap-3")
+                .child(RollModeSelector())
+                .child(DiceBoxToggle()),
+        )
+        .child(move || {
+            if roll_history.with(|h| h.is_empty()) {
+                return div();
+            }
+            div()
+                .classes("d-flex justify-content-between mb-1")
+                .child(
+                    a().attr("role", "button")
+                        .child(move || {
+                            if expanded() {
+                                "Hide roll history"
+                            } else {
+                                "Show roll history"
+                            }
+                        })
+                        .on(ev::click, move |_| set_expanded(!expanded())),
+                )
+                .child(
+                    a().attr("role", "button")
+                        .child("Clear")
+                        .on(ev::click, move |_| roll_history.set(vec![])),
+                )
+        })
+        .child(
+            div()
+                .style("max-height", "50vh")
+                .style("overflow-y", "auto")
+                .child(move || {
+                    let shown = if expanded() { usize::MAX } else { 1 };
+                    roll_history.with(|history| {
+                        history
+                            .iter()
+                            .take(shown)
+                            .map(RollOutcomeDisplay)
+                            .collect::<DivList>()
+                    })
+                }),
+        )
+}
+
+/// Buttons to pick the default mode for d20 rolls.
+fn RollModeSelector() -> HtmlDiv {
+    let roll_mode = expect_context::<RwSignal<RollMode>>();
+    div()
+        .classes("btn-group btn-group-sm mb-2 bg-body")
+        .attr("role", "group")
+        .attr("title", "Alt+click: advantage, Ctrl+click: disadvantage")
+        .child(
+            [
+                RollMode::Disadvantage,
+                RollMode::Normal,
+                RollMode::Advantage,
+            ]
+            .iter()
+            .map(|mode| {
+                let mode = *mode;
+                button()
+                    .attr("type", "button")
+                    .classes("btn btn-outline-secondary")
+                    .class("active", move || roll_mode() == mode)
+                    .child(mode.to_string())
+                    .on(ev::click, move |_| roll_mode.set(mode))
+            })
+            .collect::<Vec<HtmlElement<Button>>>(),
+        )
+}
+
+/// Setting to turn the 3D dice off, i.e. on slow devices.
+fn DiceBoxToggle() -> HtmlDiv {
+    let settings = expect_context::<RwSignal<Settings>>();
+    div()
+        .classes("form-check form-switch mb-2")
+        .child(
+            input()
+                .classes("form-check-input")
+                .attr("type", "checkbox")
+                .attr("role", "switch")
+                .id("diceBoxToggle")
+                .prop("checked", move || settings.with(|s| s.dice_3d))
+                .on(ev::change, move |e| {
+                    let checked = event_target_checked(&e);
+                    settings.update(|s| s.dice_3d = checked)
+                }),
+        )
+        .child(
+            label()
+                .classes("form-check-label")
+                .attr("for", "diceBoxToggle")
+                .child("3D dice"),
+        )
+}
+
+fn RollOutcomeDisplay(outcome: &RollOutcome) -> HtmlDiv {
+    let border = if outcome.critical {
+        "border border-warning rounded p-2 mb-2 bg-body"
+    } else {
+        "border rounded p-2 mb-2 bg-body"
+    };
+    let body = match &outcome.error {
+        Some(error) => div().classes("text-danger").child(error.to_string()),
+        None => div()
+            .classes("d-flex align-items-center gap-1 flex-wrap")
+            .child(h3().classes("mb-0 me-2").child(outcome.total))
+            .child(
+                outcome
+                    .dice
+                    .iter()
+                    .map(DieBadge)
+                    .collect::<Vec<HtmlElement<Span>>>(),
+            )
+            .child(ModifierDisplay(outcome.modifier)),
+    };
+    div()
+        .classes(border)
+        .child(
+            div()
+                .classes("d-flex justify-content-between small")
+                .child(div().child(outcome.label.to_string()))
+                .child(
+                    div()
+                        .classes("text-body-secondary")
+                        .child(outcome.expression.to_string()),
+                ),
+        )
+        .child(body)
+        .child(
+            outcome
+                .notes
+                .iter()
+                .map(|note| {
+                    div()
+                        .classes("small text-body-secondary")
+                        .child(note.to_string())
+                })
+                .collect::<Vec<HtmlDiv>>(),
+        )
+}
+
+/// A single die from a roll. Natural 20s and 1s on a d20 are
+/// highlighted, and dropped dice are struck out.
+fn DieBadge(die: &DieRoll) -> HtmlElement<Span> {
+    let color = if die.is_nat_20() {
+        "text-bg-success"
+    } else if die.is_nat_1() {
+        "text-bg-danger"
+    } else {
+        "text-bg-secondary"
+    };
+    let mut text =
+        format!("{}{}", if die.negative { "-" } else { "" }, die.value);
+    if die.exploded {
+        text += "!";
+    }
+    let badge = span()
+        .classes(format!("badge {}", color))
+        .attr("title", format!("d{}", die.sides))
+        .child(text);
+    if die.kept {
+        badge
+    } else {
+        badge.classes("opacity-50 text-decoration-line-through")
+    }
+}
+
+fn ModifierDisplay(modifier: i64) -> HtmlElement<Span> {
+    let text = match modifier {
+        0 => String::new(),
+        m if m < 0 => format!("- {}", -m),
+        m => format!("+ {}", m),
+    };
+    span().classes("text-body-secondary").child(text)
+}
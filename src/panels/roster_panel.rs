@@ -0,0 +1,513 @@
+use leptos::logging::log;
+use leptos::{html::*, *};
+use web_sys::HtmlInputElement;
+
+use crate::{
+    api::FuturesWrapper,
+    character_model::{Ability, AbilityScoresReactive, CharacterDocument},
+    components::*,
+    convert::{
+        fight_club,
+        foundry::{self, FoundryExport},
+        ImportReport, Imported, Open5eData,
+    },
+    files::{character_file_name, download_text_file, read_text_file},
+    get_character_switcher, get_current_background, get_current_class,
+    get_current_features, get_current_species, get_current_subspecies,
+    get_max_hp,
+    persistence::{
+        copy_character_storage, load_document, remove_character_storage,
+        write_document,
+    },
+    proficiencies::{save_proficiencies, skill_proficiencies},
+    roster::{display_name, Roster, RosterEntry},
+    save_file::SaveFile,
+    storage::backend,
+};
+
+/// Modal listing every character stored in the browser, with
+/// buttons to switch between, create, duplicate and delete them.
+pub fn RosterModal() -> HtmlElement<Div> {
+    div()
+        .classes("modal fade")
+        .id("rosterModal")
+        .attr("tabindex", "-1")
+        .attr("aria-labelledby", "rosterModalLabel")
+        .attr("aria-hidden", "true")
+        .child(
+            div()
+                .classes("modal-dialog modal-dialog-centered modal-lg")
+                .child(
+                    div()
+                        .classes("modal-content")
+                        .child(
+                            div()
+                                .classes("modal-header")
+                                .child(
+                                    h1().classes("modal-title fs-5")
+                                        .id("rosterModalLabel")
+                                        .child("Characters"),
+                                )
+                                .child(
+                                    button()
+                                        .attr("type", "button")
+                                        .classes("btn-close")
+                                        .attr("data-bs-dismiss", "modal")
+                                        .attr("aria-label", "Close"),
+                                ),
+                        )
+                        .child(
+                            div()
+                                .classes("modal-body")
+                                .child(CharacterList())
+                                .child(ImportExport())
+                                .child(RecycleBin()),
+                        ),
+                ),
+        )
+}
+
+fn CharacterList() -> HtmlDiv {
+    let roster = expect_context::<RwSignal<Roster>>();
+    let switch_character = get_character_switcher();
+    div()
+        .child(ul().classes("list-group mb-3").child(move || {
+            roster
+                .with(|r| r.active())
+                .into_iter()
+                .map(CharacterRow)
+                .collect::<Vec<HtmlElement<Li>>>()
+        }))
+        .child(
+            button()
+                .attr("type", "button")
+                .classes("btn btn-primary")
+                .child("New character")
+                .on(ev::click, move |_| {
+                    let mut id = String::new();
+                    roster.update(|r| id = r.add(""));
+                    switch_character(&id);
+                }),
+        )
+}
+
+fn CharacterRow(entry: RosterEntry) -> HtmlElement<Li> {
+    let roster = expect_context::<RwSignal<Roster>>();
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    let switch_character = get_character_switcher();
+    let id = entry.id.to_string();
+    let is_current = roster.with_untracked(|r| r.current == id);
+
+    let rename = {
+        let id = id.to_string();
+        move |e: ev::Event| {
+            let name = event_target_value(&e);
+            if roster.with_untracked(|r| r.current == id) {
+                // The roster picks up the new name from the character
+                document.update(|d| d.details.name = name);
+            } else {
+                let mut stored = load_document(&backend(), &id);
+                stored.details.name = name.to_string();
+                write_document(&backend(), &id, &stored);
+                roster.update(|r| r.rename(&id, &name));
+            }
+        }
+    };
+    let duplicate = {
+        let id = id.to_string();
+        let name = format!("{} (copy)", display_name(&entry));
+        move |_| {
+            let mut new_id = String::new();
+            roster.update(|r| new_id = r.add(&name));
+            copy_character_storage(&backend(), &id, &new_id);
+            let mut copy = load_document(&backend(), &new_id);
+            copy.details.name = name.to_string();
+            write_document(&backend(), &new_id, &copy);
+        }
+    };
+    let delete = {
+        let id = id.to_string();
+        move |_| {
+            roster.update(|r| r.delete(&id));
+            if roster.with_untracked(|r| r.current == id) {
+                // Open another character, or a blank one if this
+                // was the last
+                let mut next = String::new();
+                roster.update(|r| {
+                    next = match r.active().first() {
+                        Some(entry) => entry.id.to_string(),
+                        None => r.add(""),
+                    }
+                });
+                switch_character(&next);
+            }
+        }
+    };
+    let open = {
+        let id = id.to_string();
+        move |_| switch_character(&id)
+    };
+
+    let open_button = if is_current {
+        span().classes("badge text-bg-primary").child("Open")
+    } else {
+        span().child(
+            button()
+                .attr("type", "button")
+                .classes("btn btn-sm btn-outline-primary")
+                .child("Switch to")
+                .on(ev::click, open),
+        )
+    };
+    li().classes("list-group-item d-flex align-items-center gap-2")
+        .child(
+            input()
+                .classes("form-control form-control-sm")
+                .attr("placeholder", "Unnamed character")
+                .prop("value", entry.name.to_string())
+                .on(ev::change, rename),
+        )
+        .child(open_button)
+        .child(
+            button()
+                .attr("type", "button")
+                .classes("btn btn-sm btn-outline-secondary")
+                .child("Duplicate")
+                .on(ev::click, duplicate),
+        )
+        .child(
+            button()
+                .attr("type", "button")
+                .classes("btn btn-sm btn-outline-danger")
+                .child("Delete")
+                .on(ev::click, delete),
+        )
+}
+
+/// Formats characters can be imported from.
+#[derive(Clone, Copy)]
+enum ImportFormat {
+    SaveFile,
+    Foundry,
+    FightClub,
+}
+
+impl ImportFormat {
+    /// Read a character from a file's contents.
+    fn import(&self, data: &str) -> Result<Imported, String> {
+        match self {
+            ImportFormat::SaveFile => SaveFile::from_json(data)
+                .map(|save| Imported {
+                    document: save.document,
+                    report: ImportReport::default(),
+                })
+                .map_err(|e| e.to_string()),
+            ImportFormat::Foundry => {
+                with_open5e_data(|data_5e| foundry::import_actor(data, data_5e))
+            }
+            ImportFormat::FightClub => with_open5e_data(|data_5e| {
+                fight_club::import_character(data, data_5e)
+            }),
+        }
+    }
+}
+
+/// Call `f` with the lists from Open5e, once they've loaded.
+fn with_open5e_data<T>(
+    f: impl FnOnce(Open5eData) -> Result<T, String>,
+) -> Result<T, String> {
+    let api_data = expect_context::<FuturesWrapper>();
+    let loaded = untrack(|| {
+        (
+            api_data.classes.get(),
+            api_data.species.get(),
+            api_data.backgrounds.get(),
+            api_data.weapons.get(),
+        )
+    });
+    match loaded {
+        (Some(classes), Some(species), Some(backgrounds), Some(weapons)) => {
+            f(Open5eData {
+                classes: &classes,
+                species: &species,
+                backgrounds: &backgrounds,
+                weapons: &weapons,
+            })
+        }
+        _ => {
+            Err("Open5e is still loading, so try again in a moment".to_string())
+        }
+    }
+}
+
+/// Save the open character to a file, or add a character from one.
+fn ImportExport() -> HtmlDiv {
+    let roster = expect_context::<RwSignal<Roster>>();
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    let switch_character = get_character_switcher();
+    let (import_error, set_import_error) = create_signal(None::<String>);
+    // What couldn't be brought across in the last import
+    let (import_report, set_import_report) = create_signal(None::<Vec<String>>);
+
+    let export = move |_| {
+        let save = SaveFile::new(document.get_untracked());
+        let file_name = document
+            .with_untracked(|d| character_file_name(&d.details.name, "json"));
+        if download_text_file(&file_name, "application/json", &save.to_json())
+            .is_err()
+        {
+            log!("Could not download {}", file_name);
+        }
+    };
+    let export_foundry = {
+        let ability_scores = expect_context::<AbilityScoresReactive>();
+        let (class, species, subspecies, background) = (
+            get_current_class(),
+            get_current_species(),
+            get_current_subspecies(),
+            get_current_background(),
+        );
+        let (features, max_hp) = (get_current_features(), get_max_hp());
+        move |_| {
+            let features = features.get_untracked();
+            let document = document.get_untracked();
+            let actor = foundry::export_actor(&FoundryExport {
+                document: &document,
+                class_name: class
+                    .get_untracked()
+                    .map(|c| c.name)
+                    .unwrap_or_default(),
+                // Foundry has one name for both, i.e. "Hill Dwarf"
+                species_name: subspecies
+                    .get_untracked()
+                    .map(|s| s.name)
+                    .or_else(|| species.get_untracked().map(|s| s.name))
+                    .unwrap_or_default(),
+                background_name: background
+                    .get_untracked()
+                    .map(|b| b.name)
+                    .unwrap_or_default(),
+                ability_scores: Ability::ALL
+                    .into_iter()
+                    .map(|a| {
+                        let score =
+                            untrack(|| ability_scores.get_ability_score(&a));
+                        (a, score)
+                    })
+                    .collect(),
+                saves: save_proficiencies(&features),
+                skills: skill_proficiencies(&features),
+                max_hp: max_hp.get_untracked(),
+                features: &features,
+            });
+            let file_name = character_file_name(
+                &format!("{} (Foundry VTT)", document.details.name),
+                "json",
+            );
+            let json = serde_json::to_string_pretty(&actor).unwrap_or_default();
+            if download_text_file(&file_name, "application/json", &json)
+                .is_err()
+            {
+                log!("Could not download {}", file_name);
+            }
+        }
+    };
+    let import =
+        create_action(move |(file, format): &(web_sys::File, ImportFormat)| {
+            let (file, format) = (file.clone(), *format);
+            async move {
+                let result = read_text_file(&file)
+                    .await
+                    .and_then(|data| format.import(&data));
+                match result {
+                    Ok(imported) => {
+                        // Imports are added as a new character, rather
+                        // than replacing the open one.
+                        let document = imported.document;
+                        let mut id = String::new();
+                        roster.update(|r| id = r.add(&document.details.name));
+                        write_document(&backend(), &id, &document);
+                        switch_character(&id);
+                        set_import_error(None);
+                        set_import_report(
+                            Some(imported.report.unmapped)
+                                .filter(|r| !r.is_empty()),
+                        );
+                    }
+                    Err(error) => {
+                        set_import_report(None);
+                        set_import_error(Some(format!(
+                            "Could not import {}: {}",
+                            file.name(),
+                            error
+                        )))
+                    }
+                }
+            }
+        });
+
+    div()
+        .child(
+            div()
+                .classes("mt-3 d-flex gap-2")
+                .child(
+                    button()
+                        .attr("type", "button")
+                        .classes("btn btn-outline-secondary")
+                        .child("Export to file")
+                        .on(ev::click, export),
+                )
+                .child(
+                    button()
+                        .attr("type", "button")
+                        .classes("btn btn-outline-secondary")
+                        .child("Export to Foundry VTT")
+                        .on(ev::click, export_foundry),
+                )
+                .child(ImportButton(
+                    "Import from file",
+                    "characterImportInput",
+                    ".json,application/json",
+                    move |file| import.dispatch((file, ImportFormat::SaveFile)),
+                ))
+                .child(ImportButton(
+                    "Import from Foundry VTT",
+                    "foundryImportInput",
+                    ".json,application/json",
+                    move |file| import.dispatch((file, ImportFormat::Foundry)),
+                ))
+                .child(ImportButton(
+                    "Import from Fight Club 5",
+                    "fightClubImportInput",
+                    ".xml,text/xml,application/xml",
+                    move |file| {
+                        import.dispatch((file, ImportFormat::FightClub))
+                    },
+                )),
+        )
+        .child(move || {
+            import_error().map(|error| {
+                div()
+                    .classes("alert alert-danger mt-2 mb-0")
+                    .attr("role", "alert")
+                    .child(error)
+            })
+        })
+        .child(move || {
+            import_report().map(|report| {
+                div()
+                    .classes("alert alert-warning mt-2 mb-0")
+                    .attr("role", "alert")
+                    .child(
+                        "Imported, but some things couldn't be brought \
+                        across and need filling in by hand:",
+                    )
+                    .child(
+                        ul().classes("mb-0").child(
+                            report
+                                .into_iter()
+                                .map(|line| li().child(line))
+                                .collect_view(),
+                        ),
+                    )
+            })
+        })
+}
+
+/// Button that opens a file picker, calling `on_file` with the
+/// file picked.
+fn ImportButton(
+    text: &'static str,
+    id: &'static str,
+    accept: &'static str,
+    on_file: impl Fn(web_sys::File) + 'static,
+) -> HtmlDiv {
+    div()
+        .child(
+            label()
+                .classes("btn btn-outline-secondary")
+                .attr("for", id)
+                .child(text),
+        )
+        .child(
+            input()
+                .attr("type", "file")
+                .attr("accept", accept)
+                .classes("d-none")
+                .id(id)
+                .on(ev::change, move |e| {
+                    let input = event_target::<HtmlInputElement>(&e);
+                    if let Some(file) = input.files().and_then(|f| f.get(0)) {
+                        on_file(file);
+                    }
+                    // Clear the input, so that the same file can be
+                    // picked again
+                    input.set_value("");
+                }),
+        )
+}
+
+/// Deleted characters, which can be restored until they're
+/// deleted from the recycle bin as well.
+fn RecycleBin() -> HtmlDiv {
+    let roster = expect_context::<RwSignal<Roster>>();
+    div().child(move || {
+        let deleted = roster.with(|r| r.recycle_bin());
+        if deleted.is_empty() {
+            return div();
+        }
+        div()
+            .classes("mt-4")
+            .child(h2().classes("fs-6").child("Recycle bin"))
+            .child(
+                ul().classes("list-group").child(
+                    deleted
+                        .into_iter()
+                        .map(RecycleBinRow)
+                        .collect::<Vec<HtmlElement<Li>>>(),
+                ),
+            )
+    })
+}
+
+fn RecycleBinRow(entry: RosterEntry) -> HtmlElement<Li> {
+    let roster = expect_context::<RwSignal<Roster>>();
+    let restore = {
+        let id = entry.id.to_string();
+        move |_| roster.update(|r| r.restore(&id))
+    };
+    let purge = {
+        let id = entry.id.to_string();
+        let name = display_name(&entry);
+        move |_| {
+            let message = format!("Permanently delete {}?", name);
+            let confirmed = web_sys::window()
+                .and_then(|w| w.confirm_with_message(&message).ok())
+                .unwrap_or(false);
+            if confirmed {
+                remove_character_storage(&backend(), &id);
+                roster.update(|r| r.purge(&id));
+            }
+        }
+    };
+    li().classes("list-group-item d-flex align-items-center gap-2")
+        .child(
+            div()
+                .classes("me-auto text-muted")
+                .child(display_name(&entry)),
+        )
+        .child(
+            button()
+                .attr("type", "button")
+                .classes("btn btn-sm btn-outline-secondary")
+                .child("Restore")
+                .on(ev::click, restore),
+        )
+        .child(
+            button()
+                .attr("type", "button")
+                .classes("btn btn-sm btn-outline-danger")
+                .child("Delete forever")
+                .on(ev::click, purge),
+        )
+}
@@ -0,0 +1,186 @@
+use std::rc::Rc;
+
+use leptos::logging::log;
+use leptos::{html::*, *};
+use web_sys::HtmlInputElement;
+
+use crate::{
+    character_model::CharacterDocument,
+    components::*,
+    dice::RollOutcome,
+    files::copy_to_clipboard,
+    get_character_switcher,
+    persistence::*,
+    roster::Roster,
+    share::*,
+    storage::{backend, set_backend, swap_in_memory, StorageBackend},
+};
+
+/// Whether the open character came from a share link, and so can't
+/// be changed.
+#[derive(Clone, Copy)]
+pub struct ReadOnly(pub RwSignal<bool>);
+
+/// The character someone shared, if the app was opened from a share
+/// link. Shared characters aren't saved, so storage is swapped for
+/// an empty one while it's open; the player's own storage is
+/// returned alongside it, for when the character is imported.
+pub fn open_shared_character(
+) -> Option<(CharacterDocument, Rc<dyn StorageBackend>)> {
+    match shared_character_in_url()? {
+        Ok(document) => Some((document, swap_in_memory())),
+        Err(e) => {
+            log!("Could not open shared character: {}", e);
+            None
+        }
+    }
+}
+
+/// Shown above a shared character, with buttons to keep a copy of
+/// it or go back to the player's own characters.
+pub fn SharedCharacterBanner(home: Rc<dyn StorageBackend>) -> HtmlDiv {
+    let ReadOnly(read_only) = expect_context::<ReadOnly>();
+    let roster = expect_context::<RwSignal<Roster>>();
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    let roll_history = expect_context::<RwSignal<Vec<RollOutcome>>>();
+    let switch_character = get_character_switcher();
+
+    let import = move |_| {
+        // Back to the player's own storage, and everything in it.
+        // Settings were kept in it all along.
+        set_backend(home.clone());
+        roll_history.set(load_roll_history(&backend()));
+        let shared = document.get_untracked();
+        let mut home_roster = load_roster(&backend());
+        let id = home_roster.add(&shared.details.name);
+        roster.set(home_roster);
+        write_document(&backend(), &id, &shared);
+        switch_character(&id);
+        read_only.set(false);
+        forget_share_link();
+    };
+
+    div().child(move || {
+        read_only().then(|| {
+            div()
+                .classes("container alert alert-info d-flex align-items-center gap-2 mt-4")
+                .attr("role", "alert")
+                .child(div().classes("me-auto").child(move || {
+                    format!(
+                        "{} was shared with you. It can't be changed, \
+                        but you can import it as your own copy.",
+                        document.with(|d| {
+                            if d.details.name.is_empty() {
+                                "This character".to_string()
+                            } else {
+                                d.details.name.to_string()
+                            }
+                        })
+                    )
+                }))
+                .child(
+                    button()
+                        .attr("type", "button")
+                        .classes("btn btn-primary")
+                        .child("Import as my copy")
+                        .on(ev::click, import.clone()),
+                )
+                .child(
+                    button()
+                        .attr("type", "button")
+                        .classes("btn btn-outline-secondary")
+                        .child("Back to my characters")
+                        .on(ev::click, |_| close_shared_character()),
+                )
+        })
+    })
+}
+
+/// Modal with a link to the open character, to send to someone else.
+pub fn ShareModal() -> HtmlElement<Div> {
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    let link = create_memo(move |_| document.with(share_link));
+    let (copied, set_copied) = create_signal(false);
+    create_effect(move |_| {
+        link.track();
+        set_copied(false);
+    });
+    let copy = create_action(move |link: &String| {
+        let link = link.to_string();
+        async move {
+            match copy_to_clipboard(&link).await {
+                Ok(_) => set_copied(true),
+                Err(e) => log!("Could not copy the link: {:?}", e),
+            }
+        }
+    });
+
+    div()
+        .classes("modal fade")
+        .id("shareModal")
+        .attr("tabindex", "-1")
+        .attr("aria-labelledby", "shareModalLabel")
+        .attr("aria-hidden", "true")
+        .child(
+            div().classes("modal-dialog modal-dialog-centered").child(
+                div()
+                    .classes("modal-content")
+                    .child(
+                        div()
+                            .classes("modal-header")
+                            .child(
+                                h1().classes("modal-title fs-5")
+                                    .id("shareModalLabel")
+                                    .child("Share"),
+                            )
+                            .child(
+                                button()
+                                    .attr("type", "button")
+                                    .classes("btn-close")
+                                    .attr("data-bs-dismiss", "modal")
+                                    .attr("aria-label", "Close"),
+                            ),
+                    )
+                    .child(
+                        div()
+                            .classes("modal-body")
+                            .child(p().classes("text-muted").child(
+                                "Anyone with this link can see the \
+                                character as it is now, e.g. to show your \
+                                DM. They can't change it, but they can \
+                                import their own copy.",
+                            ))
+                            .child(
+                                div()
+                                    .classes("input-group")
+                                    .child(
+                                        input()
+                                            .classes("form-control")
+                                            .attr("readonly", true)
+                                            .attr("aria-label", "Share link")
+                                            .prop("value", link)
+                                            .on(ev::focus, |e| {
+                                                event_target::<HtmlInputElement>(&e)
+                                                    .select()
+                                            }),
+                                    )
+                                    .child(
+                                        button()
+                                            .attr("type", "button")
+                                            .classes("btn btn-outline-secondary")
+                                            .child(move || {
+                                                if copied() {
+                                                    "Copied"
+                                                } else {
+                                                    "Copy"
+                                                }
+                                            })
+                                            .on(ev::click, move |_| {
+                                                copy.dispatch(link.get_untracked())
+                                            }),
+                                    ),
+                            ),
+                    ),
+            ),
+        )
+}
@@ -0,0 +1,142 @@
+use leptos::logging::log;
+use leptos::{html::*, *};
+
+use crate::{
+    character_model::CharacterDocument,
+    files::{character_file_name, copy_to_clipboard, download_text_file},
+    panels::print_panel::get_print_sheet,
+    stat_block::*,
+};
+
+/// Modal with the open character as a stat block, to copy or download.
+pub fn StatBlockModal() -> HtmlElement<Div> {
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    let sheet = get_print_sheet();
+    let (format, set_format) = create_signal(StatBlockFormat::Markdown);
+    let stat_block =
+        Signal::derive(move || sheet.with(|s| render_stat_block(s, format())));
+    let (copied, set_copied) = create_signal(false);
+    create_effect(move |_| {
+        stat_block.track();
+        set_copied(false);
+    });
+    let copy = create_action(move |text: &String| {
+        let text = text.to_string();
+        async move {
+            match copy_to_clipboard(&text).await {
+                Ok(_) => set_copied(true),
+                Err(e) => log!("Could not copy the stat block: {:?}", e),
+            }
+        }
+    });
+    let download = move |_| {
+        let format = format.get_untracked();
+        let name = document.with_untracked(|d| d.details.name.to_string());
+        if let Err(e) = download_text_file(
+            &character_file_name(
+                &format!("{} (stat block)", name),
+                format.extension(),
+            ),
+            format.mime_type(),
+            &stat_block.get_untracked(),
+        ) {
+            log!("Could not download the stat block: {:?}", e);
+        }
+    };
+    let format_button = move |label: &'static str, value: StatBlockFormat| {
+        button()
+            .attr("type", "button")
+            .classes("btn btn-outline-secondary")
+            .class("active", move || format() == value)
+            .child(label)
+            .on(ev::click, move |_| set_format(value))
+    };
+
+    div()
+        .classes("modal fade")
+        .id("statBlockModal")
+        .attr("tabindex", "-1")
+        .attr("aria-labelledby", "statBlockModalLabel")
+        .attr("aria-hidden", "true")
+        .child(
+            div()
+                .classes("modal-dialog modal-dialog-centered modal-lg")
+                .child(
+                    div()
+                        .classes("modal-content")
+                        .child(
+                            div()
+                                .classes("modal-header")
+                                .child(
+                                    h1().classes("modal-title fs-5")
+                                        .id("statBlockModalLabel")
+                                        .child("Stat Block"),
+                                )
+                                .child(
+                                    button()
+                                        .attr("type", "button")
+                                        .classes("btn-close")
+                                        .attr("data-bs-dismiss", "modal")
+                                        .attr("aria-label", "Close"),
+                                ),
+                        )
+                        .child(
+                            div()
+                                .classes("modal-body")
+                                .child(p().classes("text-muted").child(
+                                    "The character written out for posting, \
+                                i.e. in Discord or on a wiki.",
+                                ))
+                                .child(
+                                    div()
+                                        .classes("btn-group mb-2")
+                                        .attr("role", "group")
+                                        .child(format_button(
+                                            "Markdown",
+                                            StatBlockFormat::Markdown,
+                                        ))
+                                        .child(format_button(
+                                            "Plain text",
+                                            StatBlockFormat::PlainText,
+                                        )),
+                                )
+                                .child(
+                                    textarea()
+                                        .classes("form-control font-monospace")
+                                        .attr("readonly", true)
+                                        .attr("rows", 16)
+                                        .attr("aria-label", "Stat block")
+                                        .prop("value", stat_block),
+                                ),
+                        )
+                        .child(
+                            div()
+                                .classes("modal-footer")
+                                .child(
+                                    button()
+                                        .attr("type", "button")
+                                        .classes("btn btn-outline-secondary")
+                                        .child("Download")
+                                        .on(ev::click, download),
+                                )
+                                .child(
+                                    button()
+                                        .attr("type", "button")
+                                        .classes("btn btn-primary")
+                                        .child(move || {
+                                            if copied() {
+                                                "Copied"
+                                            } else {
+                                                "Copy"
+                                            }
+                                        })
+                                        .on(ev::click, move |_| {
+                                            copy.dispatch(
+                                                stat_block.get_untracked(),
+                                            )
+                                        }),
+                                ),
+                        ),
+                ),
+        )
+}
@@ -1,83 +1,79 @@
-use leptos::logging::log;
 use leptos::{
-    create_action, create_signal, create_slice, ev, event_target_value,
-    expect_context, html::*, SignalSetter,
+    create_signal, create_slice, ev, event_target_value, expect_context,
+    html::*, SignalSetter,
 };
 use leptos::{RwSignal, Signal};
 
 use crate::character_model::{
-    AbilityScores, AbilityScoresReactive, CharacterDetails,
+    Ability, AbilityScores, AbilityScoresReactive, CharacterDocument,
 };
 use crate::components::*;
-use crate::dice::get_dice_result;
+use crate::dice::roll_mode::RollKind;
+use crate::roll_check_on_click;
 
 pub fn StatsPanel() -> HtmlElement<Div> {
     let ability_scores = expect_context::<AbilityScoresReactive>();
-    let character = expect_context::<RwSignal<CharacterDetails>>();
+    let document = expect_context::<RwSignal<CharacterDocument>>();
     HorizontalPanel().child(
         GridRow()
             .child(GridCol().child(AbilityScoreBox(
-                "Strength",
+                Ability::Strength,
                 Signal::derive(move || ability_scores.str_score()),
                 create_slice(
-                    character,
-                    |c| c.ability_scores.base_str,
-                    |c, v| c.ability_scores.base_str = v,
+                    document,
+                    |d| d.details.ability_scores.base_str,
+                    |d, v| d.details.ability_scores.base_str = v,
                 ),
             )))
             .child(GridCol().child(AbilityScoreBox(
-                "Dexterity",
+                Ability::Dexterity,
                 Signal::derive(move || ability_scores.dex_score()),
                 create_slice(
-                    character,
-                    |c| c.ability_scores.base_dex,
-                    |c, v| c.ability_scores.base_dex = v,
+                    document,
+                    |d| d.details.ability_scores.base_dex,
+                    |d, v| d.details.ability_scores.base_dex = v,
                 ),
             )))
             .child(GridCol().child(AbilityScoreBox(
-                "Constitution",
+                Ability::Constitution,
                 Signal::derive(move || ability_scores.con_score()),
                 create_slice(
-                    character,
-                    |c| c.ability_scores.base_con,
-                    |c, v| c.ability_scores.base_con = v,
+                    document,
+                    |d| d.details.ability_scores.base_con,
+                    |d, v| d.details.ability_scores.base_con = v,
                 ),
             )))
             .child(GridCol().child(AbilityScoreBox(
-                "Wisdom",
+                Ability::Wisdom,
                 Signal::derive(move || ability_scores.wis_score()),
                 create_slice(
-                    character,
-                    |c| c.ability_scores.base_wis,
-                    |c, v| c.ability_scores.base_wis = v,
+                    document,
+                    |d| d.details.ability_scores.base_wis,
+                    |d, v| d.details.ability_scores.base_wis = v,
                 ),
             )))
             .child(GridCol().child(AbilityScoreBox(
-                "Intelligence",
+                Ability::Intelligence,
                 Signal::derive(move || ability_scores.int_score()),
                 create_slice(
-                    character,
-                    |c| c.ability_scores.base_int,
-                    |c, v| c.ability_scores.base_int = v,
+                    document,
+                    |d| d.details.ability_scores.base_int,
+                    |d, v| d.details.ability_scores.base_int = v,
                 ),
             )))
             .child(GridCol().child(AbilityScoreBox(
-                "Charisma",
+                Ability::Charisma,
                 Signal::derive(move || ability_scores.cha_score()),
                 create_slice(
-                    character,
-                    |c| c.ability_scores.base_cha,
-                    |c, v| c.ability_scores.base_cha = v,
+                    document,
+                    |d| d.details.ability_scores.base_cha,
+                    |d, v| d.details.ability_scores.base_cha = v,
                 ),
             ))),
     )
 }
-struct RollDiceParams {
-    dice_string: String,
-    bonus: i64,
-}
 fn AbilityScoreBox(
-    score_name: &str,
+    ability: Ability,
     score: Signal<i32>,
     (score_base, set_score_base): (Signal<i32>, SignalSetter<i32>),
 ) -> HtmlElement<Div> {
@@ -88,22 +84,16 @@ fn AbilityScoreBox(
     let display_score =
         move || if edit_mode() { score_base() } else { score() };
 
-    let roll_dice = create_action(|input: &RollDiceParams| {
-        // Get a copy of the input values so that we can move them into
-        // the async block below.
-        let roll_string = input.dice_string.clone();
-        let roll_bonus = input.bonus;
-        async move {
-            let r = get_dice_result(&roll_string).await;
-            if let Some(result) = r.first() {
-                log!("{}", result.value + roll_bonus)
-            }
-        }
-    });
+    let score_name = ability.to_string().to_string();
+    let roll = roll_check_on_click(
+        format!("{} check", score_name),
+        score_mod,
+        RollKind::Check(ability),
+    );
 
     div()
         .classes("d-flex flex-column")
-        .child(score_name.to_string())
+        .child(score_name)
         .child(
             div()
                 .classes("border rounded mx-auto d-flex align-items-center justify-content-center")
@@ -113,14 +103,8 @@ fn AbilityScoreBox(
                 .style("text-align", "center")
                 .style("cursor", "pointer")
                 .child(h2().child(score_mod).style("margin-top", "-10%"))
-                .on(ev::click, move |_| {
-                    roll_dice.dispatch(
-                        RollDiceParams{
-                            dice_string:"1d20".to_string(), 
-                            bonus: i64::from(score_mod()) 
-                        }
-                    )
-                }),
+                .attr("title", "Alt+click: advantage, Ctrl+click: disadvantage")
+                .on(ev::click, roll),
         )
         .child(
             input()
@@ -0,0 +1,430 @@
+use std::time::Duration;
+
+use leptos::{html::*, leptos_dom::helpers::TimeoutHandle, *};
+
+use crate::{
+    character_model::CharacterDocument, components::*, roster::Roster,
+    settings::Settings, storage::backend, sync::*,
+};
+
+/// Syncs the open character with the sync server, and downloads any
+/// characters made on other devices.
+#[derive(Clone, Copy)]
+pub struct Syncer(pub Action<(), ()>);
+
+/// Set up syncing, and sync the open character automatically
+/// a few seconds after it changes, if that's turned on.
+pub fn create_syncer() -> Syncer {
+    let roster = expect_context::<RwSignal<Roster>>();
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    let settings = expect_context::<RwSignal<Settings>>();
+    let sync_status = expect_context::<RwSignal<SyncStatus>>();
+
+    let sync = create_action(move |_: &()| {
+        let id = roster.with_untracked(|r| r.current.to_string());
+        let known = roster.with_untracked(|r| r.known_ids());
+        let local = document.get_untracked();
+        let sync_settings = settings.with_untracked(|s| s.sync.clone());
+        async move {
+            sync_status.set(SyncStatus::Syncing);
+            let storage = backend();
+            let result =
+                match sync_character(&sync_settings, &storage, &id, &local)
+                    .await
+                {
+                    Ok(outcome) => {
+                        fetch_new_characters(&sync_settings, &storage, &known)
+                            .await
+                            .map(|new_characters| (outcome, new_characters))
+                    }
+                    Err(e) => Err(e),
+                };
+            let (outcome, new_characters) = match result {
+                Ok(result) => result,
+                Err(e) => {
+                    return sync_status.set(SyncStatus::Failed(e.to_string()))
+                }
+            };
+            if !new_characters.is_empty() {
+                roster.update(|r| {
+                    for (id, name) in &new_characters {
+                        r.add_with_id(id, name);
+                    }
+                });
+            }
+            match outcome {
+                SyncOutcome::Downloaded(remote) => {
+                    // Only take the server's version if nothing has
+                    // changed here while we were syncing; if it has,
+                    // the next sync will sort it out.
+                    let unchanged = roster.with_untracked(|r| r.current == id)
+                        && document.with_untracked(|d| *d == local);
+                    if unchanged {
+                        accept_remote(&storage, &remote);
+                        document.set(remote.document);
+                    }
+                    sync_status.set(SyncStatus::Synced);
+                }
+                SyncOutcome::Conflict(remote) => {
+                    sync_status.set(SyncStatus::Conflict(Box::new(remote)))
+                }
+                SyncOutcome::UpToDate | SyncOutcome::Uploaded => {
+                    sync_status.set(SyncStatus::Synced)
+                }
+            }
+        }
+    });
+
+    let pending = store_value(None::<TimeoutHandle>);
+    create_effect(move |_| {
+        document.track();
+        let enabled =
+            settings.with(|s| s.sync.enabled && s.sync.is_configured());
+        if let Some(handle) = pending.get_value() {
+            handle.clear();
+        }
+        if enabled {
+            let handle = set_timeout_with_handle(
+                move || sync.dispatch(()),
+                Duration::from_secs(3),
+            );
+            pending.set_value(handle.ok());
+        }
+    });
+    Syncer(sync)
+}
+
+/// Modal for setting up the sync server, syncing, and settling
+/// conflicts between this device and the server.
+pub fn SyncModal() -> HtmlElement<Div> {
+    div()
+        .classes("modal fade")
+        .id("syncModal")
+        .attr("tabindex", "-1")
+        .attr("aria-labelledby", "syncModalLabel")
+        .attr("aria-hidden", "true")
+        .child(
+            div()
+                .classes("modal-dialog modal-dialog-centered modal-lg")
+                .child(
+                    div()
+                        .classes("modal-content")
+                        .child(
+                            div()
+                                .classes("modal-header")
+                                .child(
+                                    h1().classes("modal-title fs-5")
+                                        .id("syncModalLabel")
+                                        .child("Sync"),
+                                )
+                                .child(
+                                    button()
+                                        .attr("type", "button")
+                                        .classes("btn-close")
+                                        .attr("data-bs-dismiss", "modal")
+                                        .attr("aria-label", "Close"),
+                                ),
+                        )
+                        .child(
+                            div()
+                                .classes("modal-body")
+                                .child(SyncSettingsForm())
+                                .child(SyncStatusDisplay())
+                                .child(ConflictResolver()),
+                        ),
+                ),
+        )
+}
+
+fn SyncSettingsForm() -> HtmlDiv {
+    let settings = expect_context::<RwSignal<Settings>>();
+    let Syncer(sync) = expect_context::<Syncer>();
+    let (server_url, set_server_url) = create_slice(
+        settings,
+        |s| s.sync.server_url.to_string(),
+        |s, v| s.sync.server_url = v,
+    );
+    let (token, set_token) = create_slice(
+        settings,
+        |s| s.sync.token.to_string(),
+        |s, v| s.sync.token = v,
+    );
+    let (enabled, set_enabled) =
+        create_slice(settings, |s| s.sync.enabled, |s, v| s.sync.enabled = v);
+    let configured = move || settings.with(|s| s.sync.is_configured());
+
+    div()
+        .child(p().classes("text-muted").child(
+            "Keep characters in step between devices by syncing them \
+                with a Goblin Sheet sync server, e.g. one running on a \
+                home server.",
+        ))
+        .child(
+            div()
+                .classes("mb-2")
+                .child(
+                    label()
+                        .classes("form-label")
+                        .attr("for", "syncServerUrl")
+                        .child("Server address"),
+                )
+                .child(
+                    input()
+                        .classes("form-control")
+                        .id("syncServerUrl")
+                        .attr("placeholder", "http://192.168.1.20:8787")
+                        .prop("value", server_url)
+                        .on(ev::change, move |e| {
+                            set_server_url(event_target_value(&e))
+                        }),
+                ),
+        )
+        .child(
+            div()
+                .classes("mb-2")
+                .child(
+                    label()
+                        .classes("form-label")
+                        .attr("for", "syncToken")
+                        .child("Token"),
+                )
+                .child(
+                    input()
+                        .classes("form-control")
+                        .attr("type", "password")
+                        .id("syncToken")
+                        .prop("value", token)
+                        .on(ev::change, move |e| {
+                            set_token(event_target_value(&e))
+                        }),
+                ),
+        )
+        .child(
+            div()
+                .classes("d-flex align-items-center gap-3 mb-3")
+                .child(
+                    div()
+                        .classes("form-check form-switch mb-0")
+                        .child(
+                            input()
+                                .classes("form-check-input")
+                                .attr("type", "checkbox")
+                                .attr("role", "switch")
+                                .id("syncEnabledToggle")
+                                .prop("checked", enabled)
+                                .on(ev::change, move |e| {
+                                    set_enabled(event_target_checked(&e))
+                                }),
+                        )
+                        .child(
+                            label()
+                                .classes("form-check-label")
+                                .attr("for", "syncEnabledToggle")
+                                .child("Sync automatically"),
+                        ),
+                )
+                .child(
+                    button()
+                        .attr("type", "button")
+                        .classes("btn btn-primary ms-auto")
+                        .prop("disabled", move || {
+                            !configured() || sync.pending().get()
+                        })
+                        .child("Sync now")
+                        .on(ev::click, move |_| sync.dispatch(())),
+                ),
+        )
+}
+
+fn SyncStatusDisplay() -> HtmlDiv {
+    let sync_status = expect_context::<RwSignal<SyncStatus>>();
+    div().child(move || match sync_status() {
+        SyncStatus::Idle | SyncStatus::Conflict(_) => div(),
+        SyncStatus::Syncing => div().classes("text-muted").child("Syncing..."),
+        SyncStatus::Synced => div().classes("text-success").child("Synced"),
+        SyncStatus::Failed(error) => div()
+            .classes("alert alert-danger mb-0")
+            .attr("role", "alert")
+            .child(format!("Could not sync: {}", error)),
+    })
+}
+
+/// Shown when the open character has been changed both here and on
+/// the server. Either version can be kept, or they can be merged by
+/// picking which version to take each part of the character from.
+fn ConflictResolver() -> HtmlDiv {
+    let roster = expect_context::<RwSignal<Roster>>();
+    let document = expect_context::<RwSignal<CharacterDocument>>();
+    let settings = expect_context::<RwSignal<Settings>>();
+    let sync_status = expect_context::<RwSignal<SyncStatus>>();
+    let (choices, set_choices) = create_signal(MergeChoices {
+        details: MergeSide::Local,
+        selected_optional_features: MergeSide::Local,
+        attack_list: MergeSide::Local,
+    });
+
+    // Save a version of the character over the server's
+    let overwrite = create_action(
+        move |(remote, resolved): &(RemoteCharacter, CharacterDocument)| {
+            let (remote, resolved) = (remote.clone(), resolved.clone());
+            let sync_settings = settings.with_untracked(|s| s.sync.clone());
+            async move {
+                document.set(resolved.clone());
+                let result = overwrite_remote(
+                    &sync_settings,
+                    &backend(),
+                    &remote,
+                    &resolved,
+                )
+                .await;
+                sync_status.set(match result {
+                    Ok(SyncOutcome::Conflict(remote)) => {
+                        SyncStatus::Conflict(Box::new(remote))
+                    }
+                    Ok(_) => SyncStatus::Synced,
+                    Err(e) => SyncStatus::Failed(e.to_string()),
+                });
+            }
+        },
+    );
+
+    div().child(move || {
+        let remote = match sync_status() {
+            SyncStatus::Conflict(remote)
+                if roster.with(|r| r.current == remote.id) =>
+            {
+                *remote
+            }
+            _ => return div(),
+        };
+        let keep_local = {
+            let remote = remote.clone();
+            move |_| {
+                overwrite.dispatch((remote.clone(), document.get_untracked()))
+            }
+        };
+        let keep_remote = {
+            let remote = remote.clone();
+            move |_| {
+                accept_remote(&backend(), &remote);
+                document.set(remote.document.clone());
+                sync_status.set(SyncStatus::Synced);
+            }
+        };
+        let merge = {
+            let remote = remote.clone();
+            move |_| {
+                let merged = document.with_untracked(|local| {
+                    merge_documents(local, &remote.document, choices())
+                });
+                overwrite.dispatch((remote.clone(), merged))
+            }
+        };
+        div()
+            .classes("alert alert-warning mt-3 mb-0")
+            .child(
+                h2().classes("fs-6")
+                    .child("This character was changed on another device too"),
+            )
+            .child(p().child(format!(
+                "The server's version, {}, was saved {}. Keep this \
+                device's version, the server's, or merge the two.",
+                remote.document.details.name,
+                format_time(remote.updated_at)
+            )))
+            .child(
+                div()
+                    .classes("d-flex gap-2 mb-3")
+                    .child(
+                        button()
+                            .attr("type", "button")
+                            .classes("btn btn-outline-primary")
+                            .child("Keep this device's")
+                            .on(ev::click, keep_local),
+                    )
+                    .child(
+                        button()
+                            .attr("type", "button")
+                            .classes("btn btn-outline-primary")
+                            .child("Use the server's")
+                            .on(ev::click, keep_remote),
+                    ),
+            )
+            .child(
+                GridRow()
+                    .classes("g-2 align-items-end")
+                    .child(MergeChoiceSelect(
+                        "Details and ability scores",
+                        move || choices().details,
+                        move |side| set_choices.update(|c| c.details = side),
+                    ))
+                    .child(MergeChoiceSelect(
+                        "Feature choices",
+                        move || choices().selected_optional_features,
+                        move |side| {
+                            set_choices
+                                .update(|c| c.selected_optional_features = side)
+                        },
+                    ))
+                    .child(MergeChoiceSelect(
+                        "Attacks",
+                        move || choices().attack_list,
+                        move |side| {
+                            set_choices.update(|c| c.attack_list = side)
+                        },
+                    ))
+                    .child(
+                        GridCol().child(
+                            button()
+                                .attr("type", "button")
+                                .classes("btn btn-primary w-100")
+                                .child("Merge")
+                                .on(ev::click, merge),
+                        ),
+                    ),
+            )
+    })
+}
+
+/// Which version to take part of the character from, when merging.
+fn MergeChoiceSelect(
+    name: &'static str,
+    side: impl Fn() -> MergeSide + 'static,
+    set_side: impl Fn(MergeSide) + 'static,
+) -> HtmlDiv {
+    let side = Signal::derive(side);
+    GridCol()
+        .child(label().classes("form-label small").child(name))
+        .child(
+            CustomSelect()
+                .child(
+                    option()
+                        .attr("value", "local")
+                        .prop("selected", move || side() == MergeSide::Local)
+                        .child("This device"),
+                )
+                .child(
+                    option()
+                        .attr("value", "remote")
+                        .prop("selected", move || side() == MergeSide::Remote)
+                        .child("Server"),
+                )
+                .on(ev::change, move |e| {
+                    set_side(if event_target_value(&e) == "remote" {
+                        MergeSide::Remote
+                    } else {
+                        MergeSide::Local
+                    })
+                }),
+        )
+}
+
+/// Local date and time, from seconds since the epoch.
+fn format_time(seconds: u64) -> String {
+    let date = js_sys::Date::new(&wasm_bindgen::JsValue::from_f64(
+        seconds as f64 * 1000.0,
+    ));
+    String::from(
+        date.to_locale_string("default", &wasm_bindgen::JsValue::UNDEFINED),
+    )
+}
@@ -0,0 +1,417 @@
+//! Saving and loading the app's data. Each function takes the
+//! `StorageBackend` to use, which in the app is `storage::backend()`.
+
+use leptos::logging::log;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::character_model::{CharacterDetails, CharacterDocument};
+use crate::dice::RollOutcome;
+use crate::migrations::*;
+use crate::roster::Roster;
+use crate::settings::{DataSettings, Settings};
+use crate::storage::StorageBackend;
+use crate::sync::SyncState;
+
+const DOCUMENT_STORAGE_KEY: &str = "char_sheet_document";
+// Before schema version 2, characters were stored in three parts
+const CHAR_STORAGE_KEY: &str = "char_sheet_character";
+const OPTIONS_STORAGE_KEY: &str = "char_sheet_selected_optional_features";
+const ATTACKS_STORAGE_KEY: &str = "char_sheet_attack_actions";
+const ROLL_HISTORY_STORAGE_KEY: &str = "char_sheet_roll_history";
+const SETTINGS_STORAGE_KEY: &str = "char_sheet_settings";
+const ROSTER_STORAGE_KEY: &str = "char_sheet_roster";
+const SYNC_STORAGE_KEY: &str = "char_sheet_sync";
+
+/// Keys that held a character's data before it was stored as a
+/// single document.
+const SPLIT_STORAGE_KEYS: [&str; 3] =
+    [CHAR_STORAGE_KEY, OPTIONS_STORAGE_KEY, ATTACKS_STORAGE_KEY];
+
+/// Keys that hold a single character's data. Each character in the
+/// roster stores these under its own id; see `character_key`.
+const CHARACTER_STORAGE_KEYS: [&str; 5] = [
+    DOCUMENT_STORAGE_KEY,
+    CHAR_STORAGE_KEY,
+    OPTIONS_STORAGE_KEY,
+    ATTACKS_STORAGE_KEY,
+    SYNC_STORAGE_KEY,
+];
+
+/// Storage key for a piece of a character's data.
+fn character_key(key: &str, id: &str) -> String {
+    format!("{}:{}", key, id)
+}
+
+/// Store a value as JSON, logging if it can't be stored.
+fn write_json<T: Serialize>(storage: &dyn StorageBackend, key: &str, data: &T) {
+    if let Ok(json) = serde_json::to_string(data) {
+        if let Err(e) = storage.set(key, &json) {
+            log!("Could not save {}: {}", key, e);
+        }
+    }
+}
+
+fn load_json<T: DeserializeOwned>(
+    storage: &dyn StorageBackend,
+    key: &str,
+) -> Option<T> {
+    serde_json::from_str::<T>(&storage.get(key)?).ok()
+}
+
+pub fn load_roster(storage: &dyn StorageBackend) -> Roster {
+    match load_json::<Roster>(storage, ROSTER_STORAGE_KEY) {
+        Some(mut roster) => {
+            // Make sure there's always a character open
+            if roster.entry(&roster.current).is_none() {
+                roster.current = match roster.active().first() {
+                    Some(entry) => entry.id.to_string(),
+                    None => roster.add(""),
+                };
+            }
+            roster
+        }
+        None => migrate_single_character(storage),
+    }
+}
+
+/// Before the roster, only one character could be stored, under the
+/// bare storage keys. Move that character into the roster.
+fn migrate_single_character(storage: &dyn StorageBackend) -> Roster {
+    let mut roster = Roster::default();
+    let name = load_json::<CharacterDetails>(storage, CHAR_STORAGE_KEY)
+        .map(|c| c.name)
+        .unwrap_or_default();
+    let id = roster.add(&name);
+    for key in SPLIT_STORAGE_KEYS {
+        if let Some(data) = storage.get(key) {
+            let _ = storage.set(&character_key(key, &id), &data);
+            storage.remove(key);
+        }
+    }
+    roster.current = id;
+    roster
+}
+
+pub fn write_roster(storage: &dyn StorageBackend, roster: &Roster) {
+    write_json(storage, ROSTER_STORAGE_KEY, roster)
+}
+
+/// Copy all of a character's data to a new id. The copy is a new
+/// character as far as syncing goes, so its sync state isn't copied.
+pub fn copy_character_storage(
+    storage: &dyn StorageBackend,
+    from: &str,
+    to: &str,
+) {
+    for key in CHARACTER_STORAGE_KEYS
+        .iter()
+        .filter(|k| **k != SYNC_STORAGE_KEY)
+    {
+        if let Some(data) = storage.get(&character_key(key, from)) {
+            let _ = storage.set(&character_key(key, to), &data);
+        }
+    }
+}
+
+/// Remove all of a character's data, including backups.
+pub fn remove_character_storage(storage: &dyn StorageBackend, id: &str) {
+    for key in CHARACTER_STORAGE_KEYS {
+        let key = character_key(key, id);
+        storage.remove(&key);
+        storage.remove(&format!("{}:backup", key));
+        for version in 0..SCHEMA_VERSION {
+            storage.remove(&format!("{}:backup:v{}", key, version));
+        }
+    }
+}
+
+/// Load a piece of a character's data, migrating it if it was saved
+/// with an older schema version. The raw data is backed up before
+/// migrating, and if it can't be loaded, so that it's never lost.
+fn load_versioned<T: DeserializeOwned>(
+    storage: &dyn StorageBackend,
+    key: &str,
+    migrations: &[Migration],
+) -> Option<T> {
+    let data = storage.get(key)?;
+    match from_versioned_json::<T>(&data, migrations) {
+        Ok(migrated) => {
+            if migrated.from_version < SCHEMA_VERSION {
+                let backup =
+                    format!("{}:backup:v{}", key, migrated.from_version);
+                let _ = storage.set(&backup, &data);
+            }
+            Some(migrated.data)
+        }
+        Err(e) => {
+            log!("Could not load {}: {}", key, e);
+            let _ = storage.set(&format!("{}:backup", key), &data);
+            None
+        }
+    }
+}
+
+/// Store a piece of a character's data, tagged with the
+/// current schema version.
+fn write_versioned<T: Serialize>(
+    storage: &dyn StorageBackend,
+    key: &str,
+    data: &T,
+) {
+    if let Ok(json) = to_versioned_json(data) {
+        if let Err(e) = storage.set(key, &json) {
+            log!("Could not save {}: {}", key, e);
+        }
+    }
+}
+
+/// Load a character. Characters saved before schema version 2 are
+/// stored in parts, which are put together into a document here.
+pub fn load_document(
+    storage: &dyn StorageBackend,
+    id: &str,
+) -> CharacterDocument {
+    let key = character_key(DOCUMENT_STORAGE_KEY, id);
+    let document = if storage.get(&key).is_some() {
+        load_versioned(storage, &key, &DOCUMENT_MIGRATIONS)
+    } else {
+        load_split_document(storage, id)
+    };
+    document.unwrap_or_default()
+}
+
+fn load_split_document(
+    storage: &dyn StorageBackend,
+    id: &str,
+) -> Option<CharacterDocument> {
+    let load_part = |key: &str| {
+        load_json::<serde_json::Value>(storage, &character_key(key, id))
+    };
+    let (version, document) = assemble_split_document(
+        load_part(CHAR_STORAGE_KEY)?,
+        load_part(OPTIONS_STORAGE_KEY),
+        load_part(ATTACKS_STORAGE_KEY),
+    );
+    // Back up the parts together, then migrate them as any other
+    // document. The parts are left in place until the document
+    // has been written.
+    let backup = format!(
+        "{}:backup:v{}",
+        character_key(DOCUMENT_STORAGE_KEY, id),
+        version
+    );
+    let _ = storage.set(&backup, &document.to_string());
+    let migrated = migrate(document, version, &DOCUMENT_MIGRATIONS)
+        .and_then(|d| serde_json::from_value(d).map_err(LoadError::Invalid));
+    match migrated {
+        Ok(document) => Some(document),
+        Err(e) => {
+            log!("Could not load character {}: {}", id, e);
+            None
+        }
+    }
+}
+
+/// Store a character as a single document, replacing any parts
+/// it was stored in before.
+pub fn write_document(
+    storage: &dyn StorageBackend,
+    id: &str,
+    document: &CharacterDocument,
+) {
+    write_versioned(
+        storage,
+        &character_key(DOCUMENT_STORAGE_KEY, id),
+        document,
+    );
+    for key in SPLIT_STORAGE_KEYS {
+        storage.remove(&character_key(key, id));
+    }
+}
+
+/// What was last synced for a character; see `sync`.
+pub fn load_sync_state(
+    storage: &dyn StorageBackend,
+    id: &str,
+) -> Option<SyncState> {
+    load_json(storage, &character_key(SYNC_STORAGE_KEY, id))
+}
+
+pub fn write_sync_state(
+    storage: &dyn StorageBackend,
+    id: &str,
+    state: &SyncState,
+) {
+    write_json(storage, &character_key(SYNC_STORAGE_KEY, id), state)
+}
+
+pub fn write_roll_history(
+    storage: &dyn StorageBackend,
+    history: &[RollOutcome],
+) {
+    write_json(storage, ROLL_HISTORY_STORAGE_KEY, &history)
+}
+
+pub fn load_roll_history(storage: &dyn StorageBackend) -> Vec<RollOutcome> {
+    load_json(storage, ROLL_HISTORY_STORAGE_KEY).unwrap_or_default()
+}
+
+pub fn write_settings(storage: &dyn StorageBackend, settings: &Settings) {
+    write_json(storage, SETTINGS_STORAGE_KEY, settings)
+}
+
+/// The saved settings. If there aren't any, a new player starts on
+/// the bundled SRD, but someone who already has characters saved only
+/// had Open5e before, so keeps it rather than losing anything outside
+/// the SRD.
+pub fn load_settings(storage: &dyn StorageBackend) -> Settings {
+    load_json(storage, SETTINGS_STORAGE_KEY).unwrap_or_else(|| {
+        let mut settings = Settings::default();
+        if has_characters(storage) {
+            settings.data = DataSettings::open5e();
+        }
+        settings
+    })
+}
+
+/// Whether any characters are saved, in the roster or from before it.
+fn has_characters(storage: &dyn StorageBackend) -> bool {
+    storage.get(ROSTER_STORAGE_KEY).is_some()
+        || SPLIT_STORAGE_KEYS
+            .iter()
+            .any(|key| storage.get(key).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    use crate::storage::{backend, set_backend, swap_in_memory, MemoryStorage};
+    use crate::test_data::grog;
+
+    #[test]
+    fn document_round_trips() {
+        let storage = MemoryStorage::default();
+        write_document(&storage, "grog", &grog());
+        assert!(load_document(&storage, "grog") == grog());
+        // Nothing stored yet for this one
+        assert!(
+            load_document(&storage, "pike") == CharacterDocument::default()
+        );
+    }
+
+    #[test]
+    fn moves_single_character_into_roster() {
+        let storage = MemoryStorage::default();
+        storage
+            .set(
+                CHAR_STORAGE_KEY,
+                include_str!("../tests/fixtures/v0/character.json"),
+            )
+            .unwrap();
+        storage
+            .set(
+                ATTACKS_STORAGE_KEY,
+                include_str!("../tests/fixtures/v0/attacks.json"),
+            )
+            .unwrap();
+
+        let roster = load_roster(&storage);
+        assert_eq!(roster.characters.len(), 1);
+        assert_eq!(roster.characters[0].name, "Grog");
+        assert!(storage.get(CHAR_STORAGE_KEY).is_none());
+
+        // The parts are put together into a document, and
+        // replaced by it once it has been written
+        let document = load_document(&storage, &roster.current);
+        assert_eq!(document.details.name, "Grog");
+        assert_eq!(document.attack_list[0].name, "Greataxe");
+        write_document(&storage, &roster.current, &document);
+        assert!(storage
+            .get(&character_key(CHAR_STORAGE_KEY, &roster.current))
+            .is_none());
+        assert!(load_document(&storage, &roster.current) == document);
+    }
+
+    #[test]
+    fn roster_always_has_a_character_open() {
+        let storage = MemoryStorage::default();
+        let roster = load_roster(&storage);
+        assert!(roster.entry(&roster.current).is_some());
+
+        let mut stale = roster.clone();
+        stale.current = "gone".to_string();
+        write_roster(&storage, &stale);
+        let roster = load_roster(&storage);
+        assert!(roster.entry(&roster.current).is_some());
+    }
+
+    #[test]
+    fn copies_and_removes_characters() {
+        let storage = MemoryStorage::default();
+        write_document(&storage, "grog", &grog());
+        copy_character_storage(&storage, "grog", "copy");
+        assert!(load_document(&storage, "copy") == grog());
+
+        remove_character_storage(&storage, "grog");
+        assert!(
+            load_document(&storage, "grog") == CharacterDocument::default()
+        );
+        assert!(load_document(&storage, "copy") == grog());
+    }
+
+    #[test]
+    fn backs_up_data_that_cannot_be_loaded() {
+        let storage = MemoryStorage::default();
+        let key = character_key(DOCUMENT_STORAGE_KEY, "grog");
+        storage.set(&key, "not json").unwrap();
+        assert!(
+            load_document(&storage, "grog") == CharacterDocument::default()
+        );
+        assert_eq!(
+            storage.get(&format!("{}:backup", key)).as_deref(),
+            Some("not json")
+        );
+    }
+
+    #[test]
+    fn keeps_existing_players_on_open5e() {
+        // A new player starts on the bundled SRD
+        let storage = MemoryStorage::default();
+        assert_eq!(load_settings(&storage).data.open5e_base_url(), None);
+
+        // Characters saved before there were any settings
+        for key in [ROSTER_STORAGE_KEY, CHAR_STORAGE_KEY] {
+            let storage = MemoryStorage::default();
+            storage.set(key, "{}").unwrap();
+            assert_eq!(load_settings(&storage).data, DataSettings::open5e());
+        }
+
+        // Saved settings are kept as they are
+        let storage = MemoryStorage::default();
+        write_roster(&storage, &Roster::default());
+        write_settings(&storage, &Settings::default());
+        assert_eq!(load_settings(&storage), Settings::default());
+    }
+
+    #[test]
+    fn shared_view_keeps_home_settings() {
+        let home = Rc::new(MemoryStorage::default());
+        let mut settings = Settings {
+            data: DataSettings::open5e(),
+            ..Default::default()
+        };
+        write_settings(&home, &settings);
+        set_backend(home);
+
+        let home = swap_in_memory();
+        assert!(load_settings(&backend()).data.open5e_base_url().is_none());
+        assert_eq!(load_settings(&home), settings);
+        // Changes while viewing are saved to the player's own storage
+        settings.dice_3d = false;
+        write_settings(&home, &settings);
+        assert_eq!(load_settings(&home), settings);
+        assert!(backend().keys().is_empty());
+    }
+}
@@ -0,0 +1,433 @@
+//! The character laid out as a classic two page paper sheet: the
+//! numbers, skills and attacks on the first page, and features,
+//! spells and inventory on the second.
+//!
+//! Everything the sheet shows is worked out before it gets here, so
+//! rendering is just turning a `PrintSheet` into HTML.
+
+use lazy_regex::regex_captures;
+
+use crate::api::api_extensions::{Feature, FeatureType};
+use crate::character_model::{Ability, Skill};
+use crate::markdown::parse_markdown_table;
+
+/// Everything that goes on the printed sheet.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct PrintSheet {
+    pub name: String,
+    pub class: String,
+    pub level: i32,
+    /// Subspecies name if there is one, i.e. "Hill Dwarf".
+    pub species: String,
+    pub background: String,
+    pub xp: i32,
+    pub proficiency_bonus: i32,
+    pub abilities: Vec<PrintAbility>,
+    pub skills: Vec<PrintSkill>,
+    pub passive_perception: i32,
+    pub initiative: i32,
+    pub speed: i32,
+    pub max_hp: i32,
+    /// i.e. "3d10" for a 3rd level fighter.
+    pub hit_dice: String,
+    pub attacks: Vec<PrintAttack>,
+    /// Weapon, armor, tool and language proficiencies.
+    pub proficiencies: Vec<String>,
+    /// i.e. "darkvision 60 ft.", not counting passive Perception.
+    pub senses: Vec<String>,
+    pub languages: Vec<String>,
+    pub features: Vec<PrintFeature>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct PrintAbility {
+    pub ability: Ability,
+    pub score: i32,
+    pub modifier: i32,
+    pub save: i32,
+    pub save_proficient: bool,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct PrintSkill {
+    pub skill: Skill,
+    pub bonus: i32,
+    pub proficient: bool,
+}
+
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct PrintAttack {
+    pub name: String,
+    pub to_hit: i32,
+    /// Dice, modifier and type, i.e. "1d8+3 slashing".
+    pub damage: String,
+    /// Range and properties.
+    pub notes: String,
+    /// The attack written out in full, as in a stat block.
+    pub description: String,
+}
+
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct PrintFeature {
+    /// What the feature comes from, i.e. "Fighter" or "Hill Dwarf".
+    pub source: String,
+    pub name: String,
+    /// Markdown, as it comes from Open5e.
+    pub desc: String,
+}
+
+/// Senses from the character's features, i.e. "darkvision 60 ft.",
+/// from a species trait that says "You can see ... within 60 feet".
+pub fn senses(features: &[Feature]) -> Vec<String> {
+    features
+        .iter()
+        .filter(|f| f.name.to_lowercase().contains("darkvision"))
+        .filter_map(|f| {
+            let (_, feet) = regex_captures!(r"(\d+) feet", &f.desc)?;
+            Some(format!("darkvision {} ft.", feet))
+        })
+        .take(1)
+        .collect()
+}
+
+/// Languages the character speaks. Species list theirs in a sentence,
+/// i.e. "You can speak, read, and write Common and Dwarvish.", and
+/// backgrounds add them as features. Languages still to be picked,
+/// i.e. "one extra language of your choice", aren't languages yet,
+/// so they're left out.
+pub fn languages(species_languages: &str, features: &[Feature]) -> Vec<String> {
+    let from_species =
+        regex_captures!(r"speak, read, and write ([^.]+)", species_languages)
+            .map(|(_, list)| {
+                list.replace(" and ", ", ")
+                    .split(',')
+                    .map(|l| l.trim().to_string())
+                    .collect::<Vec<String>>()
+            })
+            .unwrap_or_default();
+    let from_features = features.iter().filter_map(|f| match &f.feature_type {
+        FeatureType::OtherProficency(prof) if f.name == "Language" => {
+            prof.strip_prefix("Language:").map(|l| l.trim().to_string())
+        }
+        _ => None,
+    });
+    from_species
+        .into_iter()
+        .chain(from_features)
+        .filter(|l| !l.is_empty() && !l.contains("choice"))
+        .collect()
+}
+
+/// Spells and inventory aren't tracked yet, so the sheet leaves this
+/// many lines for each to be written in by hand.
+const BLANK_LINES: usize = 12;
+
+/// The sheet as HTML, one `.print-page` element per page.
+pub fn render_print_sheet(sheet: &PrintSheet) -> String {
+    let mut html = String::new();
+    html += "<div class=\"print-page\">";
+    html += &header(sheet);
+    html += "<div class=\"row g-3\">";
+    html += &format!(
+        "<div class=\"col-4\">{}{}</div>",
+        abilities(sheet),
+        skills(sheet)
+    );
+    html += &format!(
+        "<div class=\"col-8\">{}{}{}</div>",
+        combat(sheet),
+        attacks(sheet),
+        proficiencies(sheet)
+    );
+    html += "</div></div>";
+
+    html += "<div class=\"print-page\">";
+    html += &format!(
+        "<h2 class=\"h4\">{}</h2>",
+        escape(&format!("{}, continued", or_blank(&sheet.name)))
+    );
+    html += &features(sheet);
+    html += "<div class=\"row g-3\">";
+    html += &format!(
+        "<div class=\"col-6\">{}</div>",
+        lines_to_fill("Spells", "The sheet doesn't track spells yet.")
+    );
+    html += &format!(
+        "<div class=\"col-6\">{}</div>",
+        lines_to_fill("Inventory", "The sheet doesn't track items yet.")
+    );
+    html += "</div></div>";
+    html
+}
+
+fn header(sheet: &PrintSheet) -> String {
+    let field = |label: &str, value: &str| {
+        format!(
+            "<div class=\"col\"><div class=\"print-field\">{}</div>\
+            <small class=\"text-muted\">{}</small></div>",
+            escape(value),
+            label
+        )
+    };
+    format!(
+        "<div class=\"row mb-3 align-items-end\">\
+        <div class=\"col-4\"><h1 class=\"h2 mb-0\">{}</h1>\
+        <small class=\"text-muted\">Character Name</small></div>\
+        <div class=\"col-8\"><div class=\"row\">{}{}{}{}</div></div></div>",
+        escape(or_blank(&sheet.name)),
+        field(
+            "Class & Level",
+            format!("{} {}", sheet.class, sheet.level).trim()
+        ),
+        field("Species", &sheet.species),
+        field("Background", &sheet.background),
+        field("Experience Points", &sheet.xp.to_string()),
+    )
+}
+
+fn abilities(sheet: &PrintSheet) -> String {
+    let mut html = format!(
+        "<div class=\"print-box mb-3\"><div class=\"d-flex \
+        justify-content-between\"><strong>Proficiency Bonus</strong>\
+        <span>{:+}</span></div></div>",
+        sheet.proficiency_bonus
+    );
+    html += "<table class=\"table table-sm print-table mb-3\">\
+        <thead><tr><th>Ability</th><th>Score</th><th>Mod</th>\
+        <th>Save</th></tr></thead><tbody>";
+    for ability in &sheet.abilities {
+        html += &format!(
+            "<tr><td>{}</td><td>{}</td><td>{:+}</td><td>{} {:+}</td></tr>",
+            ability.ability.to_string(),
+            ability.score,
+            ability.modifier,
+            proficiency_mark(ability.save_proficient),
+            ability.save
+        );
+    }
+    html += "</tbody></table>";
+    html
+}
+
+fn skills(sheet: &PrintSheet) -> String {
+    let mut html = String::from(
+        "<table class=\"table table-sm print-table mb-3\">\
+        <thead><tr><th colspan=\"2\">Skills</th></tr></thead><tbody>",
+    );
+    for skill in &sheet.skills {
+        html += &format!(
+            "<tr><td>{} {} <small class=\"text-muted\">({})</small></td>\
+            <td>{:+}</td></tr>",
+            proficiency_mark(skill.proficient),
+            skill.skill,
+            &skill.skill.ability().to_string()[..3],
+            skill.bonus
+        );
+    }
+    html += "</tbody></table>";
+    html += &format!(
+        "<div class=\"print-box\"><div class=\"d-flex \
+        justify-content-between\"><strong>Passive Perception</strong>\
+        <span>{}</span></div></div>",
+        sheet.passive_perception
+    );
+    html
+}
+
+fn combat(sheet: &PrintSheet) -> String {
+    let stat = |label: &str, value: String| {
+        format!(
+            "<div class=\"col\"><div class=\"print-box text-center\">\
+            <div class=\"fs-4\">{}</div><small>{}</small></div></div>",
+            value, label
+        )
+    };
+    format!(
+        "<div class=\"row g-2 mb-3\">{}{}{}{}{}{}</div>",
+        // Armor isn't tracked, so AC is left to fill in
+        stat("Armor Class", "&nbsp;".to_string()),
+        stat("Initiative", format!("{:+}", sheet.initiative)),
+        stat("Speed", format!("{} ft.", sheet.speed)),
+        stat("Max HP", sheet.max_hp.to_string()),
+        stat("Current HP", "&nbsp;".to_string()),
+        stat("Hit Dice", escape(or_blank(&sheet.hit_dice))),
+    )
+}
+
+fn attacks(sheet: &PrintSheet) -> String {
+    let mut html = String::from(
+        "<table class=\"table table-sm print-table mb-3\">\
+        <thead><tr><th>Attack</th><th>To Hit</th><th>Damage</th>\
+        <th>Notes</th></tr></thead><tbody>",
+    );
+    for attack in &sheet.attacks {
+        html += &format!(
+            "<tr><td>{}</td><td>{:+}</td><td>{}</td><td>{}</td></tr>",
+            escape(&attack.name),
+            attack.to_hit,
+            escape(&attack.damage),
+            escape(&attack.notes)
+        );
+    }
+    // Room for a few more, written in by hand
+    for _ in sheet.attacks.len()..5 {
+        html += "<tr><td>&nbsp;</td><td></td><td></td><td></td></tr>";
+    }
+    html += "</tbody></table>";
+    html
+}
+
+/// Other proficiencies, languages and senses, i.e. darkvision.
+fn proficiencies(sheet: &PrintSheet) -> String {
+    let line = |label: &str, list: &[String]| {
+        format!(
+            "<p class=\"mb-1\"><em>{}:</em> {}</p>",
+            label,
+            escape(&list.join(", "))
+        )
+    };
+    format!(
+        "<div class=\"print-box\"><strong>Other Proficiencies, Languages \
+        &amp; Senses</strong>{}{}{}</div>",
+        line("Proficiencies", &sheet.proficiencies),
+        line("Languages", &sheet.languages),
+        line("Senses", &sheet.senses),
+    )
+}
+
+/// Features under a heading for each thing they come from, in the
+/// order those first appear.
+fn features(sheet: &PrintSheet) -> String {
+    let mut sources: Vec<&str> = vec![];
+    for feature in &sheet.features {
+        if !sources.contains(&feature.source.as_str()) {
+            sources.push(&feature.source);
+        }
+    }
+    let mut html = String::from("<div class=\"print-features mb-3\">");
+    html += "<h3 class=\"h5\">Features &amp; Traits</h3>";
+    for source in sources {
+        html += &format!("<h4 class=\"h6 mt-3\">{}</h4>", escape(source));
+        for feature in sheet.features.iter().filter(|f| f.source == source) {
+            html += &format!(
+                "<div class=\"print-feature\"><strong>{}</strong>{}</div>",
+                escape(&feature.name),
+                parse_markdown_table(&feature.desc)
+            );
+        }
+    }
+    html += "</div>";
+    html
+}
+
+fn lines_to_fill(title: &str, note: &str) -> String {
+    format!(
+        "<div class=\"print-box\"><strong>{}</strong> \
+        <small class=\"text-muted\">{}</small>{}</div>",
+        title,
+        note,
+        "<div class=\"print-line\"></div>".repeat(BLANK_LINES)
+    )
+}
+
+/// A filled circle for proficient, an empty one otherwise, as on the
+/// official sheet.
+fn proficiency_mark(proficient: bool) -> &'static str {
+    if proficient {
+        "&#9679;"
+    } else {
+        "&#9675;"
+    }
+}
+
+fn or_blank(text: &str) -> &str {
+    if text.is_empty() {
+        "Unnamed character"
+    } else {
+        text
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_data::grog_sheet;
+
+    #[test]
+    fn prints_two_pages() {
+        let html = render_print_sheet(&PrintSheet {
+            name: "Grog <Strongjaw>".to_string(),
+            ..grog_sheet()
+        });
+        assert_eq!(html.matches("class=\"print-page\"").count(), 2);
+        assert!(html.contains("Barbarian 3"));
+        assert!(html.contains("<td>Strength</td><td>18</td><td>+4</td>"));
+        assert!(html.contains("&#9679; +6"));
+        assert!(html.contains("1d12+4 slashing"));
+        assert!(html.contains("3d12"));
+        assert!(html.contains("<em>Languages:</em> Common, Giant"));
+        assert!(html.contains("<em>Senses:</em> darkvision 60 ft."));
+        // Names can't add their own markup
+        assert!(html.contains("Grog &lt;Strongjaw&gt;"));
+        assert!(!html.contains("<Strongjaw>"));
+    }
+
+    #[test]
+    fn finds_darkvision() {
+        let darkvision = Feature {
+            name: "Darkvision".to_string(),
+            desc: "You can see in dim light within 60 feet of you.".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(senses(&[darkvision]), vec!["darkvision 60 ft."]);
+        assert!(senses(&[]).is_empty());
+    }
+
+    #[test]
+    fn lists_languages_but_not_choices() {
+        let language = |prof: &str| Feature {
+            name: "Language".to_string(),
+            feature_type: FeatureType::OtherProficency(prof.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            languages(
+                "***Languages.*** You can speak, read, and write Common, \
+                Elvish, and one extra language of your choice.",
+                &[
+                    language("Language: Sylvan"),
+                    language("Two languages of your choice")
+                ]
+            ),
+            vec!["Common", "Elvish", "Sylvan"]
+        );
+        assert_eq!(
+            languages(
+                "You can speak, read, and write Common and Dwarvish. \
+                Dwarvish is full of hard consonants.",
+                &[]
+            ),
+            vec!["Common", "Dwarvish"]
+        );
+    }
+
+    #[test]
+    fn groups_features_by_source_with_markdown() {
+        let html = render_print_sheet(&grog_sheet());
+        // Descriptions are rendered, tables included
+        assert!(html.contains("<table>"));
+        assert!(html.contains("<strong>1d12</strong>"));
+        // Both barbarian features come before the goliath one
+        let position = |text: &str| html.find(text).unwrap();
+        assert_eq!(html.matches(">Barbarian</h4>").count(), 1);
+        assert!(position("Reckless Attack") < position(">Goliath</h4>"));
+        assert!(position(">Goliath</h4>") < position("Stone's Endurance"));
+    }
+}
@@ -0,0 +1,65 @@
+//! Proficiencies a character gets from their features. The sheet, the
+//! printed sheet and exports all work these out the same way.
+
+use crate::api::api_extensions::{Feature, FeatureType};
+use crate::character_model::{Ability, Skill};
+
+/// Abilities the character is proficient in saving throws for.
+pub fn save_proficiencies(features: &[Feature]) -> Vec<Ability> {
+    features
+        .iter()
+        .filter_map(|f| match &f.feature_type {
+            FeatureType::SavingThrow(ability) => Some(ability.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Every skill proficiency, as the features name them. This includes
+/// ones that aren't standard skills; see `skill_proficiencies`.
+pub fn skill_proficiency_names(features: &[Feature]) -> Vec<String> {
+    features
+        .iter()
+        .filter_map(|f| match &f.feature_type {
+            FeatureType::SkillProficency(skill) => Some(skill.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Standard skills the character is proficient in.
+pub fn skill_proficiencies(features: &[Feature]) -> Vec<Skill> {
+    skill_proficiency_names(features)
+        .iter()
+        .filter_map(|s| Skill::from_string(s))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_save_and_skill_proficiencies() {
+        let features = [
+            Feature {
+                feature_type: FeatureType::SavingThrow(Ability::Strength),
+                ..Default::default()
+            },
+            Feature::new_skill("Athletics", "class:fighter"),
+            Feature::new_skill("Basket Weaving", "background:weaver"),
+            Feature {
+                feature_type: FeatureType::OtherProficency(
+                    "Smith's tools".to_string(),
+                ),
+                ..Default::default()
+            },
+        ];
+        assert_eq!(save_proficiencies(&features), vec![Ability::Strength]);
+        assert_eq!(
+            skill_proficiency_names(&features),
+            vec!["Athletics", "Basket Weaving"]
+        );
+        assert_eq!(skill_proficiencies(&features), vec![Skill::Athletics]);
+    }
+}
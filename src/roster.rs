@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A character saved in this browser. The character's data is
+/// stored under keys that include its id.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct RosterEntry {
+    pub id: String,
+    pub name: String,
+    /// Set while the character is in the recycle bin.
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+/// Every character saved in this browser, and which one is open.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct Roster {
+    pub current: String,
+    pub characters: Vec<RosterEntry>,
+    /// Ids of characters deleted for good, so that syncing doesn't
+    /// download them again from the server.
+    #[serde(default)]
+    pub purged: Vec<String>,
+}
+
+impl Roster {
+    pub fn entry(&self, id: &str) -> Option<&RosterEntry> {
+        self.characters.iter().find(|c| c.id == id)
+    }
+    /// Characters that aren't in the recycle bin.
+    pub fn active(&self) -> Vec<RosterEntry> {
+        self.characters
+            .iter()
+            .filter(|c| !c.deleted)
+            .cloned()
+            .collect()
+    }
+    pub fn recycle_bin(&self) -> Vec<RosterEntry> {
+        self.characters
+            .iter()
+            .filter(|c| c.deleted)
+            .cloned()
+            .collect()
+    }
+    /// Add a new entry, returning its id.
+    pub fn add(&mut self, name: &str) -> String {
+        let id = Uuid::new_v4().to_string();
+        self.characters.push(RosterEntry {
+            id: id.to_string(),
+            name: name.to_string(),
+            deleted: false,
+        });
+        id
+    }
+    /// Ids of every character this browser has had, i.e. ones in
+    /// the roster, in the recycle bin, or deleted for good.
+    pub fn known_ids(&self) -> Vec<String> {
+        self.characters
+            .iter()
+            .map(|c| c.id.to_string())
+            .chain(self.purged.iter().cloned())
+            .collect()
+    }
+    /// Add an entry for a character that already has an id, i.e.
+    /// one synced from another device. Purged characters stay gone.
+    pub fn add_with_id(&mut self, id: &str, name: &str) {
+        if self.entry(id).is_none() && !self.purged.iter().any(|p| p == id) {
+            self.characters.push(RosterEntry {
+                id: id.to_string(),
+                name: name.to_string(),
+                deleted: false,
+            });
+        }
+    }
+    pub fn rename(&mut self, id: &str, name: &str) {
+        if let Some(entry) = self.characters.iter_mut().find(|c| c.id == id) {
+            entry.name = name.to_string();
+        }
+    }
+    /// Move a character to the recycle bin.
+    pub fn delete(&mut self, id: &str) {
+        if let Some(entry) = self.characters.iter_mut().find(|c| c.id == id) {
+            entry.deleted = true;
+        }
+    }
+    /// Take a character back out of the recycle bin.
+    pub fn restore(&mut self, id: &str) {
+        if let Some(entry) = self.characters.iter_mut().find(|c| c.id == id) {
+            entry.deleted = false;
+        }
+    }
+    /// Remove a character from the roster entirely. Its data
+    /// needs to be removed from storage separately.
+    pub fn purge(&mut self, id: &str) {
+        if self.entry(id).is_some() {
+            self.characters.retain(|c| c.id != id);
+            self.purged.push(id.to_string());
+        }
+    }
+}
+
+/// Name to show for a character that hasn't been named yet.
+pub fn display_name(entry: &RosterEntry) -> String {
+    if entry.name.trim().is_empty() {
+        "Unnamed character".to_string()
+    } else {
+        entry.name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::{load_roster, write_roster};
+    use crate::storage::MemoryStorage;
+
+    fn names(entries: &[RosterEntry]) -> Vec<String> {
+        entries.iter().map(|e| e.name.to_string()).collect()
+    }
+
+    #[test]
+    fn adds_characters_in_order() {
+        let mut roster = Roster::default();
+        let grog = roster.add("Grog");
+        let pike = roster.add("Pike");
+        let vex = roster.add("Vex");
+        assert_eq!(names(&roster.active()), vec!["Grog", "Pike", "Vex"]);
+
+        // Every character gets its own id
+        assert!(grog != pike && pike != vex && grog != vex);
+        assert!(Uuid::parse_str(&grog).is_ok());
+        assert_eq!(roster.entry(&pike).unwrap().name, "Pike");
+
+        // A synced character keeps its id, and isn't added twice
+        roster.add_with_id("scanlan", "Scanlan");
+        roster.add_with_id("scanlan", "Scanlan");
+        assert_eq!(roster.characters.len(), 4);
+        assert_eq!(roster.characters[3].id, "scanlan");
+    }
+
+    #[test]
+    fn renames_characters() {
+        let mut roster = Roster::default();
+        let id = roster.add("");
+        assert_eq!(
+            display_name(roster.entry(&id).unwrap()),
+            "Unnamed character"
+        );
+        roster.rename(&id, "Grog");
+        assert_eq!(display_name(roster.entry(&id).unwrap()), "Grog");
+        // Renaming a character that isn't there does nothing
+        roster.rename("gone", "Pike");
+        assert_eq!(names(&roster.characters), vec!["Grog"]);
+    }
+
+    #[test]
+    fn deletes_restores_and_purges() {
+        let mut roster = Roster::default();
+        let grog = roster.add("Grog");
+        let pike = roster.add("Pike");
+        let vex = roster.add("Vex");
+
+        roster.delete(&pike);
+        assert_eq!(names(&roster.active()), vec!["Grog", "Vex"]);
+        assert_eq!(names(&roster.recycle_bin()), vec!["Pike"]);
+
+        // Restored characters go back to where they were
+        roster.restore(&pike);
+        assert_eq!(names(&roster.active()), vec!["Grog", "Pike", "Vex"]);
+        assert!(roster.recycle_bin().is_empty());
+
+        roster.delete(&grog);
+        roster.purge(&grog);
+        assert!(roster.entry(&grog).is_none());
+        assert_eq!(names(&roster.characters), vec!["Pike", "Vex"]);
+        assert!(roster.entry(&vex).is_some());
+    }
+
+    #[test]
+    fn purged_characters_are_not_synced_back() {
+        let mut roster = Roster::default();
+        roster.add_with_id("grog", "Grog");
+        roster.add_with_id("pike", "Pike");
+        roster.delete("grog");
+        roster.purge("grog");
+        assert_eq!(roster.known_ids(), vec!["pike", "grog"]);
+
+        // The server still has Grog, but he isn't downloaded again
+        roster.add_with_id("grog", "Grog");
+        assert!(roster.entry("grog").is_none());
+        assert_eq!(roster.purged, vec!["grog"]);
+    }
+
+    #[test]
+    fn round_trips_through_storage() {
+        let storage = MemoryStorage::default();
+        let mut roster = Roster::default();
+        roster.add("Grog");
+        let pike = roster.add("Pike");
+        roster.delete(&pike);
+        roster.current = pike.to_string();
+        write_roster(&storage, &roster);
+        assert_eq!(load_roster(&storage), roster);
+    }
+}
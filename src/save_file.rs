@@ -0,0 +1,88 @@
+//! Characters saved to a file, so they can be backed up or moved
+//! to another browser.
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::character_model::CharacterDocument;
+use crate::migrations::{
+    migrate, LoadError, SAVE_FILE_MIGRATIONS, SCHEMA_VERSION,
+};
+
+/// Marks a JSON file as a character exported from this app.
+pub const SAVE_FILE_FORMAT: &str = "goblin_sheet_character";
+
+/// Everything about a character, in one file.
+#[derive(Serialize, Deserialize)]
+pub struct SaveFile {
+    pub format: String,
+    /// Schema version the file was saved with. See `migrations`.
+    #[serde(default)]
+    pub version: u32,
+    #[serde(flatten)]
+    pub document: CharacterDocument,
+}
+
+impl SaveFile {
+    pub fn new(document: CharacterDocument) -> Self {
+        Self {
+            format: SAVE_FILE_FORMAT.to_string(),
+            version: SCHEMA_VERSION,
+            document,
+        }
+    }
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+    pub fn from_json(data: &str) -> Result<SaveFile, ImportError> {
+        let value = serde_json::from_str::<serde_json::Value>(data)
+            .map_err(ImportError::NotJson)?;
+        if value.get("format").and_then(|f| f.as_str())
+            != Some(SAVE_FILE_FORMAT)
+        {
+            return Err(ImportError::WrongFormat);
+        }
+        let version = value
+            .get("version")
+            .and_then(|v| v.as_u64())
+            .unwrap_or_default() as u32;
+        if version == SCHEMA_VERSION {
+            // Parse the text rather than the value when we can, so that
+            // errors come with a line and column.
+            return serde_json::from_str::<SaveFile>(data)
+                .map_err(ImportError::Invalid);
+        }
+        let value = migrate(value, version, &SAVE_FILE_MIGRATIONS)
+            .map_err(ImportError::Migration)?;
+        serde_json::from_value::<SaveFile>(value).map_err(ImportError::Invalid)
+    }
+}
+
+/// Why a file couldn't be imported.
+#[derive(Debug)]
+pub enum ImportError {
+    NotJson(serde_json::Error),
+    WrongFormat,
+    Migration(LoadError),
+    Invalid(serde_json::Error),
+}
+impl Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::NotJson(e) => {
+                write!(f, "The file isn't valid JSON ({}).", e)
+            }
+            ImportError::WrongFormat => write!(
+                f,
+                "The file isn't a character exported from Goblin Sheet."
+            ),
+            ImportError::Migration(e) => {
+                write!(f, "The file couldn't be read: {}.", e)
+            }
+            ImportError::Invalid(e) => {
+                write!(f, "The character in the file is invalid: {}.", e)
+            }
+        }
+    }
+}
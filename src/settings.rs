@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+
+/// Preferences for the app itself, as opposed to the character.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Settings {
+    /// Show rolls in the 3D dice box. When off, or if the dice box
+    /// can't be loaded, dice are rolled without it.
+    #[serde(default = "default_true")]
+    pub dice_3d: bool,
+    #[serde(default)]
+    pub sync: SyncSettings,
+    /// Settings saved before there was a choice of data source were
+    /// using Open5e, so they keep it rather than losing anything
+    /// outside the SRD. Players with no settings saved at all are
+    /// handled by `load_settings`.
+    #[serde(default = "DataSettings::open5e")]
+    pub data: DataSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            dice_3d: true,
+            sync: SyncSettings::default(),
+            data: DataSettings::default(),
+        }
+    }
+}
+
+/// Where to sync characters to; see `sync_server` for the server.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct SyncSettings {
+    /// i.e. "http://192.168.1.20:8787"
+    pub server_url: String,
+    pub token: String,
+    /// Sync the open character automatically as it changes.
+    pub enabled: bool,
+}
+
+impl SyncSettings {
+    pub fn is_configured(&self) -> bool {
+        !self.server_url.trim().is_empty() && !self.token.is_empty()
+    }
+}
+
+/// Open5e's own API, used unless another Open5e instance is given.
+pub const OPEN5E_URL: &str = "https://api.open5e.com";
+
+/// Where the classes, species and so on come from: the SRD bundled
+/// with the app, or an Open5e instance.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct DataSettings {
+    pub use_open5e: bool,
+    /// i.e. "http://localhost:8000" for a self-hosted instance. Empty
+    /// for Open5e's own.
+    pub open5e_url: String,
+}
+
+impl DataSettings {
+    /// Open5e's own API, which was the only source before the bundled
+    /// SRD, so what anyone who used the app before then is kept on.
+    pub fn open5e() -> Self {
+        Self {
+            use_open5e: true,
+            open5e_url: String::new(),
+        }
+    }
+
+    /// The Open5e instance to fetch from, without a trailing slash, or
+    /// `None` to use the bundled SRD.
+    pub fn open5e_base_url(&self) -> Option<String> {
+        if !self.use_open5e {
+            return None;
+        }
+        let url = self.open5e_url.trim().trim_end_matches('/');
+        Some(if url.is_empty() { OPEN5E_URL } else { url }.to_string())
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_data_source() {
+        let mut data = Settings::default().data;
+        assert_eq!(data.open5e_base_url(), None);
+        data.use_open5e = true;
+        assert_eq!(data.open5e_base_url().as_deref(), Some(OPEN5E_URL));
+        data.open5e_url = " http://localhost:8000/ ".to_string();
+        assert_eq!(
+            data.open5e_base_url().as_deref(),
+            Some("http://localhost:8000")
+        );
+        // Settings from before there was a choice stay on Open5e
+        let settings: Settings =
+            serde_json::from_str(r#"{"dice_3d": false}"#).unwrap();
+        assert_eq!(
+            settings.data.open5e_base_url().as_deref(),
+            Some(OPEN5E_URL)
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! Links that hold a whole character, so it can be shown to someone
+//! else without a server. The character is compressed and put in the
+//! URL's fragment (the part after the `#`), which browsers never send
+//! to the server the app is hosted on.
+//!
+//! Only the document goes in the link, which is just slugs and the
+//! player's choices. Everything from Open5e is fetched again by
+//! whoever opens it, which keeps links short. Homebrew entries the
+//! character uses are the exception: there's nowhere else to fetch
+//! them from, so they go in the link too. Ones the character no
+//! longer uses are left out, as is the character's level history,
+//! which is only for the player's own use.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use miniz_oxide::{
+    deflate::compress_to_vec, inflate::decompress_to_vec_with_limit,
+};
+
+use crate::character_model::CharacterDocument;
+use crate::migrations::{
+    from_versioned_json, to_versioned_json, DOCUMENT_MIGRATIONS,
+};
+
+/// Starts the fragment of a share link, i.e. `#share=...`.
+pub const SHARE_FRAGMENT_PREFIX: &str = "#share=";
+
+/// No character comes anywhere near this, so anything bigger than
+/// this once decompressed isn't one.
+const MAX_SHARED_SIZE: usize = 1024 * 1024;
+
+/// Turn a character into the text that goes after `#share=`.
+pub fn encode_shared_character(document: &CharacterDocument) -> String {
+    let json =
+        to_versioned_json(&shared_document(document)).unwrap_or_default();
+    URL_SAFE_NO_PAD.encode(compress_to_vec(json.as_bytes(), 9))
+}
+
+/// The parts of a character that go in a share link.
+fn shared_document(document: &CharacterDocument) -> CharacterDocument {
+    let mut shared = document.clone();
+    let (details, homebrew) = (&shared.details, &mut shared.homebrew);
+    homebrew.classes.retain(|c| c.slug == details.class);
+    homebrew.species.retain(|s| s.slug == details.species);
+    homebrew
+        .backgrounds
+        .retain(|b| b.slug == details.background);
+    shared.level_history.clear();
+    shared
+}
+
+/// Read a character back out of a link's fragment.
+pub fn decode_shared_character(
+    encoded: &str,
+) -> Result<CharacterDocument, String> {
+    let compressed = URL_SAFE_NO_PAD
+        .decode(encoded.trim())
+        .map_err(|_| "the link is incomplete".to_string())?;
+    let json = decompress_to_vec_with_limit(&compressed, MAX_SHARED_SIZE)
+        .map_err(|_| "the link is incomplete".to_string())?;
+    let json = String::from_utf8(json)
+        .map_err(|_| "the link is incomplete".to_string())?;
+    from_versioned_json::<CharacterDocument>(&json, &DOCUMENT_MIGRATIONS)
+        .map(|migrated| migrated.data)
+        .map_err(|e| e.to_string())
+}
+
+/// Link to this page that opens a character read-only.
+pub fn share_link(document: &CharacterDocument) -> String {
+    let location = window().location();
+    format!(
+        "{}{}{}{}",
+        location.origin().unwrap_or_default(),
+        location.pathname().unwrap_or_default(),
+        SHARE_FRAGMENT_PREFIX,
+        encode_shared_character(document)
+    )
+}
+
+/// The character in the page's URL, if it was opened from a share link.
+pub fn shared_character_in_url() -> Option<Result<CharacterDocument, String>> {
+    let hash = window().location().hash().ok()?;
+    hash.strip_prefix(SHARE_FRAGMENT_PREFIX)
+        .map(decode_shared_character)
+}
+
+/// Leave a shared character for the player's own characters, by
+/// reloading the page without the share link in the URL.
+pub fn close_shared_character() {
+    forget_share_link();
+    let _ = window().location().reload();
+}
+
+/// Take the share link out of the address bar, so that reloading
+/// opens the player's own characters.
+pub fn forget_share_link() {
+    let window = window();
+    let path = window.location().pathname().unwrap_or_default();
+    if let Ok(history) = window.history() {
+        let _ = history.replace_state_with_url(
+            &wasm_bindgen::JsValue::NULL,
+            "",
+            Some(&path),
+        );
+    }
+}
+
+fn window() -> web_sys::Window {
+    web_sys::window().expect("no window")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::api_model::Class;
+    use crate::level_history::LevelSnapshot;
+
+    #[test]
+    fn shared_characters_round_trip() {
+        let mut document = CharacterDocument::default();
+        document.details.name = "Grog Strongjaw".to_string();
+        document.details.class = "barbarian".to_string();
+        let encoded = encode_shared_character(&document);
+        // Safe to put in a URL as is
+        assert!(encoded
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert!(decode_shared_character(&encoded).unwrap() == document);
+    }
+
+    #[test]
+    fn shares_only_homebrew_in_use() {
+        let homebrew_class = |slug: &str| Class {
+            slug: slug.to_string(),
+            ..Default::default()
+        };
+        let mut document = CharacterDocument::default();
+        document.details.class = "homebrew-blood-hunter".to_string();
+        document.homebrew.classes = vec![
+            homebrew_class("homebrew-blood-hunter"),
+            homebrew_class("homebrew-artificer"),
+        ];
+        let shared =
+            decode_shared_character(&encode_shared_character(&document))
+                .unwrap();
+        assert_eq!(
+            shared.homebrew.classes,
+            vec![homebrew_class("homebrew-blood-hunter")]
+        );
+    }
+
+    #[test]
+    fn leaves_out_level_history() {
+        let mut document = CharacterDocument::default();
+        document.details.set_level(2);
+        document.level_history = vec![LevelSnapshot {
+            level: 1,
+            max_hp: 12,
+            ..Default::default()
+        }];
+        let encoded = encode_shared_character(&document);
+        let json = String::from_utf8(
+            decompress_to_vec_with_limit(
+                &URL_SAFE_NO_PAD.decode(&encoded).unwrap(),
+                MAX_SHARED_SIZE,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert!(!json.contains("level_history"));
+        let shared = decode_shared_character(&encoded).unwrap();
+        assert!(shared.level_history.is_empty());
+        assert_eq!(shared.details.level(), 2);
+    }
+
+    #[test]
+    fn rejects_broken_links() {
+        let encoded = encode_shared_character(&CharacterDocument::default());
+        assert!(decode_shared_character(&encoded[..encoded.len() / 2]).is_err());
+        assert!(decode_shared_character("not a character!").is_err());
+    }
+}
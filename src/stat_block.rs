@@ -0,0 +1,325 @@
+//! The character written out as a stat block, laid out like a
+//! creature's in the Monster Manual, for posting in Discord or on a
+//! wiki. It's made from the same numbers as the printed sheet.
+
+use crate::character_model::Ability;
+use crate::print::PrintSheet;
+
+/// What a stat block can be written as.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StatBlockFormat {
+    Markdown,
+    PlainText,
+}
+
+impl StatBlockFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            StatBlockFormat::Markdown => "md",
+            StatBlockFormat::PlainText => "txt",
+        }
+    }
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            StatBlockFormat::Markdown => "text/markdown",
+            StatBlockFormat::PlainText => "text/plain",
+        }
+    }
+}
+
+/// Stat blocks list ability scores in this order, rather than the
+/// sheet's.
+const ABILITY_ORDER: [(Ability, &str); 6] = [
+    (Ability::Strength, "STR"),
+    (Ability::Dexterity, "DEX"),
+    (Ability::Constitution, "CON"),
+    (Ability::Intelligence, "INT"),
+    (Ability::Wisdom, "WIS"),
+    (Ability::Charisma, "CHA"),
+];
+
+pub fn render_stat_block(
+    sheet: &PrintSheet,
+    format: StatBlockFormat,
+) -> String {
+    let markdown = format == StatBlockFormat::Markdown;
+    // Descriptions from Open5e, and attack descriptions, are markdown
+    let text = |text: &str| {
+        if markdown {
+            text.to_string()
+        } else {
+            strip_markdown(text)
+        }
+    };
+    let heading = |level: usize, title: &str| {
+        if markdown {
+            format!("{} {}", "#".repeat(level), title)
+        } else {
+            let underline = if level <= 2 { "=" } else { "-" };
+            format!("{}\n{}", title, underline.repeat(title.chars().count()))
+        }
+    };
+    let property = |name: &str, value: &str| {
+        if markdown {
+            format!("**{}** {}", name, value)
+        } else {
+            format!("{}: {}", name, value)
+        }
+    };
+    let entry = |name: &str, desc: &str| {
+        let desc = text(desc);
+        let desc = desc.trim();
+        match (markdown, desc.is_empty()) {
+            (true, true) => format!("***{}.***", name),
+            (true, false) => format!("***{}.*** {}", name, desc),
+            (false, true) => format!("{}.", name),
+            (false, false) => format!("{}. {}", name, desc),
+        }
+    };
+    let ability = |ability: &Ability| {
+        sheet.abilities.iter().find(|a| a.ability == *ability)
+    };
+
+    let mut sections = vec![];
+    sections.push(heading(
+        2,
+        if sheet.name.is_empty() {
+            "Unnamed character"
+        } else {
+            &sheet.name
+        },
+    ));
+    let class_and_level = if sheet.class.is_empty() {
+        String::new()
+    } else {
+        format!("{} {}", sheet.class, sheet.level)
+    };
+    let summary = [
+        [sheet.species.as_str(), class_and_level.as_str()]
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<&str>>()
+            .join(" "),
+        sheet.background.to_string(),
+    ]
+    .into_iter()
+    .filter(|s| !s.is_empty())
+    .collect::<Vec<String>>()
+    .join(", ");
+    if !summary.is_empty() {
+        sections.push(if markdown {
+            format!("*{}*", summary)
+        } else {
+            summary
+        });
+    }
+
+    // Armor isn't tracked, so this is the character's AC without it
+    let dex_mod = ability(&Ability::Dexterity)
+        .map(|a| a.modifier)
+        .unwrap_or_default();
+    let defenses = [
+        property("Armor Class", &format!("{} (without armor)", 10 + dex_mod)),
+        property(
+            "Hit Points",
+            &if sheet.hit_dice.is_empty() {
+                sheet.max_hp.to_string()
+            } else {
+                format!("{} ({})", sheet.max_hp, sheet.hit_dice)
+            },
+        ),
+        property("Speed", &format!("{} ft.", sheet.speed)),
+    ];
+    sections.push(lines(&defenses, markdown));
+
+    let scores = ABILITY_ORDER
+        .iter()
+        .map(|(a, label)| {
+            let (score, modifier) =
+                ability(a).map(|a| (a.score, a.modifier)).unwrap_or((10, 0));
+            (label, format!("{} ({:+})", score, modifier))
+        })
+        .collect::<Vec<_>>();
+    sections.push(if markdown {
+        format!(
+            "| {} |\n|{}\n| {} |",
+            scores
+                .iter()
+                .map(|(label, _)| **label)
+                .collect::<Vec<&str>>()
+                .join(" | "),
+            ":---:|".repeat(scores.len()),
+            scores
+                .iter()
+                .map(|(_, score)| score.as_str())
+                .collect::<Vec<&str>>()
+                .join(" | ")
+        )
+    } else {
+        scores
+            .iter()
+            .map(|(label, score)| format!("{} {}", label, score))
+            .collect::<Vec<String>>()
+            .join("   ")
+    });
+
+    let mut details = vec![];
+    let saves = ABILITY_ORDER
+        .iter()
+        .filter_map(|(a, _)| ability(a))
+        .filter(|a| a.save_proficient)
+        .map(|a| format!("{} {:+}", &a.ability.to_string()[..3], a.save))
+        .collect::<Vec<String>>();
+    if !saves.is_empty() {
+        details.push(property("Saving Throws", &saves.join(", ")));
+    }
+    let skills = sheet
+        .skills
+        .iter()
+        .filter(|s| s.proficient)
+        .map(|s| format!("{} {:+}", s.skill, s.bonus))
+        .collect::<Vec<String>>();
+    if !skills.is_empty() {
+        details.push(property("Skills", &skills.join(", ")));
+    }
+    let mut senses = sheet.senses.clone();
+    senses.push(format!("passive Perception {}", sheet.passive_perception));
+    details.push(property("Senses", &senses.join(", ")));
+    if !sheet.languages.is_empty() {
+        details.push(property("Languages", &sheet.languages.join(", ")));
+    }
+    details.push(property(
+        "Proficiency Bonus",
+        &format!("{:+}", sheet.proficiency_bonus),
+    ));
+    sections.push(lines(&details, markdown));
+
+    if !sheet.attacks.is_empty() {
+        sections.push(heading(3, "Attacks"));
+        for attack in &sheet.attacks {
+            sections.push(entry(&attack.name, &attack.description));
+        }
+    }
+
+    if !sheet.features.is_empty() {
+        sections.push(heading(3, "Features"));
+        let mut sources: Vec<&str> = vec![];
+        for feature in &sheet.features {
+            if !sources.contains(&feature.source.as_str()) {
+                sources.push(&feature.source);
+            }
+        }
+        for source in sources {
+            sections.push(heading(4, source));
+            for feature in sheet.features.iter().filter(|f| f.source == source)
+            {
+                sections.push(entry(&feature.name, &feature.desc));
+            }
+        }
+    }
+
+    sections.join("\n\n") + "\n"
+}
+
+/// Lines that belong together. Markdown needs two spaces at the end
+/// of a line to keep it from running into the next one.
+fn lines(lines: &[String], markdown: bool) -> String {
+    lines.join(if markdown { "  \n" } else { "\n" })
+}
+
+/// Take the emphasis and heading marks out of markdown, i.e.
+/// "_Hit:_ 7 (**1d8 + 3**)" becomes "Hit: 7 (1d8 + 3)". Lists and
+/// tables are readable enough as they are.
+pub fn strip_markdown(markdown: &str) -> String {
+    markdown
+        .lines()
+        .map(|line| {
+            let line = if line.starts_with('#') {
+                line.trim_start_matches('#').trim_start()
+            } else {
+                line
+            };
+            // A list item's bullet stays
+            let (bullet, rest) = match line.strip_prefix("* ") {
+                Some(rest) => ("* ", rest),
+                None => ("", line),
+            };
+            let chars = rest.chars().collect::<Vec<char>>();
+            let is_boundary = |i: Option<usize>| {
+                i.and_then(|i| chars.get(i))
+                    .is_none_or(|c| !c.is_alphanumeric())
+            };
+            let stripped = chars
+                .iter()
+                .enumerate()
+                .filter(|(i, c)| match c {
+                    '*' => false,
+                    // Underscores inside words, as in a URL, stay
+                    '_' => {
+                        !(is_boundary(i.checked_sub(1))
+                            || is_boundary(Some(i + 1)))
+                    }
+                    _ => true,
+                })
+                .map(|(_, c)| c)
+                .collect::<String>();
+            format!("{}{}", bullet, stripped)
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_data::grog_sheet;
+
+    #[test]
+    fn writes_markdown_stat_block() {
+        let block = render_stat_block(&grog_sheet(), StatBlockFormat::Markdown);
+        for expected in [
+            "## Grog\n\n*Goliath Barbarian 3, Outlander*",
+            "**Armor Class** 12 (without armor)  \n**Hit Points** 35 (3d12)",
+            "| STR | DEX | CON | INT | WIS | CHA |",
+            "| 18 (+4) | 14 (+2) |",
+            "**Saving Throws** Str +6",
+            "**Skills** Athletics +6  \n",
+            "**Senses** darkvision 60 ft., passive Perception 11",
+            "**Languages** Common, Giant",
+            "### Attacks\n\n***Greataxe.*** _Melee Weapon Attack:_ +6",
+            "#### Barbarian\n\n***Rage.*** In battle",
+            "#### Goliath\n\n***Stone's Endurance.***",
+        ] {
+            assert!(block.contains(expected), "{}\n\n{}", expected, block);
+        }
+        // Only proficient skills are listed
+        assert!(!block.contains("Arcana"));
+    }
+
+    #[test]
+    fn writes_plain_text_stat_block() {
+        let block =
+            render_stat_block(&grog_sheet(), StatBlockFormat::PlainText);
+        assert!(
+            block.starts_with("Grog\n====\n\nGoliath Barbarian 3, Outlander")
+        );
+        assert!(block.contains("STR 18 (+4)   DEX 14 (+2)"));
+        assert!(block.contains("Hit Points: 35 (3d12)\nSpeed: 30 ft."));
+        assert!(block.contains(
+            "Greataxe. Melee Weapon Attack: +6 to hit, reach 5 ft., one \
+            target. Hit: 1d12 + 4 slashing damage."
+        ));
+        assert!(block.contains("fight with primal ferocity"));
+        assert!(!block.contains('*'));
+        assert!(!block.contains('#'));
+        assert!(!block.contains('_'));
+    }
+
+    #[test]
+    fn strips_markdown() {
+        assert_eq!(
+            strip_markdown("### Rage\n\n* _Hit:_ **1d8**, see some_file"),
+            "Rage\n\n* Hit: 1d8, see some_file"
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use leptos::logging::log;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{IdbDatabase, IdbObjectStore, IdbRequest, IdbTransactionMode};
+
+use super::{MemoryStorage, StorageBackend, StorageError};
+
+const DATABASE_VERSION: u32 = 1;
+const STORE_NAME: &str = "values";
+
+/// The browser's IndexedDB, which can hold far more than localStorage.
+///
+/// IndexedDB only works asynchronously, so everything in it is read
+/// into memory when it's opened and reads are served from there.
+/// Writes go to memory straight away and are saved to the database
+/// in the background.
+pub struct IndexedDbStorage {
+    database: IdbDatabase,
+    cache: MemoryStorage,
+}
+
+impl IndexedDbStorage {
+    /// Open the database called `name`, creating it if it's new.
+    pub async fn open(name: &str) -> Result<Self, StorageError> {
+        let factory = web_sys::window()
+            .and_then(|w| w.indexed_db().ok().flatten())
+            .ok_or_else(|| StorageError("IndexedDB isn't available".into()))?;
+        let request = factory
+            .open_with_u32(name, DATABASE_VERSION)
+            .map_err(js_error)?;
+        // Create the store the first time the database is opened
+        let on_upgrade = Closure::once_into_js({
+            let request = request.clone();
+            move || {
+                if let Ok(database) = request.result() {
+                    let database = database.unchecked_into::<IdbDatabase>();
+                    let _ = database.create_object_store(STORE_NAME);
+                }
+            }
+        });
+        request.set_onupgradeneeded(Some(on_upgrade.unchecked_ref()));
+        let database = request_result(&request)
+            .await?
+            .unchecked_into::<IdbDatabase>();
+
+        let store = database
+            .transaction_with_str(STORE_NAME)
+            .and_then(|t| t.object_store(STORE_NAME))
+            .map_err(js_error)?;
+        // Make both requests before waiting on either, as the
+        // transaction closes once it has nothing left to do
+        let keys = store.get_all_keys().map_err(js_error)?;
+        let values = store.get_all().map_err(js_error)?;
+        let keys = js_sys::Array::from(&request_result(&keys).await?);
+        let values = js_sys::Array::from(&request_result(&values).await?);
+
+        let cache = MemoryStorage::default();
+        for (key, value) in keys.iter().zip(values.iter()) {
+            if let (Some(key), Some(value)) =
+                (key.as_string(), value.as_string())
+            {
+                cache.set(&key, &value)?;
+            }
+        }
+        Ok(Self { database, cache })
+    }
+
+    /// Make a change to the database, without waiting for it.
+    fn write(
+        &self,
+        write: impl FnOnce(&IdbObjectStore) -> Result<IdbRequest, JsValue>,
+    ) -> Result<(), StorageError> {
+        let store = self
+            .database
+            .transaction_with_str_and_mode(
+                STORE_NAME,
+                IdbTransactionMode::Readwrite,
+            )
+            .and_then(|t| t.object_store(STORE_NAME))
+            .map_err(js_error)?;
+        let request = write(&store).map_err(js_error)?;
+        let on_error = Closure::once_into_js(|| {
+            log!("Could not save to IndexedDB");
+        });
+        request.set_onerror(Some(on_error.unchecked_ref()));
+        Ok(())
+    }
+}
+
+impl StorageBackend for IndexedDbStorage {
+    fn get(&self, key: &str) -> Option<String> {
+        self.cache.get(key)
+    }
+    fn set(&self, key: &str, value: &str) -> Result<(), StorageError> {
+        self.cache.set(key, value)?;
+        self.write(|store| {
+            store.put_with_key(
+                &JsValue::from_str(value),
+                &JsValue::from_str(key),
+            )
+        })
+    }
+    fn remove(&self, key: &str) {
+        self.cache.remove(key);
+        if let Err(e) =
+            self.write(|store| store.delete(&JsValue::from_str(key)))
+        {
+            log!("Could not remove {} from IndexedDB: {}", key, e);
+        }
+    }
+    fn keys(&self) -> Vec<String> {
+        self.cache.keys()
+    }
+}
+
+/// Wait for an IndexedDB request to finish, and get its result.
+async fn request_result(request: &IdbRequest) -> Result<JsValue, StorageError> {
+    let promise = js_sys::Promise::new(&mut |resolve, reject| {
+        let on_success = Closure::once_into_js({
+            let request = request.clone();
+            move || {
+                let result = request.result().unwrap_or(JsValue::UNDEFINED);
+                let _ = resolve.call1(&JsValue::NULL, &result);
+            }
+        });
+        let on_error = Closure::once_into_js(move || {
+            let _ = reject.call0(&JsValue::NULL);
+        });
+        request.set_onsuccess(Some(on_success.unchecked_ref()));
+        request.set_onerror(Some(on_error.unchecked_ref()));
+    });
+    JsFuture::from(promise)
+        .await
+        .map_err(|_| StorageError("IndexedDB request failed".to_string()))
+}
+
+fn js_error(error: JsValue) -> StorageError {
+    StorageError(format!("{:?}", error))
+}
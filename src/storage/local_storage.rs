@@ -0,0 +1,37 @@
+use super::{StorageBackend, StorageError};
+
+/// The browser's localStorage. Synchronous and simple, but limited
+/// to around 5MB per site.
+pub struct LocalStorage {
+    storage: web_sys::Storage,
+}
+
+impl LocalStorage {
+    /// Returns `None` if there's no window, or if the browser
+    /// won't give us access to localStorage.
+    pub fn new() -> Option<Self> {
+        let storage = web_sys::window()?.local_storage().ok()??;
+        Some(Self { storage })
+    }
+}
+
+impl StorageBackend for LocalStorage {
+    fn get(&self, key: &str) -> Option<String> {
+        self.storage.get_item(key).ok().flatten()
+    }
+    fn set(&self, key: &str, value: &str) -> Result<(), StorageError> {
+        self.storage.set_item(key, value).map_err(|e| {
+            // Usually the quota being exceeded
+            StorageError(format!("could not write {}: {:?}", key, e))
+        })
+    }
+    fn remove(&self, key: &str) {
+        let _ = self.storage.remove_item(key);
+    }
+    fn keys(&self) -> Vec<String> {
+        let length = self.storage.length().unwrap_or(0);
+        (0..length)
+            .filter_map(|i| self.storage.key(i).ok().flatten())
+            .collect()
+    }
+}
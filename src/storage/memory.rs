@@ -0,0 +1,29 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use super::{StorageBackend, StorageError};
+
+/// Keeps data in memory only, so it's lost when the page is closed.
+/// Used when the browser won't let us store anything, and in tests.
+#[derive(Default)]
+pub struct MemoryStorage {
+    values: RefCell<BTreeMap<String, String>>,
+}
+
+impl StorageBackend for MemoryStorage {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values.borrow().get(key).cloned()
+    }
+    fn set(&self, key: &str, value: &str) -> Result<(), StorageError> {
+        self.values
+            .borrow_mut()
+            .insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+    fn remove(&self, key: &str) {
+        self.values.borrow_mut().remove(key);
+    }
+    fn keys(&self) -> Vec<String> {
+        self.values.borrow().keys().cloned().collect()
+    }
+}
@@ -0,0 +1,140 @@
+//! Where the app keeps its data. Everything is stored as JSON strings
+//! under string keys, through a `StorageBackend`, so the same code can
+//! save to localStorage, IndexedDB or, in tests, plain memory.
+
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::rc::Rc;
+
+use leptos::logging::log;
+
+mod indexed_db;
+mod local_storage;
+mod memory;
+
+pub use indexed_db::IndexedDbStorage;
+pub use local_storage::LocalStorage;
+pub use memory::MemoryStorage;
+
+/// A place to keep string values under string keys.
+pub trait StorageBackend {
+    fn get(&self, key: &str) -> Option<String>;
+    fn set(&self, key: &str, value: &str) -> Result<(), StorageError>;
+    fn remove(&self, key: &str);
+    /// Every key with a value stored.
+    fn keys(&self) -> Vec<String>;
+}
+
+/// So that the shared `backend()` can be passed wherever a
+/// `&dyn StorageBackend` is wanted.
+impl<T: StorageBackend + ?Sized> StorageBackend for Rc<T> {
+    fn get(&self, key: &str) -> Option<String> {
+        (**self).get(key)
+    }
+    fn set(&self, key: &str, value: &str) -> Result<(), StorageError> {
+        (**self).set(key, value)
+    }
+    fn remove(&self, key: &str) {
+        (**self).remove(key)
+    }
+    fn keys(&self) -> Vec<String> {
+        (**self).keys()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageError(pub String);
+impl Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The IndexedDB database characters and settings are kept in.
+const DATABASE_NAME: &str = "goblin_sheet";
+/// The IndexedDB database data fetched from elsewhere is cached in.
+const CACHE_DATABASE_NAME: &str = "goblin_sheet_cache";
+
+thread_local! {
+    static BACKEND: RefCell<Rc<dyn StorageBackend>> =
+        RefCell::new(default_backend());
+    static CACHE_BACKEND: RefCell<Rc<dyn StorageBackend>> =
+        RefCell::new(Rc::new(MemoryStorage::default()));
+}
+
+/// The backend the app is currently storing data in.
+pub fn backend() -> Rc<dyn StorageBackend> {
+    BACKEND.with(|b| b.borrow().clone())
+}
+
+pub fn set_backend(backend: Rc<dyn StorageBackend>) {
+    BACKEND.with(|b| *b.borrow_mut() = backend);
+}
+
+/// Swap in empty memory storage, i.e. while a character shared with
+/// the player is open, so nothing of it is saved over their own data.
+/// Returns the player's own storage, which their settings are still
+/// loaded from and saved to.
+pub fn swap_in_memory() -> Rc<dyn StorageBackend> {
+    let home = backend();
+    set_backend(Rc::new(MemoryStorage::default()));
+    home
+}
+
+/// Where data fetched from elsewhere, i.e. Open5e's lists, is cached.
+/// This is kept apart from `backend()` so that a full cache can never
+/// stop a character being saved. It's never localStorage, whose few
+/// megabytes are left for characters, so it's memory until IndexedDB
+/// is opened, or for good if it can't be.
+pub fn cache_backend() -> Rc<dyn StorageBackend> {
+    CACHE_BACKEND.with(|b| b.borrow().clone())
+}
+
+/// localStorage if the browser lets us use it, otherwise memory,
+/// which at least keeps the app working for the session.
+fn default_backend() -> Rc<dyn StorageBackend> {
+    // There's no browser to ask in tests
+    if cfg!(test) {
+        return Rc::new(MemoryStorage::default());
+    }
+    match LocalStorage::new() {
+        Some(local_storage) => Rc::new(local_storage),
+        None => Rc::new(MemoryStorage::default()),
+    }
+}
+
+/// Switch to IndexedDB, which has far more room than localStorage,
+/// if it's available. Has to be done before the app starts, as
+/// IndexedDB can only be opened asynchronously. Data already in
+/// localStorage is copied across the first time. The cache gets its
+/// own database, so it's opened here too.
+pub async fn open_preferred_backend() {
+    match IndexedDbStorage::open(CACHE_DATABASE_NAME).await {
+        Ok(indexed_db) => {
+            CACHE_BACKEND.with(|b| *b.borrow_mut() = Rc::new(indexed_db))
+        }
+        Err(e) => log!("Could not open the IndexedDB cache: {}", e),
+    }
+    match IndexedDbStorage::open(DATABASE_NAME).await {
+        Ok(indexed_db) => {
+            if indexed_db.keys().is_empty() {
+                if let Some(local_storage) = LocalStorage::new() {
+                    copy_all(&local_storage, &indexed_db);
+                }
+            }
+            set_backend(Rc::new(indexed_db));
+        }
+        Err(e) => log!("Could not open IndexedDB, using localStorage: {}", e),
+    }
+}
+
+/// Copy every value from one backend to another.
+pub fn copy_all(from: &dyn StorageBackend, to: &dyn StorageBackend) {
+    for key in from.keys() {
+        if let Some(value) = from.get(&key) {
+            if let Err(e) = to.set(&key, &value) {
+                log!("Could not copy {}: {}", key, e);
+            }
+        }
+    }
+}
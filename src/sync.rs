@@ -0,0 +1,454 @@
+//! Syncing characters with a self-hosted `goblin_sync` server, so
+//! that they follow the player between devices.
+//!
+//! The server keeps a revision number for each character. We remember
+//! the revision we last synced, along with the character as it was
+//! then, so that we can tell whether the character has changed here,
+//! on the server, or in both places. If only one side has changed, it
+//! wins; if both have, the player picks which to keep, or merges them.
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::character_model::CharacterDocument;
+use crate::migrations::*;
+use crate::persistence::{load_sync_state, write_document, write_sync_state};
+use crate::settings::SyncSettings;
+use crate::storage::StorageBackend;
+
+/// A character as it is on the server.
+#[derive(Clone, PartialEq)]
+pub struct RemoteCharacter {
+    pub id: String,
+    pub revision: u64,
+    /// Seconds since the epoch.
+    pub updated_at: u64,
+    pub document: CharacterDocument,
+}
+
+/// A character as the server sends it, before its document has
+/// been brought up to the current schema version.
+#[derive(Deserialize)]
+struct StoredCharacter {
+    id: String,
+    revision: u64,
+    updated_at: u64,
+    document: Value,
+}
+
+impl TryFrom<StoredCharacter> for RemoteCharacter {
+    type Error = SyncError;
+    fn try_from(stored: StoredCharacter) -> Result<Self, SyncError> {
+        let document =
+            from_versioned_value(stored.document, &DOCUMENT_MIGRATIONS)
+                .map_err(|e| SyncError::Invalid(e.to_string()))?;
+        Ok(Self {
+            id: stored.id,
+            revision: stored.revision,
+            updated_at: stored.updated_at,
+            document: document.data,
+        })
+    }
+}
+
+/// What the server lists for each character. Only the id is needed,
+/// to tell which characters aren't here yet.
+#[derive(Deserialize)]
+struct RemoteSummary {
+    id: String,
+}
+
+/// The revision of a character we last synced, and the character as
+/// it was then.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct SyncState {
+    pub revision: u64,
+    pub base: CharacterDocument,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncError {
+    NotConfigured,
+    Network(String),
+    Unauthorized,
+    Server(u16),
+    Invalid(String),
+}
+impl Display for SyncError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncError::NotConfigured => {
+                write!(f, "enter the server's address and token first")
+            }
+            SyncError::Network(e) => {
+                write!(f, "could not reach server ({})", e)
+            }
+            SyncError::Unauthorized => {
+                write!(f, "the server rejected the token")
+            }
+            SyncError::Server(status) => {
+                write!(f, "the server responded with status {}", status)
+            }
+            SyncError::Invalid(e) => {
+                write!(f, "could not read the server's response ({})", e)
+            }
+        }
+    }
+}
+
+/// How syncing the open character is going, shown in the sync modal.
+#[derive(Clone, PartialEq)]
+pub enum SyncStatus {
+    Idle,
+    Syncing,
+    Synced,
+    Conflict(Box<RemoteCharacter>),
+    Failed(String),
+}
+
+/// What needs to happen to bring a character and the server in line.
+#[derive(Clone, PartialEq)]
+pub enum SyncPlan {
+    UpToDate,
+    /// Only changed here; send it to the server.
+    Upload {
+        base_revision: u64,
+    },
+    /// Only changed on the server; take the server's version.
+    Download(RemoteCharacter),
+    /// Changed in both places.
+    Conflict(RemoteCharacter),
+}
+
+/// Work out what to do to sync a character, from the character here,
+/// what we last synced, and the character on the server.
+pub fn plan_sync(
+    local: &CharacterDocument,
+    state: Option<&SyncState>,
+    remote: Option<&RemoteCharacter>,
+) -> SyncPlan {
+    let remote = match remote {
+        Some(remote) => remote,
+        None => return SyncPlan::Upload { base_revision: 0 },
+    };
+    let changed_here = state.is_none_or(|s| s.base != *local);
+    let changed_there = state.is_none_or(|s| s.revision != remote.revision);
+    match (changed_here, changed_there) {
+        (false, false) => SyncPlan::UpToDate,
+        (true, false) => SyncPlan::Upload {
+            base_revision: remote.revision,
+        },
+        (false, true) => SyncPlan::Download(remote.clone()),
+        // Nothing to choose between if both made the same changes
+        (true, true) if remote.document == *local => {
+            SyncPlan::Download(remote.clone())
+        }
+        (true, true) => SyncPlan::Conflict(remote.clone()),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MergeSide {
+    Local,
+    Remote,
+}
+
+/// Which version to take each part of a character from, when merging.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MergeChoices {
+    pub details: MergeSide,
+    pub selected_optional_features: MergeSide,
+    pub attack_list: MergeSide,
+}
+
+pub fn merge_documents(
+    local: &CharacterDocument,
+    remote: &CharacterDocument,
+    choices: MergeChoices,
+) -> CharacterDocument {
+    let pick = |side| match side {
+        MergeSide::Local => local,
+        MergeSide::Remote => remote,
+    };
+    CharacterDocument {
+        details: pick(choices.details).details.clone(),
+        selected_optional_features: pick(choices.selected_optional_features)
+            .selected_optional_features
+            .clone(),
+        attack_list: pick(choices.attack_list).attack_list.clone(),
+        // Homebrew and level history go with the details they belong to
+        homebrew: pick(choices.details).homebrew.clone(),
+        level_history: pick(choices.details).level_history.clone(),
+    }
+}
+
+/// How syncing a character turned out.
+#[derive(Clone, PartialEq)]
+pub enum SyncOutcome {
+    UpToDate,
+    Uploaded,
+    /// The server has a newer version. It's up to the caller to
+    /// take it with `accept_remote`, as the character may have been
+    /// changed here while syncing.
+    Downloaded(RemoteCharacter),
+    Conflict(RemoteCharacter),
+}
+
+/// Sync a character with the server.
+pub async fn sync_character(
+    settings: &SyncSettings,
+    storage: &dyn StorageBackend,
+    id: &str,
+    local: &CharacterDocument,
+) -> Result<SyncOutcome, SyncError> {
+    let remote = fetch_character(settings, id).await?;
+    let state = load_sync_state(storage, id);
+    match plan_sync(local, state.as_ref(), remote.as_ref()) {
+        SyncPlan::UpToDate => Ok(SyncOutcome::UpToDate),
+        SyncPlan::Upload { base_revision } => {
+            upload(settings, storage, id, base_revision, local).await
+        }
+        SyncPlan::Download(remote) => Ok(SyncOutcome::Downloaded(remote)),
+        SyncPlan::Conflict(remote) => Ok(SyncOutcome::Conflict(remote)),
+    }
+}
+
+/// Settle a conflict by saving `resolved` over the server's version,
+/// i.e. the character as it is here, or a merge of the two.
+pub async fn overwrite_remote(
+    settings: &SyncSettings,
+    storage: &dyn StorageBackend,
+    remote: &RemoteCharacter,
+    resolved: &CharacterDocument,
+) -> Result<SyncOutcome, SyncError> {
+    upload(settings, storage, &remote.id, remote.revision, resolved).await
+}
+
+/// Take the server's version of a character, replacing the one here.
+pub fn accept_remote(storage: &dyn StorageBackend, remote: &RemoteCharacter) {
+    write_document(storage, &remote.id, &remote.document);
+    write_sync_state(
+        storage,
+        &remote.id,
+        &SyncState {
+            revision: remote.revision,
+            base: remote.document.clone(),
+        },
+    );
+}
+
+/// Download the characters on the server that aren't here, i.e.
+/// ones made on another device. `known` are the ids of every
+/// character here, including ones in the recycle bin and ones that
+/// were deleted for good; see `Roster::known_ids`. Returns the
+/// ids and names of the new characters.
+pub async fn fetch_new_characters(
+    settings: &SyncSettings,
+    storage: &dyn StorageBackend,
+    known: &[String],
+) -> Result<Vec<(String, String)>, SyncError> {
+    let mut new_characters = vec![];
+    for summary in list_characters(settings).await? {
+        if known.contains(&summary.id) {
+            continue;
+        }
+        if let Some(remote) = fetch_character(settings, &summary.id).await? {
+            accept_remote(storage, &remote);
+            new_characters.push((remote.id, remote.document.details.name));
+        }
+    }
+    Ok(new_characters)
+}
+
+async fn upload(
+    settings: &SyncSettings,
+    storage: &dyn StorageBackend,
+    id: &str,
+    base_revision: u64,
+    document: &CharacterDocument,
+) -> Result<SyncOutcome, SyncError> {
+    let body = json!({
+        "base_revision": base_revision,
+        "document": to_versioned_value(document)
+            .map_err(|e| SyncError::Invalid(e.to_string()))?,
+    });
+    let response = request(settings, reqwest::Method::PUT, id)?
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| SyncError::Network(e.to_string()))?;
+    match response.status().as_u16() {
+        200 => {
+            let saved = read_character(response).await?;
+            write_sync_state(
+                storage,
+                id,
+                &SyncState {
+                    revision: saved.revision,
+                    base: document.clone(),
+                },
+            );
+            Ok(SyncOutcome::Uploaded)
+        }
+        // Someone else saved it between fetching and uploading
+        409 => Ok(SyncOutcome::Conflict(read_character(response).await?)),
+        status => Err(status_error(status)),
+    }
+}
+
+pub async fn fetch_character(
+    settings: &SyncSettings,
+    id: &str,
+) -> Result<Option<RemoteCharacter>, SyncError> {
+    let response = request(settings, reqwest::Method::GET, id)?
+        .send()
+        .await
+        .map_err(|e| SyncError::Network(e.to_string()))?;
+    match response.status().as_u16() {
+        200 => Ok(Some(read_character(response).await?)),
+        404 => Ok(None),
+        status => Err(status_error(status)),
+    }
+}
+
+/// Every character on the server.
+async fn list_characters(
+    settings: &SyncSettings,
+) -> Result<Vec<RemoteSummary>, SyncError> {
+    let response = request(settings, reqwest::Method::GET, "")?
+        .send()
+        .await
+        .map_err(|e| SyncError::Network(e.to_string()))?;
+    match response.status().as_u16() {
+        200 => response
+            .json::<Vec<RemoteSummary>>()
+            .await
+            .map_err(|e| SyncError::Invalid(e.to_string())),
+        status => Err(status_error(status)),
+    }
+}
+
+/// Start a request for a character, or the list of characters if
+/// `id` is empty.
+fn request(
+    settings: &SyncSettings,
+    method: reqwest::Method,
+    id: &str,
+) -> Result<reqwest::RequestBuilder, SyncError> {
+    if !settings.is_configured() {
+        return Err(SyncError::NotConfigured);
+    }
+    let url = format!(
+        "{}/characters/{}",
+        settings.server_url.trim().trim_end_matches('/'),
+        id
+    );
+    Ok(reqwest::Client::new()
+        .request(method, url.trim_end_matches('/'))
+        .bearer_auth(&settings.token))
+}
+
+async fn read_character(
+    response: reqwest::Response,
+) -> Result<RemoteCharacter, SyncError> {
+    response
+        .json::<StoredCharacter>()
+        .await
+        .map_err(|e| SyncError::Invalid(e.to_string()))?
+        .try_into()
+}
+
+fn status_error(status: u16) -> SyncError {
+    match status {
+        401 => SyncError::Unauthorized,
+        status => SyncError::Server(status),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::api_extensions::FeatureOptionsSelection;
+
+    fn named(name: &str) -> CharacterDocument {
+        let mut document = CharacterDocument::default();
+        document.details.name = name.to_string();
+        document
+    }
+
+    fn remote(revision: u64, document: CharacterDocument) -> RemoteCharacter {
+        RemoteCharacter {
+            id: "grog".to_string(),
+            revision,
+            updated_at: 0,
+            document,
+        }
+    }
+
+    #[test]
+    fn plans_sync_from_what_changed_where() {
+        let state = SyncState {
+            revision: 3,
+            base: named("Grog"),
+        };
+        let state = Some(&state);
+        let unchanged = remote(3, named("Grog"));
+        let changed = remote(4, named("Grog the Grand"));
+
+        assert!(
+            plan_sync(&named("Grog"), state, None)
+                == SyncPlan::Upload { base_revision: 0 }
+        );
+        assert!(
+            plan_sync(&named("Grog"), state, Some(&unchanged))
+                == SyncPlan::UpToDate
+        );
+        assert!(
+            plan_sync(&named("Grog the Great"), state, Some(&unchanged))
+                == SyncPlan::Upload { base_revision: 3 }
+        );
+        assert!(
+            plan_sync(&named("Grog"), state, Some(&changed))
+                == SyncPlan::Download(changed.clone())
+        );
+        assert!(
+            plan_sync(&named("Grog the Great"), state, Some(&changed))
+                == SyncPlan::Conflict(changed.clone())
+        );
+        // Never synced from here, but the same as the server's
+        assert!(
+            plan_sync(&named("Grog the Grand"), None, Some(&changed))
+                == SyncPlan::Download(changed.clone())
+        );
+    }
+
+    #[test]
+    fn merges_parts_from_either_side() {
+        let mut local = named("Grog");
+        local
+            .selected_optional_features
+            .push(FeatureOptionsSelection {
+                slug: "class:barbarian:class-skills:0".to_string(),
+                selection: 2,
+            });
+        let mut theirs = named("Grog the Grand");
+        theirs.details.xp = 300;
+        let merged = merge_documents(
+            &local,
+            &theirs,
+            MergeChoices {
+                details: MergeSide::Remote,
+                selected_optional_features: MergeSide::Local,
+                attack_list: MergeSide::Local,
+            },
+        );
+        assert_eq!(merged.details.name, "Grog the Grand");
+        assert_eq!(merged.details.xp, 300);
+        assert!(
+            merged.selected_optional_features
+                == local.selected_optional_features
+        );
+    }
+}
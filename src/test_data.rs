@@ -0,0 +1,92 @@
+//! A character for tests to use, as a document and as the numbers the
+//! printed sheet and stat block are made from.
+
+use crate::character_model::{Ability, CharacterDocument, Skill};
+use crate::print::{
+    PrintAbility, PrintAttack, PrintFeature, PrintSheet, PrintSkill,
+};
+
+pub fn grog() -> CharacterDocument {
+    let mut document = CharacterDocument::default();
+    document.details.name = "Grog".to_string();
+    document.details.class = "barbarian".to_string();
+    document
+}
+
+/// Grog at 3rd level, with 18 Strength and 14 Dexterity.
+pub fn grog_sheet() -> PrintSheet {
+    let score = |ability: &Ability| match ability {
+        Ability::Strength => 18,
+        Ability::Dexterity => 14,
+        _ => 10,
+    };
+    PrintSheet {
+        name: "Grog".to_string(),
+        class: "Barbarian".to_string(),
+        level: 3,
+        species: "Goliath".to_string(),
+        background: "Outlander".to_string(),
+        proficiency_bonus: 2,
+        abilities: Ability::ALL
+            .into_iter()
+            .map(|ability| {
+                let modifier = (score(&ability) - 10) / 2;
+                let save_proficient = ability == Ability::Strength;
+                PrintAbility {
+                    score: score(&ability),
+                    modifier,
+                    save: modifier + if save_proficient { 2 } else { 0 },
+                    save_proficient,
+                    ability,
+                }
+            })
+            .collect(),
+        skills: vec![
+            PrintSkill {
+                skill: Skill::Athletics,
+                bonus: 6,
+                proficient: true,
+            },
+            PrintSkill {
+                skill: Skill::Arcana,
+                bonus: -1,
+                proficient: false,
+            },
+        ],
+        passive_perception: 11,
+        speed: 30,
+        max_hp: 35,
+        hit_dice: "3d12".to_string(),
+        senses: vec!["darkvision 60 ft.".to_string()],
+        languages: vec!["Common".to_string(), "Giant".to_string()],
+        attacks: vec![PrintAttack {
+            name: "Greataxe".to_string(),
+            to_hit: 6,
+            damage: "1d12+4 slashing".to_string(),
+            notes: "Heavy, Two-handed".to_string(),
+            description: "_Melee Weapon Attack:_ +6 to hit, reach 5 ft., \
+                one target. _Hit:_ 1d12 + 4 slashing damage."
+                .to_string(),
+        }],
+        features: vec![
+            PrintFeature {
+                source: "Barbarian".to_string(),
+                name: "Rage".to_string(),
+                desc: "In battle, you fight with **primal** ferocity.\n\n\
+                    | Level | Rages |\n|---|---|\n| 1st | 2 |"
+                    .to_string(),
+            },
+            PrintFeature {
+                source: "Goliath".to_string(),
+                name: "Stone's Endurance".to_string(),
+                desc: "Reduce the damage by **1d12**.".to_string(),
+            },
+            PrintFeature {
+                source: "Barbarian".to_string(),
+                name: "Reckless Attack".to_string(),
+                desc: String::new(),
+            },
+        ],
+        ..Default::default()
+    }
+}
@@ -0,0 +1,149 @@
+//! Just enough HTTP/1.1 to serve the sync API: one request per
+//! connection, with the body sized by `Content-Length`.
+
+use std::io::{self, Read, Write};
+
+/// Largest request we'll accept. Characters are a few kilobytes;
+/// this leaves plenty of room for portraits and notes.
+const MAX_REQUEST_SIZE: usize = 8 * 1024 * 1024;
+const MAX_HEADERS: usize = 32;
+
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Value of a header, ignoring the case of its name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    pub fn json(status: u16, body: &impl serde::Serialize) -> Self {
+        Self {
+            status,
+            body: serde_json::to_string(body).unwrap_or_default(),
+        }
+    }
+    pub fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &serde_json::json!({ "error": message }))
+    }
+    pub fn empty(status: u16) -> Self {
+        Self {
+            status,
+            body: String::new(),
+        }
+    }
+}
+
+/// Read a request from the connection. Returns `None` if the
+/// connection was closed before a whole request arrived.
+pub fn read_request(stream: &mut impl Read) -> io::Result<Option<Request>> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 4096];
+    loop {
+        let read = stream.read(&mut chunk)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        if buffer.len() > MAX_REQUEST_SIZE {
+            return Err(invalid("request too large"));
+        }
+
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let mut parsed = httparse::Request::new(&mut headers);
+        let header_length = match parsed.parse(&buffer) {
+            Ok(httparse::Status::Complete(length)) => length,
+            Ok(httparse::Status::Partial) => continue,
+            Err(e) => return Err(invalid(&e.to_string())),
+        };
+        let headers = parsed
+            .headers
+            .iter()
+            .map(|h| {
+                let value = String::from_utf8_lossy(h.value).to_string();
+                (h.name.to_string(), value)
+            })
+            .collect::<Vec<(String, String)>>();
+        let mut request = Request {
+            method: parsed.method.unwrap_or_default().to_string(),
+            path: parsed.path.unwrap_or_default().to_string(),
+            headers,
+            body: vec![],
+        };
+        let content_length = request
+            .header("Content-Length")
+            .and_then(|l| l.trim().parse::<usize>().ok())
+            .unwrap_or(0);
+        if header_length + content_length > MAX_REQUEST_SIZE {
+            return Err(invalid("request too large"));
+        }
+        // Read the rest of the body, if it hasn't all arrived yet
+        while buffer.len() < header_length + content_length {
+            let read = stream.read(&mut chunk)?;
+            if read == 0 {
+                return Ok(None);
+            }
+            buffer.extend_from_slice(&chunk[..read]);
+        }
+        request.body =
+            buffer[header_length..header_length + content_length].to_vec();
+        return Ok(Some(request));
+    }
+}
+
+pub fn write_response(
+    stream: &mut impl Write,
+    response: &Response,
+    allow_origin: &str,
+) -> io::Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\n\
+        Content-Length: {}\r\n\
+        Connection: close\r\n\
+        Access-Control-Allow-Origin: {}\r\n\
+        Access-Control-Allow-Methods: GET, PUT, OPTIONS\r\n\
+        Access-Control-Allow-Headers: Authorization, Content-Type\r\n",
+        response.status,
+        reason(response.status),
+        response.body.len(),
+        allow_origin,
+    );
+    if !response.body.is_empty() {
+        head.push_str("Content-Type: application/json\r\n");
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(response.body.as_bytes())?;
+    stream.flush()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        _ => "Internal Server Error",
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
@@ -0,0 +1,140 @@
+//! A small server for syncing Goblin Sheet characters between
+//! devices. Characters are kept as files in a directory, and every
+//! request has to carry the server's access token.
+//!
+//! The API:
+//! - `GET /characters`: a summary of every character
+//! - `GET /characters/{id}`: a character, with its revision
+//! - `PUT /characters/{id}`: save a character. The body is
+//!   `{"base_revision": n, "document": {...}}`, where `n` is the
+//!   revision the changes were made to. If the character has been
+//!   saved since, nothing is saved and the response is a 409 with
+//!   the character as it is now.
+
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+pub mod http;
+pub mod store;
+
+use http::{read_request, write_response, Request, Response};
+use store::{PutResult, Store};
+
+pub struct Config {
+    /// Clients have to send this as a bearer token.
+    pub token: String,
+    /// Sent as `Access-Control-Allow-Origin`, so that the app can
+    /// talk to the server from wherever it's hosted.
+    pub allow_origin: String,
+}
+
+#[derive(Deserialize)]
+struct PutRequest {
+    base_revision: u64,
+    document: Value,
+}
+
+/// Handle connections until the listener fails, one thread each.
+pub fn serve(
+    listener: TcpListener,
+    store: Arc<Store>,
+    config: Arc<Config>,
+) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let (store, config) = (store.clone(), config.clone());
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, &store, &config) {
+                eprintln!("Error handling request: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    store: &Store,
+    config: &Config,
+) -> io::Result<()> {
+    let response = match read_request(&mut stream) {
+        Ok(Some(request)) => handle(&request, store, config),
+        Ok(None) => return Ok(()),
+        Err(e) => Response::error(400, &e.to_string()),
+    };
+    write_response(&mut stream, &response, &config.allow_origin)
+}
+
+/// Work out the response to a request.
+pub fn handle(request: &Request, store: &Store, config: &Config) -> Response {
+    // Browsers check first whether they're allowed to send the
+    // request, without credentials
+    if request.method == "OPTIONS" {
+        return Response::empty(204);
+    }
+    if !authorized(request, &config.token) {
+        return Response::error(401, "missing or incorrect token");
+    }
+    let path = request.path.split('?').next().unwrap_or_default();
+    let segments = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<&str>>();
+    let result = match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["characters"]) => {
+            store.list().map(|list| Response::json(200, &list))
+        }
+        ("GET", ["characters", id]) => store.get(id).map(|c| match c {
+            Some(character) => Response::json(200, &character),
+            None => Response::error(404, "no such character"),
+        }),
+        ("PUT", ["characters", id]) => {
+            let put = match serde_json::from_slice::<PutRequest>(&request.body)
+            {
+                Ok(put) => put,
+                Err(e) => return Response::error(400, &e.to_string()),
+            };
+            if !store::valid_id(id) {
+                return Response::error(400, "invalid character id");
+            }
+            store
+                .put(id, put.base_revision, put.document)
+                .map(|result| match result {
+                    PutResult::Saved(character) => {
+                        Response::json(200, &character)
+                    }
+                    PutResult::Conflict(current) => {
+                        Response::json(409, &current)
+                    }
+                })
+        }
+        (_, ["characters"]) | (_, ["characters", _]) => {
+            Ok(Response::error(405, "method not allowed"))
+        }
+        _ => Ok(Response::error(404, "not found")),
+    };
+    result.unwrap_or_else(|e| {
+        eprintln!("Storage error: {}", e);
+        Response::error(500, "could not access storage")
+    })
+}
+
+fn authorized(request: &Request, token: &str) -> bool {
+    let given = request
+        .header("Authorization")
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    // Compare the whole thing every time, so that how long the
+    // comparison takes doesn't give away how much of it matched
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
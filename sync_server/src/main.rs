@@ -0,0 +1,72 @@
+use std::net::TcpListener;
+use std::process::exit;
+use std::sync::Arc;
+
+use goblin_sync::store::Store;
+use goblin_sync::{serve, Config};
+
+const USAGE: &str = "\
+Usage: goblin_sync [options]
+
+Options:
+  --token TOKEN         Token clients must send to use the server. Can also
+                        be set with the GOBLIN_SYNC_TOKEN environment variable.
+  --bind ADDRESS        Address to listen on [default: 127.0.0.1]. Use 0.0.0.0
+                        to accept connections from other devices.
+  --port PORT           Port to listen on [default: 8787]
+  --data DIR            Directory to keep characters in [default: goblin_sync_data]
+  --allow-origin ORIGIN Origin the app is served from [default: *]
+  --help                Show this message";
+
+fn main() {
+    let mut token = std::env::var("GOBLIN_SYNC_TOKEN").ok();
+    let mut bind = "127.0.0.1".to_string();
+    let mut port = "8787".to_string();
+    let mut data = "goblin_sync_data".to_string();
+    let mut allow_origin = "*".to_string();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let target = match arg.as_str() {
+            "--token" => token.insert(String::new()),
+            "--bind" => &mut bind,
+            "--port" => &mut port,
+            "--data" => &mut data,
+            "--allow-origin" => &mut allow_origin,
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ => fail(&format!("Unknown option {}", arg)),
+        };
+        *target = args
+            .next()
+            .unwrap_or_else(|| fail(&format!("{} needs a value", arg)));
+    }
+    let token = match token {
+        Some(token) if !token.is_empty() => token,
+        _ => fail(
+            "A token is required, so that only your group can use the server",
+        ),
+    };
+
+    let store = Store::open(&data)
+        .unwrap_or_else(|e| fail(&format!("Could not open {}: {}", data, e)));
+    let address = format!("{}:{}", bind, port);
+    let listener = TcpListener::bind(&address).unwrap_or_else(|e| {
+        fail(&format!("Could not listen on {}: {}", address, e))
+    });
+    println!("Syncing characters in {} on http://{}", data, address);
+    let config = Config {
+        token,
+        allow_origin,
+    };
+    if let Err(e) = serve(listener, Arc::new(store), Arc::new(config)) {
+        fail(&e.to_string());
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    exit(1)
+}
@@ -0,0 +1,167 @@
+//! Characters stored on disk, one JSON file each.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A character document, as stored on the server. The server doesn't
+/// look inside the document, other than for the character's name.
+/// The app sends documents tagged with their schema version, as
+/// `{"version": n, "data": {...}}`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StoredCharacter {
+    pub id: String,
+    /// Goes up by one every time the character is saved. Clients send
+    /// the revision their changes are based on, so that changes made
+    /// elsewhere in the meantime aren't overwritten without warning.
+    pub revision: u64,
+    /// When the character was last saved, in seconds since the epoch.
+    pub updated_at: u64,
+    pub document: Value,
+}
+
+/// What's listed for each character, without the whole document.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CharacterSummary {
+    pub id: String,
+    pub name: String,
+    pub revision: u64,
+    pub updated_at: u64,
+}
+
+pub enum PutResult {
+    Saved(StoredCharacter),
+    /// The character was saved by someone else since the revision the
+    /// change was based on. Holds the character as it is now.
+    Conflict(StoredCharacter),
+}
+
+pub struct Store {
+    dir: PathBuf,
+    // Held while saving, so that checking the revision and writing
+    // the new one happen together
+    lock: Mutex<()>,
+}
+
+impl Store {
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            lock: Mutex::new(()),
+        })
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    pub fn get(&self, id: &str) -> io::Result<Option<StoredCharacter>> {
+        if !valid_id(id) {
+            return Ok(None);
+        }
+        match fs::read_to_string(self.path(id)) {
+            Ok(data) => Ok(Some(serde_json::from_str(&data)?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn list(&self) -> io::Result<Vec<CharacterSummary>> {
+        let mut summaries = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let id = match path.file_stem().and_then(|s| s.to_str()) {
+                Some(id) if path.extension().is_some_and(|e| e == "json") => {
+                    id.to_string()
+                }
+                _ => continue,
+            };
+            // One file that can't be read shouldn't hide every other
+            // character, so skip it and carry on
+            let character = match self.get(&id) {
+                Ok(Some(character)) => character,
+                Ok(None) => continue,
+                Err(e) => {
+                    eprintln!("Skipping {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            summaries.push(CharacterSummary {
+                name: character_name(&character.document),
+                id: character.id,
+                revision: character.revision,
+                updated_at: character.updated_at,
+            });
+        }
+        summaries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(summaries)
+    }
+
+    /// Save a character, if nobody else has saved it since
+    /// `base_revision`. New characters have a base revision of 0.
+    pub fn put(
+        &self,
+        id: &str,
+        base_revision: u64,
+        document: Value,
+    ) -> io::Result<PutResult> {
+        if !valid_id(id) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid character id",
+            ));
+        }
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        // A character that isn't here is saved whatever its base
+        // revision, in case it was removed from the server by hand
+        let current = self.get(id)?;
+        let current_revision = match current {
+            Some(current) if current.revision != base_revision => {
+                return Ok(PutResult::Conflict(current));
+            }
+            Some(current) => current.revision,
+            None => 0,
+        };
+        let character = StoredCharacter {
+            id: id.to_string(),
+            revision: current_revision + 1,
+            updated_at: now(),
+            document,
+        };
+        // Write to a temporary file first, so that a crash part way
+        // through never leaves a half written character
+        let temp = self.dir.join(format!("{}.json.tmp", id));
+        fs::write(&temp, serde_json::to_string(&character)?)?;
+        fs::rename(&temp, self.path(id))?;
+        Ok(PutResult::Saved(character))
+    }
+}
+
+/// Ids are used as file names, so only allow the characters the app
+/// uses for them (UUIDs).
+pub fn valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 64
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+fn character_name(document: &Value) -> String {
+    document
+        .pointer("/data/details/name")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
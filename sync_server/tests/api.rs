@@ -0,0 +1,179 @@
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use goblin_sync::store::{CharacterSummary, Store, StoredCharacter};
+use goblin_sync::{serve, Config};
+use serde_json::{json, Value};
+
+const TOKEN: &str = "let-me-in";
+
+struct TestServer {
+    address: SocketAddr,
+    data: PathBuf,
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.data);
+    }
+}
+
+/// Start a server on a free port, storing characters in a fresh
+/// temporary directory.
+fn start_server() -> TestServer {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let data = std::env::temp_dir().join(format!(
+        "goblin_sync_test_{}_{}",
+        std::process::id(),
+        COUNT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let store = Arc::new(Store::open(&data).unwrap());
+    let config = Arc::new(Config {
+        token: TOKEN.to_string(),
+        allow_origin: "*".to_string(),
+    });
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || serve(listener, store, config));
+    TestServer { address, data }
+}
+
+/// Make a request, returning the status and the body.
+fn request(
+    server: &TestServer,
+    method: &str,
+    path: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (u16, String) {
+    let body = body.map(|b| b.to_string()).unwrap_or_default();
+    let auth = token
+        .map(|t| format!("Authorization: Bearer {}\r\n", t))
+        .unwrap_or_default();
+    let mut stream = TcpStream::connect(server.address).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\n{}Content-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        auth,
+        body.len(),
+        body
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let status = response[9..12].parse().unwrap();
+    let body = response
+        .split_once("\r\n\r\n")
+        .map(|(_, body)| body.to_string())
+        .unwrap_or_default();
+    (status, body)
+}
+
+fn put(
+    server: &TestServer,
+    id: &str,
+    base_revision: u64,
+    name: &str,
+) -> (u16, StoredCharacter) {
+    let body = json!({
+        "base_revision": base_revision,
+        "document": { "version": 2, "data": { "details": { "name": name } } },
+    });
+    let path = format!("/characters/{}", id);
+    let (status, body) = request(server, "PUT", &path, Some(TOKEN), Some(body));
+    (status, serde_json::from_str(&body).unwrap())
+}
+
+#[test]
+fn requires_the_token() {
+    let server = start_server();
+    assert_eq!(request(&server, "GET", "/characters", None, None).0, 401);
+    assert_eq!(
+        request(&server, "GET", "/characters", Some("let-me-inn"), None).0,
+        401
+    );
+    assert_eq!(
+        request(&server, "GET", "/characters", Some(TOKEN), None).0,
+        200
+    );
+    // Browsers check CORS without the token
+    assert_eq!(
+        request(&server, "OPTIONS", "/characters", None, None).0,
+        204
+    );
+}
+
+#[test]
+fn saves_and_lists_characters() {
+    let server = start_server();
+    let (status, saved) = put(&server, "grog", 0, "Grog");
+    assert_eq!(status, 200);
+    assert_eq!(saved.revision, 1);
+
+    let (status, body) =
+        request(&server, "GET", "/characters/grog", Some(TOKEN), None);
+    assert_eq!(status, 200);
+    let loaded = serde_json::from_str::<StoredCharacter>(&body).unwrap();
+    assert_eq!(loaded, saved);
+
+    let (_, body) = request(&server, "GET", "/characters", Some(TOKEN), None);
+    let list = serde_json::from_str::<Vec<CharacterSummary>>(&body).unwrap();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0].name, "Grog");
+
+    let (status, _) =
+        request(&server, "GET", "/characters/pike", Some(TOKEN), None);
+    assert_eq!(status, 404);
+}
+
+#[test]
+fn lists_characters_around_corrupt_files() {
+    let server = start_server();
+    put(&server, "grog", 0, "Grog");
+    std::fs::write(server.data.join("pike.json"), "not json").unwrap();
+
+    let (status, body) =
+        request(&server, "GET", "/characters", Some(TOKEN), None);
+    assert_eq!(status, 200);
+    let list = serde_json::from_str::<Vec<CharacterSummary>>(&body).unwrap();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0].name, "Grog");
+}
+
+#[test]
+fn detects_conflicting_saves() {
+    let server = start_server();
+    put(&server, "grog", 0, "Grog");
+    // Two devices both make changes to revision 1
+    let (status, saved) = put(&server, "grog", 1, "Grog the Grand");
+    assert_eq!(status, 200);
+    assert_eq!(saved.revision, 2);
+    let (status, current) = put(&server, "grog", 1, "Grog the Great");
+    assert_eq!(status, 409);
+    assert_eq!(current, saved);
+    // Once the second device has seen the latest revision, it can
+    // save over it
+    let (status, saved) = put(&server, "grog", 2, "Grog the Great");
+    assert_eq!(status, 200);
+    assert_eq!(saved.revision, 3);
+}
+
+#[test]
+fn rejects_ids_that_are_not_file_names() {
+    let server = start_server();
+    let body = json!({ "base_revision": 0, "document": {} });
+    let (status, _) = request(
+        &server,
+        "PUT",
+        "/characters/..%2Fescape",
+        Some(TOKEN),
+        Some(body),
+    );
+    assert_eq!(status, 400);
+}